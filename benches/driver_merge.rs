@@ -0,0 +1,112 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use chopper::chopper::driver::ChopperDriver;
+use chopper::chopper::error::ChopperResult;
+use chopper::chopper::header_graph::{HeaderChain, HeaderGraph, HeaderNode};
+use chopper::chopper::sink::{DataSink, DynHeaderSink};
+use chopper::chopper::types::{FieldType, FieldValue, Header, Nanos, Row, TIMESTAMP_RANGE_ALL};
+use chopper::driver::driver::Driver;
+use chopper::driver::merge_join::MergeJoin;
+use chopper::source::source::Source;
+
+const TOTAL_ROWS: usize = 1 << 18;
+
+/// interleaves timestamps across sources, so every row forces a switch of the min source
+struct GenSource {
+    header: Header,
+    next: Nanos,
+    step: Nanos,
+    remaining: usize,
+}
+
+impl Source for GenSource {
+    fn header(&self) -> &Header {
+        &self.header
+    }
+
+    fn next_row(&mut self) -> ChopperResult<Option<Row>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        let timestamp = self.next;
+        self.next += self.step;
+        Ok(Some(Row {
+            timestamp,
+            field_values: vec![FieldValue::Long(timestamp as i64)],
+        }))
+    }
+}
+
+struct CountSink {
+    count: usize,
+}
+
+impl DynHeaderSink for CountSink {
+    fn process_header(self: Box<Self>, _header: &mut Header) -> ChopperResult<Box<dyn DataSink>> {
+        Ok(self)
+    }
+}
+
+impl DataSink for CountSink {
+    fn write_row(&mut self, io_rows: &mut Vec<Row>) -> ChopperResult<()> {
+        self.count += io_rows.len();
+        Ok(())
+    }
+}
+
+fn setup_driver(input_count: usize) -> Box<dyn ChopperDriver> {
+    let header = Header::new(vec!["value".to_string()], vec![FieldType::Long]);
+    let mut sources: Vec<Box<dyn Source>> = Vec::new();
+    let mut headers: Vec<Header> = Vec::new();
+    let mut chains: Vec<HeaderChain> = Vec::new();
+    for i in 0..input_count {
+        sources.push(Box::new(GenSource {
+            header: header.clone(),
+            next: i as Nanos,
+            step: input_count as Nanos,
+            remaining: TOTAL_ROWS / input_count,
+        }));
+        headers.push(header.clone());
+        chains.push(HeaderChain::new(vec![HeaderNode::Merge(input_count)]));
+    }
+
+    let merge = MergeJoin::new(input_count).unwrap();
+    let header_count_tracker = merge.get_new_header_count_tracker();
+    let sink: Box<dyn DynHeaderSink> = Box::new(CountSink { count: 0 });
+    chains.push(HeaderChain::new(vec![
+        HeaderNode::MergeHeaderSink(merge, header_count_tracker),
+        HeaderNode::HeaderSink(sink),
+    ]));
+
+    Box::new(
+        Driver::new(
+            sources,
+            HeaderGraph::new(chains),
+            TIMESTAMP_RANGE_ALL,
+            headers,
+        )
+        .unwrap(),
+    )
+}
+
+fn bench_merge(c: &mut Criterion) {
+    let mut group = c.benchmark_group("driver_merge");
+    group.throughput(Throughput::Elements(TOTAL_ROWS as u64));
+    for input_count in [2, 64, 1024].iter() {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(input_count),
+            input_count,
+            |b, &input_count| {
+                b.iter_with_setup(
+                    || setup_driver(input_count),
+                    |mut driver| driver.drive().unwrap(),
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_merge);
+criterion_main!(benches);
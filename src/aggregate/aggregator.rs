@@ -0,0 +1,266 @@
+use std::cmp::Ordering;
+use std::mem;
+use std::str::FromStr;
+
+use crate::chopper::error::{ChopperResult, Error};
+use crate::chopper::types::{FieldType, FieldValue, Header};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Aggregate {
+    First,
+    Last,
+    Min,
+    Max,
+    /// long for integer columns and double for floating point ones
+    Sum,
+    /// number of values that are not null
+    Count,
+    Mean,
+    /// open, high, low and close as four columns
+    Ohlc,
+}
+
+impl FromStr for Aggregate {
+    type Err = Error;
+
+    fn from_str(aggregate: &str) -> ChopperResult<Aggregate> {
+        match aggregate {
+            "first" => Ok(Aggregate::First),
+            "last" => Ok(Aggregate::Last),
+            "min" => Ok(Aggregate::Min),
+            "max" => Ok(Aggregate::Max),
+            "sum" => Ok(Aggregate::Sum),
+            "count" => Ok(Aggregate::Count),
+            "mean" => Ok(Aggregate::Mean),
+            "ohlc" => Ok(Aggregate::Ohlc),
+            _ => Err(Error::from(format!(
+                "Aggregate -- {} is not a valid aggregate",
+                aggregate
+            ))),
+        }
+    }
+}
+
+pub(crate) fn is_integer(field_type: FieldType) -> bool {
+    matches!(
+        field_type,
+        FieldType::Byte | FieldType::Short | FieldType::Int | FieldType::Long
+    )
+}
+
+pub(crate) fn is_floating_point(field_type: FieldType) -> bool {
+    matches!(field_type, FieldType::Float | FieldType::Double)
+}
+
+fn to_long(value: &FieldValue) -> i64 {
+    match value {
+        FieldValue::Byte(v) => *v as i64,
+        FieldValue::Short(v) => *v as i64,
+        FieldValue::Int(v) => *v as i64,
+        FieldValue::Long(v) => *v,
+        _ => unreachable!(),
+    }
+}
+
+pub(crate) fn to_double(value: &FieldValue) -> f64 {
+    match value {
+        FieldValue::Float(v) => *v as f64,
+        FieldValue::Double(v) => *v,
+        _ => to_long(value) as f64,
+    }
+}
+
+/// state of one aggregate of one column within the current bucket
+#[derive(Clone)]
+pub(crate) struct ColumnAggregator {
+    name: String,
+    pub(crate) index: usize,
+    aggregate: Aggregate,
+    integer: bool,
+    first: FieldValue,
+    last: FieldValue,
+    min: FieldValue,
+    max: FieldValue,
+    long_sum: i64,
+    double_sum: f64,
+    count: i64,
+    /// last value of the previous buckets, only kept when needed to fill empty buckets
+    previous: Option<FieldValue>,
+}
+
+impl ColumnAggregator {
+    pub(crate) fn new(
+        name: String,
+        aggregate: Aggregate,
+        header: &Header,
+        keeps_previous: bool,
+    ) -> ChopperResult<Self> {
+        let index = header.get_field_index(&name)?;
+        let field_type = header.field_types()[index];
+        let supported = match aggregate {
+            Aggregate::First | Aggregate::Last | Aggregate::Count => true,
+            Aggregate::Min | Aggregate::Max | Aggregate::Ohlc => {
+                field_type != FieldType::MultiDimDoubleArray
+            }
+            Aggregate::Sum | Aggregate::Mean => {
+                is_integer(field_type) || is_floating_point(field_type)
+            }
+        };
+        if !supported {
+            return Err(Error::from(format!(
+                "Aggregate -- {:?} aggregate is not supported for column {} of type {:?}",
+                aggregate, name, field_type
+            )));
+        }
+        let keeps_previous = keeps_previous
+            && !matches!(
+                aggregate,
+                Aggregate::Sum | Aggregate::Count | Aggregate::Mean
+            );
+        Ok(ColumnAggregator {
+            name,
+            index,
+            aggregate,
+            integer: is_integer(field_type),
+            first: FieldValue::None,
+            last: FieldValue::None,
+            min: FieldValue::None,
+            max: FieldValue::None,
+            long_sum: 0,
+            double_sum: 0.0,
+            count: 0,
+            previous: match keeps_previous {
+                true => Some(FieldValue::None),
+                false => None,
+            },
+        })
+    }
+
+    pub(crate) fn output_fields(&self, field_type: FieldType) -> Vec<(String, FieldType)> {
+        let field =
+            |suffix: &str, field_type: FieldType| (format!("{}_{}", self.name, suffix), field_type);
+        match self.aggregate {
+            Aggregate::First => vec![field("first", field_type)],
+            Aggregate::Last => vec![field("last", field_type)],
+            Aggregate::Min => vec![field("min", field_type)],
+            Aggregate::Max => vec![field("max", field_type)],
+            Aggregate::Sum if self.integer => vec![field("sum", FieldType::Long)],
+            Aggregate::Sum => vec![field("sum", FieldType::Double)],
+            Aggregate::Count => vec![field("count", FieldType::Long)],
+            Aggregate::Mean => vec![field("mean", FieldType::Double)],
+            Aggregate::Ohlc => vec![
+                field("open", field_type),
+                field("high", field_type),
+                field("low", field_type),
+                field("close", field_type),
+            ],
+        }
+    }
+
+    /// nulls are ignored by all aggregates
+    pub(crate) fn update(&mut self, value: &FieldValue) -> ChopperResult<()> {
+        if value == &FieldValue::None {
+            return Ok(());
+        }
+        self.count += 1;
+        match self.aggregate {
+            Aggregate::First | Aggregate::Ohlc if self.first == FieldValue::None => {
+                self.first = value.clone()
+            }
+            Aggregate::Sum if self.integer => {
+                self.long_sum = match self.long_sum.checked_add(to_long(value)) {
+                    Some(sum) => sum,
+                    None => {
+                        return Err(Error::from(format!(
+                            "Aggregate -- sum of column {} overflows",
+                            self.name
+                        )))
+                    }
+                }
+            }
+            Aggregate::Sum | Aggregate::Mean => self.double_sum += to_double(value),
+            _ => {}
+        }
+        if matches!(self.aggregate, Aggregate::Min | Aggregate::Ohlc)
+            && (self.min == FieldValue::None
+                || value.partial_cmp(&self.min) == Some(Ordering::Less))
+        {
+            self.min = value.clone();
+        }
+        if matches!(self.aggregate, Aggregate::Max | Aggregate::Ohlc)
+            && (self.max == FieldValue::None
+                || value.partial_cmp(&self.max) == Some(Ordering::Greater))
+        {
+            self.max = value.clone();
+        }
+        if matches!(self.aggregate, Aggregate::Last | Aggregate::Ohlc) || self.previous.is_some() {
+            self.last = value.clone();
+        }
+        Ok(())
+    }
+
+    /// appends values of the current bucket and starts a new one
+    pub(crate) fn output(&mut self, field_values: &mut Vec<FieldValue>) {
+        let first = mem::replace(&mut self.first, FieldValue::None);
+        let last = mem::replace(&mut self.last, FieldValue::None);
+        let min = mem::replace(&mut self.min, FieldValue::None);
+        let max = mem::replace(&mut self.max, FieldValue::None);
+        let count = mem::replace(&mut self.count, 0);
+        let long_sum = mem::replace(&mut self.long_sum, 0);
+        let double_sum = mem::replace(&mut self.double_sum, 0.0);
+        if let Some(previous) = &mut self.previous {
+            if last != FieldValue::None {
+                *previous = last.clone();
+            }
+        }
+        match self.aggregate {
+            Aggregate::First => field_values.push(first),
+            Aggregate::Last => field_values.push(last),
+            Aggregate::Min => field_values.push(min),
+            Aggregate::Max => field_values.push(max),
+            Aggregate::Sum if self.integer => field_values.push(FieldValue::Long(long_sum)),
+            Aggregate::Sum => field_values.push(FieldValue::Double(double_sum)),
+            Aggregate::Count => field_values.push(FieldValue::Long(count)),
+            Aggregate::Mean => field_values.push(match count {
+                0 => FieldValue::None,
+                _ => FieldValue::Double(double_sum / count as f64),
+            }),
+            Aggregate::Ohlc => field_values.extend([first, max, min, last]),
+        }
+    }
+
+    /// appends values for a bucket that has no rows
+    pub(crate) fn output_empty(&self, field_values: &mut Vec<FieldValue>) {
+        let value = match &self.previous {
+            Some(previous) => previous.clone(),
+            None => FieldValue::None,
+        };
+        match self.aggregate {
+            Aggregate::Sum if self.integer => field_values.push(FieldValue::Long(0)),
+            Aggregate::Sum => field_values.push(FieldValue::Double(0.0)),
+            Aggregate::Count => field_values.push(FieldValue::Long(0)),
+            Aggregate::Mean => field_values.push(FieldValue::None),
+            Aggregate::Ohlc => field_values.extend(vec![value; 4]),
+            _ => field_values.push(value),
+        }
+    }
+}
+
+pub(crate) fn parse_aggregates(aggregates: &str) -> ChopperResult<Vec<(String, Aggregate)>> {
+    let mut parsed: Vec<(String, Aggregate)> = Vec::new();
+    for item in aggregates.split(',') {
+        // column names can contain ':', aggregate names can't
+        match item.trim().rsplit_once(':') {
+            Some((name, aggregate)) if !name.is_empty() => {
+                parsed.push((name.to_string(), aggregate.parse::<Aggregate>()?))
+            }
+            _ => {
+                return Err(Error::from(format!(
+                    "Aggregate -- aggregate [{}] is not in column:aggregate form",
+                    item
+                )))
+            }
+        }
+    }
+    Ok(parsed)
+}
@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+
+use crate::aggregate::aggregator::{parse_aggregates, Aggregate, ColumnAggregator};
+use crate::chopper::error::{ChopperResult, Error};
+use crate::chopper::sink::{DataSink, DynHeaderSink};
+use crate::chopper::types::{FieldType, FieldValue, HashableFieldValue, Header, Nanos, Row};
+use crate::util::interval::Interval;
+use crate::util::tz::ChopperTz;
+
+pub struct GroupByConfig {
+    keys: Vec<String>,
+    aggregates: Vec<(String, Aggregate)>,
+    interval: Option<Interval>,
+    timezone: ChopperTz,
+}
+
+/// aggregates of one key within the current period
+struct GroupState {
+    /// order in which keys were first seen, for rows with equal timestamps
+    sequence: usize,
+    timestamp: Nanos,
+    aggregators: Vec<ColumnAggregator>,
+}
+
+pub struct GroupBy {
+    key_indexes: Vec<usize>,
+    interval: Option<Interval>,
+    timezone: ChopperTz,
+    /// aggregators in the initial state, cloned for each new key
+    aggregators: Vec<ColumnAggregator>,
+    groups: HashMap<Vec<HashableFieldValue>, GroupState>,
+    /// end of the current period, if output is periodic
+    period_end: Option<Nanos>,
+}
+
+impl GroupBy {
+    /// outputs one row per distinct combination of key column values, with the key columns
+    /// followed by the aggregates; keys is a comma-separated list of column names and
+    /// aggregates is a comma-separated list of column:aggregate pairs, where aggregate is one of
+    /// first, last, min, max, sum, count, mean and ohlc; rows are output at the end, or, if
+    /// interval is given, whenever a period of that length ends, in which case aggregation
+    /// starts over for the next period; each output row has the timestamp of the last row of
+    /// its key, and rows are output in timestamp order; timezone is only needed for day
+    /// intervals
+    pub fn new(
+        keys: &str,
+        aggregates: &str,
+        interval: Option<&str>,
+        timezone: ChopperTz,
+    ) -> ChopperResult<Box<dyn DynHeaderSink>> {
+        let keys: Vec<String> = keys.split(',').map(|key| key.trim().to_string()).collect();
+        if keys.iter().any(|key| key.is_empty()) {
+            return Err(Error::from("GroupBy -- empty key column name"));
+        }
+        let interval = match interval {
+            None => None,
+            Some(interval) => Some(interval.parse::<Interval>()?),
+        };
+        let config = GroupByConfig {
+            keys,
+            aggregates: parse_aggregates(aggregates)?,
+            interval,
+            timezone,
+        };
+        Ok(Box::new(config) as Box<dyn DynHeaderSink>)
+    }
+
+    fn output_groups(&mut self, io_rows: &mut Vec<Row>) {
+        let mut groups: Vec<(Vec<HashableFieldValue>, GroupState)> = self.groups.drain().collect();
+        groups.sort_by_key(|(_, state)| (state.timestamp, state.sequence));
+        for (key, mut state) in groups {
+            let mut field_values: Vec<FieldValue> = key.into_iter().map(|k| k.0).collect();
+            for aggregator in &mut state.aggregators {
+                aggregator.output(&mut field_values);
+            }
+            io_rows.push(Row {
+                timestamp: state.timestamp,
+                field_values,
+            });
+        }
+    }
+}
+
+impl DynHeaderSink for GroupByConfig {
+    fn process_header(self: Box<Self>, header: &mut Header) -> ChopperResult<Box<dyn DataSink>> {
+        let mut key_indexes: Vec<usize> = Vec::new();
+        let mut field_names: Vec<String> = Vec::new();
+        let mut field_types: Vec<FieldType> = Vec::new();
+        for key in self.keys {
+            let index = header.get_field_index(&key)?;
+            key_indexes.push(index);
+            field_names.push(key);
+            field_types.push(header.field_types()[index]);
+        }
+        let mut aggregators: Vec<ColumnAggregator> = Vec::new();
+        for (name, aggregate) in self.aggregates {
+            let aggregator = ColumnAggregator::new(name, aggregate, header, false)?;
+            let field_type = header.field_types()[aggregator.index];
+            for (field_name, field_type) in aggregator.output_fields(field_type) {
+                field_names.push(field_name);
+                field_types.push(field_type);
+            }
+            aggregators.push(aggregator);
+        }
+        header.update_field_names(field_names);
+        header.update_field_types(field_types);
+
+        Ok(Box::new(GroupBy {
+            key_indexes,
+            interval: self.interval,
+            timezone: self.timezone,
+            aggregators,
+            groups: HashMap::new(),
+            period_end: None,
+        }))
+    }
+}
+
+impl DataSink for GroupBy {
+    fn write_row(&mut self, io_rows: &mut Vec<Row>) -> ChopperResult<()> {
+        let row = io_rows.pop().unwrap();
+        if let Some(interval) = self.interval {
+            match self.period_end {
+                Some(end) if row.timestamp < end => {}
+                _ => {
+                    self.output_groups(io_rows);
+                    let start = interval.bucket_start(row.timestamp, &self.timezone)?;
+                    self.period_end = Some(interval.next_bucket_start(start, &self.timezone)?);
+                }
+            }
+        }
+
+        let key: Vec<HashableFieldValue> = self
+            .key_indexes
+            .iter()
+            .map(|&i| HashableFieldValue(row.field_values[i].clone()))
+            .collect();
+        let sequence = self.groups.len();
+        let aggregators = &self.aggregators;
+        let state = self.groups.entry(key).or_insert_with(|| GroupState {
+            sequence,
+            timestamp: row.timestamp,
+            aggregators: aggregators.clone(),
+        });
+        state.timestamp = row.timestamp;
+        for aggregator in &mut state.aggregators {
+            aggregator.update(&row.field_values[aggregator.index])?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self, io_rows: &mut Vec<Row>) -> ChopperResult<()> {
+        self.output_groups(io_rows);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::aggregate::group_by::GroupBy;
+    use crate::chopper::types::{FieldType, FieldValue, HashableFieldValue, Header, Nanos, Row};
+    use crate::util::tz::ChopperTz;
+
+    const SECOND: Nanos = 1_000_000_000;
+
+    fn group_by(
+        keys: &str,
+        aggregates: &str,
+        interval: Option<&str>,
+        rows: Vec<(Nanos, &str, f64, i64)>,
+    ) -> (Header, Vec<Row>) {
+        let mut header = Header::new(
+            vec!["sym".to_string(), "px".to_string(), "qty".to_string()],
+            vec![FieldType::String, FieldType::Double, FieldType::Long],
+        );
+        let group_by =
+            GroupBy::new(keys, aggregates, interval, ChopperTz::new_always_fails()).unwrap();
+        let mut sink = group_by.process_header(&mut header).unwrap();
+
+        let mut output: Vec<Row> = Vec::new();
+        for (timestamp, sym, px, qty) in rows {
+            let mut io_rows = vec![Row {
+                timestamp,
+                field_values: vec![
+                    FieldValue::String(sym.to_string()),
+                    FieldValue::Double(px),
+                    FieldValue::Long(qty),
+                ],
+            }];
+            sink.write_row(&mut io_rows).unwrap();
+            output.append(&mut io_rows);
+        }
+        let mut io_rows: Vec<Row> = Vec::new();
+        sink.flush(&mut io_rows).unwrap();
+        output.append(&mut io_rows);
+        (header, output)
+    }
+
+    fn rows() -> Vec<(Nanos, &'static str, f64, i64)> {
+        vec![
+            (SECOND, "A", 10.0, 1),
+            (2 * SECOND, "B", 20.0, 2),
+            (3 * SECOND, "A", 12.0, 3),
+            (4 * SECOND, "C", f64::NAN, 4),
+            (12 * SECOND, "B", 21.0, 5),
+        ]
+    }
+
+    #[test]
+    fn test_group_by() {
+        let (header, output) = group_by("sym", "qty:sum,px:max,qty:count", None, rows());
+        assert_eq!(
+            header.field_names(),
+            &vec!["sym", "qty_sum", "px_max", "qty_count"]
+        );
+        // compared as hashable values, so that NaN equals NaN
+        let hashable = |rows: Vec<(Nanos, Vec<FieldValue>)>| {
+            rows.into_iter()
+                .map(|(timestamp, values)| {
+                    let values: Vec<HashableFieldValue> =
+                        values.into_iter().map(HashableFieldValue).collect();
+                    (timestamp, values)
+                })
+                .collect::<Vec<(Nanos, Vec<HashableFieldValue>)>>()
+        };
+        let output: Vec<(Nanos, Vec<FieldValue>)> = output
+            .into_iter()
+            .map(|row| (row.timestamp, row.field_values))
+            .collect();
+        assert_eq!(
+            hashable(output),
+            hashable(vec![
+                (
+                    3 * SECOND,
+                    vec![
+                        FieldValue::String("A".to_string()),
+                        FieldValue::Long(4),
+                        FieldValue::Double(12.0),
+                        FieldValue::Long(2),
+                    ]
+                ),
+                (
+                    4 * SECOND,
+                    vec![
+                        FieldValue::String("C".to_string()),
+                        FieldValue::Long(4),
+                        FieldValue::Double(f64::NAN),
+                        FieldValue::Long(1),
+                    ]
+                ),
+                (
+                    12 * SECOND,
+                    vec![
+                        FieldValue::String("B".to_string()),
+                        FieldValue::Long(7),
+                        FieldValue::Double(21.0),
+                        FieldValue::Long(2),
+                    ]
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_group_by_interval() {
+        let (_, output) = group_by("sym", "qty:last", Some("10s"), rows());
+        let timestamps: Vec<Nanos> = output.iter().map(|row| row.timestamp).collect();
+        assert_eq!(
+            timestamps,
+            vec![2 * SECOND, 3 * SECOND, 4 * SECOND, 12 * SECOND]
+        );
+        assert_eq!(output[0].field_values[1], FieldValue::Long(2));
+    }
+
+    #[test]
+    fn test_group_by_float_key() {
+        let (_, output) = group_by(
+            "px",
+            "qty:count",
+            None,
+            vec![
+                (SECOND, "A", f64::NAN, 1),
+                (2 * SECOND, "A", 0.0, 1),
+                (3 * SECOND, "A", -f64::NAN, 1),
+                (4 * SECOND, "A", -0.0, 1),
+            ],
+        );
+        assert_eq!(output.len(), 2);
+        assert_eq!(output[0].field_values[1], FieldValue::Long(2));
+        assert_eq!(output[1].field_values[1], FieldValue::Long(2));
+    }
+}
@@ -0,0 +1,4 @@
+pub mod aggregator;
+pub mod group_by;
+pub mod resample;
+pub mod rolling;
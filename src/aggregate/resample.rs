@@ -0,0 +1,280 @@
+use std::str::FromStr;
+
+use crate::aggregate::aggregator::{parse_aggregates, Aggregate, ColumnAggregator};
+use crate::chopper::error::{ChopperResult, Error};
+use crate::chopper::sink::{DataSink, DynHeaderSink};
+use crate::chopper::types::{FieldType, FieldValue, Header, Nanos, Row};
+use crate::util::interval::Interval;
+use crate::util::tz::ChopperTz;
+
+/// what to output for buckets that have no rows
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FillPolicy {
+    /// output nothing
+    #[default]
+    Skip,
+    /// output a row of nulls, except for sum and count that are 0
+    Null,
+    /// output a row where first, last, min, max and ohlc carry the last value of the previous
+    /// buckets, sum and count are 0 and mean is null
+    Previous,
+}
+
+impl FromStr for FillPolicy {
+    type Err = Error;
+
+    fn from_str(policy: &str) -> ChopperResult<FillPolicy> {
+        match policy {
+            "none" => Ok(FillPolicy::Skip),
+            "null" => Ok(FillPolicy::Null),
+            "previous" => Ok(FillPolicy::Previous),
+            _ => Err(Error::from(format!(
+                "Resample -- {} is not a valid fill policy",
+                policy
+            ))),
+        }
+    }
+}
+
+pub struct ResampleConfig {
+    interval: Interval,
+    aggregates: Vec<(String, Aggregate)>,
+    fill_policy: FillPolicy,
+    timezone: ChopperTz,
+}
+
+pub struct Resample {
+    interval: Interval,
+    fill_policy: FillPolicy,
+    timezone: ChopperTz,
+    aggregators: Vec<ColumnAggregator>,
+    /// start and end of the bucket that is being aggregated
+    bucket: Option<(Nanos, Nanos)>,
+}
+
+impl Resample {
+    /// outputs one row per time bucket, with the bucket start as its timestamp, and only
+    /// the aggregated columns; interval is e.g. 500ms, 5m or 1d; aggregates is
+    /// a comma-separated list of column:aggregate pairs, where aggregate is one of first,
+    /// last, min, max, sum, count, mean and ohlc, and output columns are named
+    /// column_aggregate, or column_open, column_high, column_low and column_close for ohlc;
+    /// timezone is only needed for day intervals
+    pub fn new(
+        interval: &str,
+        aggregates: &str,
+        fill_policy: FillPolicy,
+        timezone: ChopperTz,
+    ) -> ChopperResult<Box<dyn DynHeaderSink>> {
+        let config = ResampleConfig {
+            interval: interval.parse::<Interval>()?,
+            aggregates: parse_aggregates(aggregates)?,
+            fill_policy,
+            timezone,
+        };
+        Ok(Box::new(config) as Box<dyn DynHeaderSink>)
+    }
+
+    fn output_bucket(&mut self, bucket_start: Nanos, empty: bool, io_rows: &mut Vec<Row>) {
+        let mut field_values: Vec<FieldValue> = Vec::new();
+        for aggregator in &mut self.aggregators {
+            match empty {
+                true => aggregator.output_empty(&mut field_values),
+                false => aggregator.output(&mut field_values),
+            }
+        }
+        io_rows.push(Row {
+            timestamp: bucket_start,
+            field_values,
+        });
+    }
+}
+
+impl DynHeaderSink for ResampleConfig {
+    fn process_header(self: Box<Self>, header: &mut Header) -> ChopperResult<Box<dyn DataSink>> {
+        let mut aggregators: Vec<ColumnAggregator> = Vec::new();
+        let mut field_names: Vec<String> = Vec::new();
+        let mut field_types: Vec<FieldType> = Vec::new();
+        for (name, aggregate) in self.aggregates {
+            let aggregator = ColumnAggregator::new(
+                name,
+                aggregate,
+                header,
+                self.fill_policy == FillPolicy::Previous,
+            )?;
+            let field_type = header.field_types()[aggregator.index];
+            for (field_name, field_type) in aggregator.output_fields(field_type) {
+                field_names.push(field_name);
+                field_types.push(field_type);
+            }
+            aggregators.push(aggregator);
+        }
+        header.update_field_names(field_names);
+        header.update_field_types(field_types);
+
+        Ok(Box::new(Resample {
+            interval: self.interval,
+            fill_policy: self.fill_policy,
+            timezone: self.timezone,
+            aggregators,
+            bucket: None,
+        }))
+    }
+}
+
+impl DataSink for Resample {
+    fn write_row(&mut self, io_rows: &mut Vec<Row>) -> ChopperResult<()> {
+        let row = io_rows.pop().unwrap();
+        match self.bucket {
+            Some((start, _)) if row.timestamp < start => {
+                return Err(Error::from(format!(
+                    "Resample -- row timestamp {} is earlier than current bucket start {}",
+                    row.timestamp, start
+                )))
+            }
+            Some((_, end)) if row.timestamp < end => {}
+            Some((start, mut end)) => {
+                self.output_bucket(start, false, io_rows);
+                let next_start = self.interval.bucket_start(row.timestamp, &self.timezone)?;
+                if self.fill_policy != FillPolicy::Skip {
+                    while end < next_start {
+                        self.output_bucket(end, true, io_rows);
+                        end = self.interval.next_bucket_start(end, &self.timezone)?;
+                    }
+                }
+                let next_end = self
+                    .interval
+                    .next_bucket_start(next_start, &self.timezone)?;
+                self.bucket = Some((next_start, next_end));
+            }
+            None => {
+                let start = self.interval.bucket_start(row.timestamp, &self.timezone)?;
+                let end = self.interval.next_bucket_start(start, &self.timezone)?;
+                self.bucket = Some((start, end));
+            }
+        }
+        for aggregator in &mut self.aggregators {
+            aggregator.update(&row.field_values[aggregator.index])?;
+        }
+        Ok(())
+    }
+
+    /// last bucket is output even if it is not complete
+    fn flush(&mut self, io_rows: &mut Vec<Row>) -> ChopperResult<()> {
+        if let Some((start, _)) = self.bucket.take() {
+            self.output_bucket(start, false, io_rows);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::aggregate::resample::{FillPolicy, Resample};
+    use crate::chopper::types::{FieldType, FieldValue, Header, Nanos, Row};
+    use crate::util::tz::ChopperTz;
+
+    const SECOND: Nanos = 1_000_000_000;
+
+    fn resample(
+        interval: &str,
+        aggregates: &str,
+        fill_policy: FillPolicy,
+        rows: Vec<(Nanos, f64, i32)>,
+    ) -> (Header, Vec<Row>) {
+        let mut header = Header::new(
+            vec!["px".to_string(), "qty".to_string()],
+            vec![FieldType::Double, FieldType::Int],
+        );
+        let timezone = ChopperTz::new_from_str("America/New_York", None).unwrap();
+        let resample = Resample::new(interval, aggregates, fill_policy, timezone).unwrap();
+        let mut sink = resample.process_header(&mut header).unwrap();
+
+        let mut output: Vec<Row> = Vec::new();
+        for (timestamp, px, qty) in rows {
+            let mut io_rows = vec![Row {
+                timestamp,
+                field_values: vec![FieldValue::Double(px), FieldValue::Int(qty)],
+            }];
+            sink.write_row(&mut io_rows).unwrap();
+            output.append(&mut io_rows);
+        }
+        let mut io_rows: Vec<Row> = Vec::new();
+        sink.flush(&mut io_rows).unwrap();
+        output.append(&mut io_rows);
+        (header, output)
+    }
+
+    #[test]
+    fn test_resample() {
+        let rows = vec![
+            (10 * SECOND, 2.0, 5),
+            (20 * SECOND, 3.5, 1),
+            (50 * SECOND, 1.5, 2),
+            (70 * SECOND, 2.5, 4),
+            (200 * SECOND, 4.0, 3),
+        ];
+        let (header, output) = resample(
+            "1m",
+            "px:ohlc,qty:sum,qty:count,px:mean",
+            FillPolicy::Skip,
+            rows.clone(),
+        );
+        assert_eq!(
+            header.field_names(),
+            &vec![
+                "px_open",
+                "px_high",
+                "px_low",
+                "px_close",
+                "qty_sum",
+                "qty_count",
+                "px_mean"
+            ]
+        );
+        assert_eq!(header.field_types()[4], FieldType::Long);
+        assert_eq!(output.len(), 3);
+        assert_eq!(output[0].timestamp, 0);
+        assert_eq!(
+            output[0].field_values,
+            vec![
+                FieldValue::Double(2.0),
+                FieldValue::Double(3.5),
+                FieldValue::Double(1.5),
+                FieldValue::Double(1.5),
+                FieldValue::Long(8),
+                FieldValue::Long(3),
+                FieldValue::Double(7.0 / 3.0),
+            ]
+        );
+        assert_eq!(output[1].timestamp, 60 * SECOND);
+        assert_eq!(output[2].timestamp, 180 * SECOND);
+
+        let (_, output) = resample("1m", "px:last,qty:sum", FillPolicy::Previous, rows.clone());
+        assert_eq!(output.len(), 4);
+        assert_eq!(output[2].timestamp, 120 * SECOND);
+        assert_eq!(
+            output[2].field_values,
+            vec![FieldValue::Double(2.5), FieldValue::Long(0)]
+        );
+
+        let (_, output) = resample("1m", "px:first", FillPolicy::Null, rows);
+        assert_eq!(output[2].field_values, vec![FieldValue::None]);
+    }
+
+    #[test]
+    fn test_resample_days() {
+        // 2020-03-07 23:00 and 2020-03-08 23:00 in new york, around daylight saving time change
+        let rows = vec![(1583640000 * SECOND, 1.0, 1), (1583722800 * SECOND, 2.0, 1)];
+        let (_, output) = resample("1d", "qty:sum", FillPolicy::Skip, rows);
+        // local midnights
+        assert_eq!(output[0].timestamp, 1583557200 * SECOND);
+        assert_eq!(output[1].timestamp, 1583643600 * SECOND);
+    }
+
+    #[test]
+    fn test_resample_errors() {
+        let timezone = ChopperTz::new_always_fails();
+        assert!(Resample::new("1s", "px", FillPolicy::Skip, timezone.clone()).is_err());
+        assert!(Resample::new("1y", "px:sum", FillPolicy::Skip, timezone).is_err());
+    }
+}
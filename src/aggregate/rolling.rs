@@ -0,0 +1,580 @@
+use std::collections::{HashMap, VecDeque};
+use std::str::FromStr;
+
+use crate::aggregate::aggregator::{is_floating_point, is_integer, to_double};
+use crate::chopper::error::{ChopperResult, Error};
+use crate::chopper::sink::{DataSink, DynHeaderSink};
+use crate::chopper::types::{FieldType, FieldValue, HashableFieldValue, Header, Nanos, Row};
+use crate::util::interval::Interval;
+
+/// rows that rolling statistics are computed over
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Window {
+    /// last given number of rows, including the current one
+    Rows(usize),
+    /// rows with timestamps less than given number of nanos before that of the current row
+    Time(Nanos),
+}
+
+impl FromStr for Window {
+    type Err = Error;
+
+    /// plain number is a row count, number with units is a time interval, e.g. 500ms or 5m
+    fn from_str(window: &str) -> ChopperResult<Window> {
+        if let Ok(rows) = window.parse::<usize>() {
+            return match rows {
+                0 => Err(Error::from(
+                    "Rolling -- window has to have at least one row",
+                )),
+                _ => Ok(Window::Rows(rows)),
+            };
+        }
+        match window.parse::<Interval>()? {
+            Interval::Nanos(nanos) => Ok(Window::Time(nanos)),
+            Interval::Days(days) => Ok(Window::Time(days as Nanos * 86_400_000_000_000)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RollingStat {
+    Mean,
+    Sum,
+    Min,
+    Max,
+    /// sample standard deviation
+    StdDev,
+    /// exponentially weighted mean; for a window of n rows, weight of the current value is
+    /// 2 / (n + 1), and for a time window, weight of the previous mean decays by e every
+    /// window length
+    Ewma,
+    /// number of values that are not null
+    Count,
+}
+
+impl FromStr for RollingStat {
+    type Err = Error;
+
+    fn from_str(stat: &str) -> ChopperResult<RollingStat> {
+        match stat {
+            "mean" => Ok(RollingStat::Mean),
+            "sum" => Ok(RollingStat::Sum),
+            "min" => Ok(RollingStat::Min),
+            "max" => Ok(RollingStat::Max),
+            "stddev" => Ok(RollingStat::StdDev),
+            "ewma" => Ok(RollingStat::Ewma),
+            "count" => Ok(RollingStat::Count),
+            _ => Err(Error::from(format!(
+                "Rolling -- {} is not a valid statistic",
+                stat
+            ))),
+        }
+    }
+}
+
+impl RollingStat {
+    fn name(&self) -> &'static str {
+        match self {
+            RollingStat::Mean => "mean",
+            RollingStat::Sum => "sum",
+            RollingStat::Min => "min",
+            RollingStat::Max => "max",
+            RollingStat::StdDev => "stddev",
+            RollingStat::Ewma => "ewma",
+            RollingStat::Count => "count",
+        }
+    }
+}
+
+/// one value in a window; nulls take up a row but are not part of the statistics
+struct WindowEntry {
+    sequence: u64,
+    timestamp: Nanos,
+    value: Option<f64>,
+}
+
+/// sum and variance of the finite values in a window, with values both added and removed;
+/// sum is compensated and variance uses welford's algorithm, so that rounding errors don't pile
+/// up on long streams
+#[derive(Default)]
+struct FiniteStats {
+    count: i64,
+    sum: f64,
+    compensation: f64,
+    mean: f64,
+    m2: f64,
+}
+
+impl FiniteStats {
+    fn add(&mut self, value: f64) {
+        self.count += 1;
+        self.add_to_sum(value);
+        let delta = value - self.mean;
+        self.mean = self.sum() / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    fn remove(&mut self, value: f64) {
+        self.count -= 1;
+        if self.count == 0 {
+            *self = FiniteStats::default();
+            return;
+        }
+        self.add_to_sum(-value);
+        let delta = value - self.mean;
+        self.mean = self.sum() / self.count as f64;
+        self.m2 -= delta * (value - self.mean);
+    }
+
+    /// neumaier summation
+    fn add_to_sum(&mut self, value: f64) {
+        let sum = self.sum + value;
+        if self.sum.abs() >= value.abs() {
+            self.compensation += (self.sum - sum) + value;
+        } else {
+            self.compensation += (value - sum) + self.sum;
+        }
+        self.sum = sum;
+    }
+
+    fn sum(&self) -> f64 {
+        self.sum + self.compensation
+    }
+
+    fn variance(&self) -> f64 {
+        (self.m2 / (self.count - 1) as f64).max(0.0)
+    }
+}
+
+/// window of one column for one key; min and max are kept in monotonic queues, so that every
+/// statistic is updated in constant amortized time; NaN and infinite values are counted apart
+/// from finite ones, so that they stop affecting statistics once they leave the window
+#[derive(Default)]
+struct WindowState {
+    entries: VecDeque<WindowEntry>,
+    sequence: u64,
+    count: i64,
+    finite: FiniteStats,
+    nan_count: i64,
+    positive_infinity_count: i64,
+    negative_infinity_count: i64,
+    /// increasing values, of which the front is the minimum
+    min_queue: VecDeque<(u64, f64)>,
+    /// decreasing values, of which the front is the maximum
+    max_queue: VecDeque<(u64, f64)>,
+    /// timestamp of the last value and the mean; non-finite values are left out, since they
+    /// would stick forever
+    ewma: Option<(Nanos, f64)>,
+}
+
+impl WindowState {
+    fn push(&mut self, window: Window, timestamp: Nanos, value: Option<f64>) {
+        match window {
+            Window::Rows(rows) => {
+                while self.entries.len() >= rows {
+                    self.pop();
+                }
+            }
+            Window::Time(nanos) => {
+                while let Some(entry) = self.entries.front() {
+                    if entry.timestamp.saturating_add(nanos) > timestamp {
+                        break;
+                    }
+                    self.pop();
+                }
+            }
+        }
+
+        self.sequence += 1;
+        if let Some(value) = value {
+            self.count += 1;
+            self.add_to_counts(value, 1);
+        }
+        // NaN is neither min nor max
+        if let Some(value) = value.filter(|value| !value.is_nan()) {
+            while matches!(self.min_queue.back(), Some((_, min)) if *min >= value) {
+                self.min_queue.pop_back();
+            }
+            self.min_queue.push_back((self.sequence, value));
+            while matches!(self.max_queue.back(), Some((_, max)) if *max <= value) {
+                self.max_queue.pop_back();
+            }
+            self.max_queue.push_back((self.sequence, value));
+        }
+        if let Some(value) = value.filter(|value| value.is_finite()) {
+            self.ewma = Some(match self.ewma {
+                None => (timestamp, value),
+                Some((previous_timestamp, mean)) => {
+                    let weight = match window {
+                        Window::Rows(rows) => 2.0 / (rows as f64 + 1.0),
+                        Window::Time(nanos) => {
+                            let elapsed = timestamp.saturating_sub(previous_timestamp);
+                            1.0 - (-(elapsed as f64) / nanos as f64).exp()
+                        }
+                    };
+                    (timestamp, mean + weight * (value - mean))
+                }
+            });
+        }
+        self.entries.push_back(WindowEntry {
+            sequence: self.sequence,
+            timestamp,
+            value,
+        });
+    }
+
+    fn pop(&mut self) {
+        let entry = self.entries.pop_front().unwrap();
+        if let Some(value) = entry.value {
+            self.count -= 1;
+            self.add_to_counts(value, -1);
+            if matches!(self.min_queue.front(), Some((sequence, _)) if *sequence == entry.sequence)
+            {
+                self.min_queue.pop_front();
+            }
+            if matches!(self.max_queue.front(), Some((sequence, _)) if *sequence == entry.sequence)
+            {
+                self.max_queue.pop_front();
+            }
+        }
+    }
+
+    fn add_to_counts(&mut self, value: f64, direction: i64) {
+        if value.is_nan() {
+            self.nan_count += direction;
+        } else if value == f64::INFINITY {
+            self.positive_infinity_count += direction;
+        } else if value == f64::NEG_INFINITY {
+            self.negative_infinity_count += direction;
+        } else if direction > 0 {
+            self.finite.add(value);
+        } else {
+            self.finite.remove(value);
+        }
+    }
+
+    /// what a sum of all values is, if any of them is not finite
+    fn non_finite_sum(&self) -> Option<f64> {
+        match (
+            self.nan_count > 0,
+            self.positive_infinity_count > 0,
+            self.negative_infinity_count > 0,
+        ) {
+            (true, _, _) | (_, true, true) => Some(f64::NAN),
+            (_, true, false) => Some(f64::INFINITY),
+            (_, false, true) => Some(f64::NEG_INFINITY),
+            (false, false, false) => None,
+        }
+    }
+
+    fn stat(&self, stat: RollingStat) -> FieldValue {
+        let double = |value: Option<f64>| match value {
+            Some(value) => FieldValue::Double(value),
+            None => FieldValue::None,
+        };
+        let count = self.count as f64;
+        match stat {
+            RollingStat::Count => FieldValue::Long(self.count),
+            _ if self.count == 0 => FieldValue::None,
+            RollingStat::Mean => FieldValue::Double(match self.non_finite_sum() {
+                Some(sum) => sum,
+                None => self.finite.sum() / count,
+            }),
+            RollingStat::Sum => {
+                FieldValue::Double(self.non_finite_sum().unwrap_or(self.finite.sum()))
+            }
+            RollingStat::Min => double(self.min_queue.front().map(|(_, min)| *min)),
+            RollingStat::Max => double(self.max_queue.front().map(|(_, max)| *max)),
+            RollingStat::StdDev if self.count < 2 => FieldValue::None,
+            RollingStat::StdDev => FieldValue::Double(match self.non_finite_sum() {
+                Some(_) => f64::NAN,
+                None => self.finite.variance().sqrt(),
+            }),
+            RollingStat::Ewma => double(self.ewma.map(|(_, mean)| mean)),
+        }
+    }
+}
+
+pub struct RollingConfig {
+    window: Window,
+    window_name: String,
+    stats: Vec<(String, RollingStat)>,
+    key: Option<String>,
+}
+
+pub struct Rolling {
+    window: Window,
+    key_index: Option<usize>,
+    /// distinct columns that statistics are computed for
+    column_indexes: Vec<usize>,
+    /// statistics to append, as positions in column_indexes
+    stats: Vec<(usize, RollingStat)>,
+    /// windows of all columns, per key; without key column everything is under null key
+    windows: HashMap<HashableFieldValue, Vec<WindowState>>,
+}
+
+impl Rolling {
+    /// appends a column per statistic, named column_stat_window, e.g. px_mean_5m; window is
+    /// a number of rows, e.g. 20, or a time interval, e.g. 5m; stats is a comma-separated
+    /// list of column:stat pairs, where stat is one of mean, sum, min, max, stddev, ewma and
+    /// count; if key is given, each value of that column has its own windows; memory used is
+    /// bounded by window length times number of keys
+    pub fn new(
+        window: &str,
+        stats: &str,
+        key: Option<&str>,
+    ) -> ChopperResult<Box<dyn DynHeaderSink>> {
+        let mut parsed_stats: Vec<(String, RollingStat)> = Vec::new();
+        for item in stats.split(',') {
+            match item.trim().rsplit_once(':') {
+                Some((name, stat)) if !name.is_empty() => {
+                    parsed_stats.push((name.to_string(), stat.parse::<RollingStat>()?))
+                }
+                _ => {
+                    return Err(Error::from(format!(
+                        "Rolling -- statistic [{}] is not in column:stat form",
+                        item
+                    )))
+                }
+            }
+        }
+        let config = RollingConfig {
+            window: window.parse::<Window>()?,
+            window_name: window.to_string(),
+            stats: parsed_stats,
+            key: key.map(|key| key.to_string()),
+        };
+        Ok(Box::new(config) as Box<dyn DynHeaderSink>)
+    }
+}
+
+impl DynHeaderSink for RollingConfig {
+    fn process_header(self: Box<Self>, header: &mut Header) -> ChopperResult<Box<dyn DataSink>> {
+        let key_index = match &self.key {
+            None => None,
+            Some(key) => Some(header.get_field_index(key)?),
+        };
+        let mut column_indexes: Vec<usize> = Vec::new();
+        let mut stats: Vec<(usize, RollingStat)> = Vec::new();
+        let mut field_names: Vec<String> = Vec::new();
+        let mut field_types: Vec<FieldType> = Vec::new();
+        for (name, stat) in self.stats {
+            let index = header.get_field_index(&name)?;
+            let field_type = header.field_types()[index];
+            if !is_integer(field_type) && !is_floating_point(field_type) {
+                return Err(Error::from(format!(
+                    "Rolling -- column {} of type {:?} is not numeric",
+                    name, field_type
+                )));
+            }
+            let position = match column_indexes.iter().position(|&i| i == index) {
+                Some(position) => position,
+                None => {
+                    column_indexes.push(index);
+                    column_indexes.len() - 1
+                }
+            };
+            stats.push((position, stat));
+            field_names.push(format!("{}_{}_{}", name, stat.name(), self.window_name));
+            field_types.push(match stat {
+                RollingStat::Count => FieldType::Long,
+                _ => FieldType::Double,
+            });
+        }
+        header.field_names_mut().append(&mut field_names);
+        header.field_types_mut().append(&mut field_types);
+
+        Ok(Box::new(Rolling {
+            window: self.window,
+            key_index,
+            column_indexes,
+            stats,
+            windows: HashMap::new(),
+        }))
+    }
+}
+
+impl DataSink for Rolling {
+    fn write_row(&mut self, io_rows: &mut Vec<Row>) -> ChopperResult<()> {
+        let row = io_rows.first_mut().unwrap();
+        let key = match self.key_index {
+            None => FieldValue::None,
+            Some(i) => row.field_values[i].clone(),
+        };
+        let column_count = self.column_indexes.len();
+        let windows = self
+            .windows
+            .entry(HashableFieldValue(key))
+            .or_insert_with(|| (0..column_count).map(|_| WindowState::default()).collect());
+        for (window, &i) in windows.iter_mut().zip(&self.column_indexes) {
+            let value = match &row.field_values[i] {
+                FieldValue::None => None,
+                value => Some(to_double(value)),
+            };
+            window.push(self.window, row.timestamp, value);
+        }
+        for &(position, stat) in &self.stats {
+            row.field_values.push(windows[position].stat(stat));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::aggregate::rolling::{Rolling, Window};
+    use crate::chopper::types::{FieldType, FieldValue, Header, Nanos, Row};
+
+    const SECOND: Nanos = 1_000_000_000;
+
+    fn rolling(
+        window: &str,
+        stats: &str,
+        key: Option<&str>,
+        rows: Vec<(Nanos, &str, Option<f64>)>,
+    ) -> (Header, Vec<Vec<FieldValue>>) {
+        let mut header = Header::new(
+            vec!["sym".to_string(), "px".to_string()],
+            vec![FieldType::String, FieldType::Double],
+        );
+        let rolling = Rolling::new(window, stats, key).unwrap();
+        let mut sink = rolling.process_header(&mut header).unwrap();
+
+        let mut output: Vec<Vec<FieldValue>> = Vec::new();
+        for (timestamp, sym, px) in rows {
+            let mut io_rows = vec![Row {
+                timestamp,
+                field_values: vec![
+                    FieldValue::String(sym.to_string()),
+                    match px {
+                        Some(px) => FieldValue::Double(px),
+                        None => FieldValue::None,
+                    },
+                ],
+            }];
+            sink.write_row(&mut io_rows).unwrap();
+            output.push(io_rows.remove(0).field_values.split_off(2));
+        }
+        (header, output)
+    }
+
+    fn doubles(output: &[Vec<FieldValue>], column: usize) -> Vec<Option<f64>> {
+        output
+            .iter()
+            .map(|values| match values[column] {
+                FieldValue::Double(v) => Some(v),
+                FieldValue::Long(v) => Some(v as f64),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_rolling_rows() {
+        let rows = vec![
+            (SECOND, "A", Some(3.0)),
+            (2 * SECOND, "A", Some(1.0)),
+            (3 * SECOND, "A", None),
+            (4 * SECOND, "A", Some(5.0)),
+            (5 * SECOND, "A", Some(2.0)),
+        ];
+        let (header, output) = rolling("3", "px:mean,px:min,px:max,px:count,px:stddev", None, rows);
+        assert_eq!(
+            &header.field_names()[2..],
+            &[
+                "px_mean_3",
+                "px_min_3",
+                "px_max_3",
+                "px_count_3",
+                "px_stddev_3"
+            ]
+        );
+        assert_eq!(
+            doubles(&output, 0),
+            vec![Some(3.0), Some(2.0), Some(2.0), Some(3.0), Some(3.5)]
+        );
+        assert_eq!(
+            doubles(&output, 1),
+            vec![Some(3.0), Some(1.0), Some(1.0), Some(1.0), Some(2.0)]
+        );
+        assert_eq!(
+            doubles(&output, 2),
+            vec![Some(3.0), Some(3.0), Some(3.0), Some(5.0), Some(5.0)]
+        );
+        assert_eq!(
+            doubles(&output, 3),
+            vec![Some(1.0), Some(2.0), Some(2.0), Some(2.0), Some(2.0)]
+        );
+        assert_eq!(doubles(&output, 4)[0], None);
+        assert!((doubles(&output, 4)[4].unwrap() - 4.5f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_rolling_time_with_key() {
+        let rows = vec![
+            (SECOND, "A", Some(1.0)),
+            (2 * SECOND, "B", Some(10.0)),
+            (3 * SECOND, "A", Some(2.0)),
+            (4 * SECOND, "A", Some(4.0)),
+            (5 * SECOND, "B", Some(20.0)),
+        ];
+        let (header, output) = rolling("2s", "px:sum,px:ewma", Some("sym"), rows);
+        assert_eq!(&header.field_names()[2..], &["px_sum_2s", "px_ewma_2s"]);
+        assert_eq!(
+            doubles(&output, 0),
+            vec![Some(1.0), Some(10.0), Some(2.0), Some(6.0), Some(20.0)]
+        );
+        let ewma = doubles(&output, 1);
+        assert_eq!(ewma[0], Some(1.0));
+        assert!((ewma[2].unwrap() - (2.0 - (-1.0f64).exp())).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_rolling_non_finite() {
+        let rows = vec![
+            (SECOND, "A", Some(1.0)),
+            (2 * SECOND, "A", Some(f64::NAN)),
+            (3 * SECOND, "A", Some(f64::INFINITY)),
+            (4 * SECOND, "A", Some(3.0)),
+            (5 * SECOND, "A", Some(5.0)),
+            (6 * SECOND, "A", Some(1e16)),
+            (7 * SECOND, "A", Some(1.0)),
+            (8 * SECOND, "A", Some(2.0)),
+        ];
+        let (_, output) = rolling("2", "px:sum,px:stddev,px:max,px:ewma", None, rows);
+        let sum = doubles(&output, 0);
+        assert!(sum[1].unwrap().is_nan());
+        assert!(sum[2].unwrap().is_nan());
+        assert_eq!(sum[3], Some(f64::INFINITY));
+        assert_eq!(sum[4], Some(8.0));
+        assert_eq!(sum[7], Some(3.0));
+        let stddev = doubles(&output, 1);
+        assert!(stddev[3].unwrap().is_nan());
+        assert!((stddev[4].unwrap() - 2.0f64.sqrt()).abs() < 1e-9);
+        assert!((stddev[7].unwrap() - 0.5f64.sqrt()).abs() < 1e-9);
+        assert_eq!(
+            doubles(&output, 2),
+            vec![
+                Some(1.0),
+                Some(1.0),
+                Some(f64::INFINITY),
+                Some(f64::INFINITY),
+                Some(5.0),
+                Some(1e16),
+                Some(1e16),
+                Some(2.0)
+            ]
+        );
+        assert!(doubles(&output, 3)
+            .iter()
+            .all(|ewma| ewma.unwrap().is_finite()));
+    }
+
+    #[test]
+    fn test_window_from_str() {
+        assert_eq!("20".parse::<Window>().unwrap(), Window::Rows(20));
+        assert_eq!("5m".parse::<Window>().unwrap(), Window::Time(300 * SECOND));
+        assert!("0".parse::<Window>().is_err());
+        assert!(Rolling::new("5", "px:median", None).is_err());
+    }
+}
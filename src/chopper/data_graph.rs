@@ -1,15 +1,21 @@
 use crate::chopper::error::{ChopperResult, Error};
-use crate::chopper::sink::DataSink;
+use crate::chopper::graph_dump::{DumpNode, GraphDump, NodeInfo};
+use crate::chopper::sink::{DataSink, RowRouter};
 use crate::chopper::types::{ChainId, NodeId};
 
 pub enum DataNode {
     DataSink(Box<dyn DataSink>),
-    Merge(ChainId),
-    Split(Vec<ChainId>),
+    /// target chain and index of this input among all the inputs of the target chain,
+    /// in the order their headers were processed
+    Merge(ChainId, usize),
+    /// target chains, and router if not every row goes to every chain
+    Split(Vec<ChainId>, Option<Box<dyn RowRouter>>),
 }
 
 pub struct DataChain {
     nodes: Vec<DataNode>,
+    /// per node, what it was made from, for dumping the graph
+    infos: Vec<NodeInfo>,
 }
 
 pub struct DataGraph {
@@ -19,7 +25,8 @@ pub struct DataGraph {
 impl DataChain {
     pub fn new() -> Self {
         let nodes: Vec<DataNode> = Vec::new();
-        DataChain { nodes }
+        let infos: Vec<NodeInfo> = Vec::new();
+        DataChain { nodes, infos }
     }
 
     pub fn node(&mut self, node_id: usize) -> &mut DataNode {
@@ -57,9 +64,17 @@ impl DataGraph {
         self.data_chains.get_mut(chain_id).unwrap().node(node_id)
     }
 
-    pub fn add_node(&mut self, node: DataNode, chain_id: ChainId) -> ChopperResult<()> {
+    pub fn add_node(
+        &mut self,
+        node: DataNode,
+        info: NodeInfo,
+        chain_id: ChainId,
+    ) -> ChopperResult<()> {
         match self.data_chains.get_mut(chain_id) {
-            Some(c) => c.nodes.push(node),
+            Some(c) => {
+                c.nodes.push(node);
+                c.infos.push(info);
+            }
             None => {
                 return Err(Error::from(format!(
                     "DataGraph -- index out of bound. \
@@ -71,4 +86,33 @@ impl DataGraph {
         };
         Ok(())
     }
+
+    pub fn dump(&self) -> GraphDump {
+        let chains = self
+            .data_chains
+            .iter()
+            .map(|chain| {
+                chain
+                    .nodes
+                    .iter()
+                    .zip(&chain.infos)
+                    .map(|(node, info)| {
+                        let (targets, routed) = match node {
+                            DataNode::DataSink(_) => (Vec::new(), false),
+                            DataNode::Merge(chain_id, _) => (vec![*chain_id], false),
+                            DataNode::Split(chain_ids, router) => {
+                                (chain_ids.clone(), router.is_some())
+                            }
+                        };
+                        DumpNode {
+                            info: info.clone(),
+                            targets,
+                            routed,
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        GraphDump { chains }
+    }
 }
@@ -4,7 +4,7 @@ use chrono::NaiveDateTime;
 use chrono_tz::Tz;
 use thiserror::Error as ThisError;
 
-use crate::chopper::types::Nanos;
+use crate::chopper::types::{ChainId, FieldType, Nanos, NodeId};
 
 pub type ChopperResult<T> = Result<T, Error>;
 
@@ -26,6 +26,12 @@ pub enum Error {
     FromUtf8Error(#[from] string::FromUtf8Error),
     #[error(transparent)]
     ShapeError(#[from] ndarray::ShapeError),
+    #[error(transparent)]
+    Arrow(#[from] arrow::error::ArrowError),
+    #[error(transparent)]
+    Parquet(#[from] parquet::errors::ParquetError),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
     #[error("Failed to find column named '{0}'.")]
     ColumnMissing(String),
     #[error(
@@ -41,12 +47,87 @@ pub enum Error {
     TimeZoneMissingForOutput(Nanos),
     #[error("Converting '{0}' in timezone '{1}' to timestamp failed.")]
     TimeConversion(NaiveDateTime, Tz),
+    #[error("Failed to parse '{0}' as {1:?} in column '{2}' on line {3}.")]
+    CellParsing(String, FieldType, String, u64),
+    #[error("Timestamp {3} on row {1} of {0} is earlier than previous timestamp {2}.")]
+    TimestampOutOfOrder(String, u64, Nanos, Nanos),
+    #[error(transparent)]
+    Graph(#[from] GraphError),
     #[error("DCFactory is required to handle DC files.")]
     DCFactoryMissing,
     #[error("Error: {0}")]
     Custom(String),
 }
 
+/// structural problem of a header graph, found before any header is processed
+#[derive(ThisError, Debug, PartialEq)]
+pub enum GraphError {
+    #[error(
+        "HeaderGraph -- {source_count} sources need at least as many chains, got {chain_count}"
+    )]
+    TooFewChains {
+        source_count: usize,
+        chain_count: usize,
+    },
+    #[error("HeaderGraph -- chain {0} has no nodes")]
+    EmptyChain(ChainId),
+    #[error(
+        "HeaderGraph -- node {node_id} of chain {chain_id} points to chain {target}, \
+        but there are only {chain_count} chains"
+    )]
+    DanglingChainId {
+        chain_id: ChainId,
+        node_id: NodeId,
+        target: ChainId,
+        chain_count: usize,
+    },
+    #[error(
+        "HeaderGraph -- node {node_id} of chain {chain_id} is a MergeHeaderSink, \
+        which has to be the first node of its chain"
+    )]
+    MisplacedMergeHeaderSink { chain_id: ChainId, node_id: NodeId },
+    #[error("HeaderGraph -- split in node {node_id} of chain {chain_id} has no chains")]
+    EmptySplit { chain_id: ChainId, node_id: NodeId },
+    #[error(
+        "HeaderGraph -- split in node {node_id} of chain {chain_id} has {chain_id_count} \
+        chains, but its header count tracker expects {expected}"
+    )]
+    SplitCountMismatch {
+        chain_id: ChainId,
+        node_id: NodeId,
+        chain_id_count: usize,
+        expected: usize,
+    },
+    #[error("HeaderGraph -- chains {0:?} make up a cycle")]
+    Cycle(Vec<ChainId>),
+    #[error(
+        "HeaderGraph -- MergeHeaderSink of chain {chain_id} expects {expected} inputs, \
+        but {actual} sources and Merge nodes lead to it"
+    )]
+    MergeCountMismatch {
+        chain_id: ChainId,
+        expected: usize,
+        actual: usize,
+    },
+    #[error(
+        "HeaderGraph -- chain {0} starts with a MergeHeaderSink, so rows have to get to it \
+        through Merge nodes instead of splits"
+    )]
+    SplitIntoMerge(ChainId),
+    #[error(
+        "HeaderGraph -- Merge nodes lead to chain {0}, which does not start with a \
+        MergeHeaderSink"
+    )]
+    MergeIntoNonMerge(ChainId),
+    #[error(
+        "HeaderGraph -- chain {chain_id} gets rows from {count} sources and splits, \
+        but only a chain that starts with a MergeHeaderSink can have more than one input"
+    )]
+    MultipleInputs { chain_id: ChainId, count: usize },
+    #[error("HeaderGraph -- chain {0} gets no rows from any source, Merge node or split")]
+    Unreachable(ChainId),
+}
+
 impl From<csv::Error> for Error {
     fn from(err: csv::Error) -> Error {
         if !err.is_io_error() {
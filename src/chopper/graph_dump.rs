@@ -0,0 +1,245 @@
+use std::fmt::Write;
+
+use crate::chopper::types::{ChainId, Header};
+
+/// kind of header node a node of either graph comes from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NodeKind {
+    HeaderSink,
+    MergeHeaderSink,
+    SplitHeaderSink,
+    Merge,
+}
+
+/// what is known about a node apart from where it passes rows to
+#[derive(Clone, Debug)]
+pub struct NodeInfo {
+    pub kind: NodeKind,
+    /// type of the sink, e.g. CSVSink, if the node has one
+    pub name: Option<String>,
+    /// header of rows coming into the node, which for a merge is the merged header;
+    /// only known once the header graph is processed
+    pub header: Option<Header>,
+}
+
+#[derive(Clone, Debug)]
+pub struct DumpNode {
+    pub info: NodeInfo,
+    /// chains that get rows of this node, besides the next node of its own chain
+    pub targets: Vec<ChainId>,
+    /// whether targets get only some of the rows
+    pub routed: bool,
+}
+
+/// header or data graph as chains of nodes, to be rendered as indented text or graphviz dot
+#[derive(Clone, Debug)]
+pub struct GraphDump {
+    pub chains: Vec<Vec<DumpNode>>,
+}
+
+/// type name without module path and generic parameters, e.g. CSVSink; header sinks that
+/// only hold the config of the data sink they create are named after it, e.g. ResampleConfig
+/// is Resample
+pub fn short_type_name(type_name: &str) -> String {
+    let type_name = match type_name.find('<') {
+        None => type_name,
+        Some(i) => &type_name[..i],
+    };
+    let type_name = match type_name.rfind("::") {
+        None => type_name,
+        Some(i) => &type_name[i + 2..],
+    };
+    type_name
+        .strip_suffix("Config")
+        .unwrap_or(type_name)
+        .to_string()
+}
+
+fn header_columns(header: &Header) -> Vec<String> {
+    header
+        .field_names()
+        .iter()
+        .zip(header.field_types())
+        .map(|(name, field_type)| format!("{}: {:?}", name, field_type))
+        .collect()
+}
+
+fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl DumpNode {
+    fn title(&self) -> String {
+        match &self.info.name {
+            None => format!("{:?}", self.info.kind),
+            Some(name) => format!("{:?} {}", self.info.kind, name),
+        }
+    }
+
+    fn target_list(&self) -> String {
+        let targets: Vec<String> = self.targets.iter().map(|t| t.to_string()).collect();
+        targets.join(", ")
+    }
+}
+
+impl GraphDump {
+    /// one line per chain and per node, with the header of each node below it, e.g.
+    ///
+    /// ```text
+    /// chain 0
+    ///   HeaderSink RowFilterExpression
+    ///     header: a: Int, b: String
+    ///   Merge -> chain 1
+    /// ```
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for (chain_id, nodes) in self.chains.iter().enumerate() {
+            writeln!(text, "chain {}", chain_id).unwrap();
+            for node in nodes {
+                write!(text, "  {}", node.title()).unwrap();
+                match (node.targets.len(), node.routed) {
+                    (0, _) => {}
+                    (1, false) => write!(text, " -> chain {}", node.target_list()).unwrap(),
+                    (_, false) => write!(text, " -> chains {}", node.target_list()).unwrap(),
+                    (_, true) => {
+                        write!(text, " -> routed to chains {}", node.target_list()).unwrap()
+                    }
+                }
+                text.push('\n');
+                if let Some(header) = &node.info.header {
+                    writeln!(text, "    header: {}", header_columns(header).join(", ")).unwrap();
+                }
+            }
+        }
+        text
+    }
+
+    /// one cluster per chain; rows go from each node to the next one of its chain, and from
+    /// merges and splits to the first node of their target chains as well
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        dot.push_str("digraph chopper {\n");
+        dot.push_str("  rankdir=LR;\n");
+        dot.push_str("  node [shape=box];\n");
+        for (chain_id, nodes) in self.chains.iter().enumerate() {
+            writeln!(dot, "  subgraph cluster_{} {{", chain_id).unwrap();
+            writeln!(dot, "    label=\"chain {}\";", chain_id).unwrap();
+            for (node_id, node) in nodes.iter().enumerate() {
+                let mut label = dot_escape(&node.title());
+                if let Some(header) = &node.info.header {
+                    for column in header_columns(header) {
+                        write!(label, "\\l{}", dot_escape(&column)).unwrap();
+                    }
+                    label.push_str("\\l");
+                }
+                writeln!(dot, "    n{}_{} [label=\"{}\"];", chain_id, node_id, label).unwrap();
+            }
+            dot.push_str("  }\n");
+        }
+        for (chain_id, nodes) in self.chains.iter().enumerate() {
+            for (node_id, node) in nodes.iter().enumerate() {
+                if node_id + 1 < nodes.len() {
+                    writeln!(
+                        dot,
+                        "  n{}_{} -> n{}_{};",
+                        chain_id,
+                        node_id,
+                        chain_id,
+                        node_id + 1
+                    )
+                    .unwrap();
+                }
+                for target in &node.targets {
+                    // chains without nodes have nothing to point to
+                    if self.chains.get(*target).map_or(0, |c| c.len()) == 0 {
+                        continue;
+                    }
+                    let style = match node.routed {
+                        true => " [style=dashed]",
+                        false => "",
+                    };
+                    writeln!(
+                        dot,
+                        "  n{}_{} -> n{}_0{};",
+                        chain_id, node_id, target, style
+                    )
+                    .unwrap();
+                }
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chopper::graph_dump::{short_type_name, DumpNode, GraphDump, NodeInfo, NodeKind};
+    use crate::chopper::types::{FieldType, Header};
+
+    fn dump() -> GraphDump {
+        let header = Header::new(vec!["a\"".to_string()], vec![FieldType::Int]);
+        let node =
+            |kind: NodeKind, name: Option<&str>, targets: Vec<usize>, routed: bool| DumpNode {
+                info: NodeInfo {
+                    kind,
+                    name: name.map(|n| n.to_string()),
+                    header: Some(header.clone()),
+                },
+                targets,
+                routed,
+            };
+        GraphDump {
+            chains: vec![
+                vec![
+                    node(NodeKind::HeaderSink, Some("Filter"), vec![], false),
+                    node(NodeKind::SplitHeaderSink, Some("Split"), vec![1, 2], true),
+                ],
+                vec![node(NodeKind::HeaderSink, Some("CSVSink"), vec![], false)],
+                vec![node(NodeKind::HeaderSink, Some("CSVSink"), vec![], false)],
+            ],
+        }
+    }
+
+    #[test]
+    fn test_short_type_name() {
+        assert_eq!(
+            short_type_name("chopper::write::csv_sink::CSVSink<alloc::boxed::Box<dyn Write>>"),
+            "CSVSink"
+        );
+        assert_eq!(short_type_name("Split"), "Split");
+        assert_eq!(
+            short_type_name("chopper::aggregate::resample::ResampleConfig"),
+            "Resample"
+        );
+    }
+
+    #[test]
+    fn test_to_text() {
+        assert_eq!(
+            dump().to_text(),
+            "chain 0\n\
+            \x20 HeaderSink Filter\n\
+            \x20   header: a\": Int\n\
+            \x20 SplitHeaderSink Split -> routed to chains 1, 2\n\
+            \x20   header: a\": Int\n\
+            chain 1\n\
+            \x20 HeaderSink CSVSink\n\
+            \x20   header: a\": Int\n\
+            chain 2\n\
+            \x20 HeaderSink CSVSink\n\
+            \x20   header: a\": Int\n"
+        );
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let dot = dump().to_dot();
+        assert!(dot.starts_with("digraph chopper {\n"));
+        assert!(dot.contains("    n0_0 [label=\"HeaderSink Filter\\la\\\": Int\\l\"];\n"));
+        assert!(dot.contains("  n0_0 -> n0_1;\n"));
+        assert!(dot.contains("  n0_1 -> n1_0 [style=dashed];\n"));
+        assert!(dot.contains("  n0_1 -> n2_0 [style=dashed];\n"));
+        assert!(dot.ends_with("}\n"));
+    }
+}
@@ -1,5 +1,6 @@
 use crate::chopper::data_graph::{DataGraph, DataNode};
-use crate::chopper::error::{ChopperResult, Error};
+use crate::chopper::error::{ChopperResult, Error, GraphError};
+use crate::chopper::graph_dump::{DumpNode, GraphDump, NodeInfo, NodeKind};
 use crate::chopper::sink::{DynHeaderSink, MergeHeaderSink, SplitHeaderSink};
 use crate::chopper::types::{ChainId, Header};
 
@@ -14,12 +15,30 @@ pub enum HeaderNode {
     Merge(ChainId),
 }
 
+impl HeaderNode {
+    fn info(&self, header: Option<&Header>) -> NodeInfo {
+        let (kind, name) = match self {
+            HeaderNode::HeaderSink(hs) => (NodeKind::HeaderSink, Some(hs.name())),
+            HeaderNode::MergeHeaderSink(mhs, _) => (NodeKind::MergeHeaderSink, Some(mhs.name())),
+            HeaderNode::SplitHeaderSink(shs, _) => (NodeKind::SplitHeaderSink, Some(shs.name())),
+            HeaderNode::Merge(_) => (NodeKind::Merge, None),
+        };
+        NodeInfo {
+            kind,
+            name,
+            header: header.cloned(),
+        }
+    }
+}
+
 pub struct HeaderChain {
     nodes: Vec<HeaderNode>,
 }
 
 pub struct HeaderGraph {
     header_chains: Vec<HeaderChain>,
+    /// per chain, number of merged headers processed so far
+    merge_input_counts: Vec<usize>,
 }
 
 impl HeaderChain {
@@ -38,7 +57,11 @@ impl HeaderChain {
 
 impl HeaderGraph {
     pub fn new(header_chains: Vec<HeaderChain>) -> Self {
-        HeaderGraph { header_chains }
+        let merge_input_counts = vec![0; header_chains.len()];
+        HeaderGraph {
+            header_chains,
+            merge_input_counts,
+        }
     }
 
     pub fn len(&self) -> usize {
@@ -49,7 +72,144 @@ impl HeaderGraph {
         self.header_chains.get_mut(chain_id)
     }
 
+    /// checks that every chain id points to an existing chain, that chains without a
+    /// MergeHeaderSink have exactly one input, that MergeHeaderSinks expect as many inputs
+    /// as there are, and that there are no cycles; chains 0 to source_count - 1 get rows
+    /// from sources
+    pub fn validate(&mut self, source_count: usize) -> Result<(), GraphError> {
+        let chain_count = self.header_chains.len();
+        if source_count > chain_count {
+            return Err(GraphError::TooFewChains {
+                source_count,
+                chain_count,
+            });
+        }
+
+        // per chain, chains its nodes lead to, and number of Merge nodes and splits leading
+        // to it
+        let mut targets: Vec<Vec<ChainId>> = vec![Vec::new(); chain_count];
+        let mut merge_counts: Vec<usize> = vec![0; chain_count];
+        let mut split_counts: Vec<usize> = vec![0; chain_count];
+        for (chain_id, chain) in self.header_chains.iter_mut().enumerate() {
+            if chain.nodes.is_empty() {
+                return Err(GraphError::EmptyChain(chain_id));
+            }
+            for (node_id, node) in chain.nodes.iter_mut().enumerate() {
+                let (node_targets, counts) = match node {
+                    HeaderNode::HeaderSink(_) => continue,
+                    HeaderNode::MergeHeaderSink(..) if node_id == 0 => continue,
+                    HeaderNode::MergeHeaderSink(..) => {
+                        return Err(GraphError::MisplacedMergeHeaderSink { chain_id, node_id })
+                    }
+                    HeaderNode::SplitHeaderSink(shs, header_count_tracker) => {
+                        let chain_ids = shs.chain_ids().clone();
+                        if chain_ids.is_empty() {
+                            return Err(GraphError::EmptySplit { chain_id, node_id });
+                        }
+                        if chain_ids.len() != header_count_tracker.unprocessed_count {
+                            return Err(GraphError::SplitCountMismatch {
+                                chain_id,
+                                node_id,
+                                chain_id_count: chain_ids.len(),
+                                expected: header_count_tracker.unprocessed_count,
+                            });
+                        }
+                        (chain_ids, &mut split_counts)
+                    }
+                    HeaderNode::Merge(target) => (vec![*target], &mut merge_counts),
+                };
+                for target in node_targets {
+                    if target >= chain_count {
+                        return Err(GraphError::DanglingChainId {
+                            chain_id,
+                            node_id,
+                            target,
+                            chain_count,
+                        });
+                    }
+                    counts[target] += 1;
+                    targets[chain_id].push(target);
+                }
+            }
+        }
+
+        // chains that are left once every chain without inputs is taken out, along with
+        // what it leads to, make up cycles
+        let mut input_counts: Vec<usize> = vec![0; chain_count];
+        for target in targets.iter().flatten() {
+            input_counts[*target] += 1;
+        }
+        let mut ready: Vec<ChainId> = (0..chain_count).filter(|&c| input_counts[c] == 0).collect();
+        while let Some(chain_id) = ready.pop() {
+            for target in &targets[chain_id] {
+                input_counts[*target] -= 1;
+                if input_counts[*target] == 0 {
+                    ready.push(*target);
+                }
+            }
+        }
+        let cycle: Vec<ChainId> = (0..chain_count).filter(|&c| input_counts[c] > 0).collect();
+        if !cycle.is_empty() {
+            return Err(GraphError::Cycle(cycle));
+        }
+
+        for (chain_id, chain) in self.header_chains.iter().enumerate() {
+            let source_input_count = usize::from(chain_id < source_count);
+            match &chain.nodes[0] {
+                HeaderNode::MergeHeaderSink(_, header_count_tracker) => {
+                    if split_counts[chain_id] > 0 {
+                        return Err(GraphError::SplitIntoMerge(chain_id));
+                    }
+                    let actual = merge_counts[chain_id] + source_input_count;
+                    if actual != header_count_tracker.unprocessed_count {
+                        return Err(GraphError::MergeCountMismatch {
+                            chain_id,
+                            expected: header_count_tracker.unprocessed_count,
+                            actual,
+                        });
+                    }
+                }
+                _ => {
+                    if merge_counts[chain_id] > 0 {
+                        return Err(GraphError::MergeIntoNonMerge(chain_id));
+                    }
+                    match split_counts[chain_id] + source_input_count {
+                        0 => return Err(GraphError::Unreachable(chain_id)),
+                        1 => {}
+                        count => return Err(GraphError::MultipleInputs { chain_id, count }),
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// nodes are dumped without headers, since those are only known once headers are processed
+    pub fn dump(&mut self) -> GraphDump {
+        let mut chains: Vec<Vec<DumpNode>> = Vec::with_capacity(self.header_chains.len());
+        for chain in &mut self.header_chains {
+            let mut nodes: Vec<DumpNode> = Vec::with_capacity(chain.nodes.len());
+            for node in &mut chain.nodes {
+                let info = node.info(None);
+                let targets = match node {
+                    HeaderNode::SplitHeaderSink(shs, _) => shs.chain_ids().clone(),
+                    HeaderNode::Merge(chain_id) => vec![*chain_id],
+                    _ => Vec::new(),
+                };
+                nodes.push(DumpNode {
+                    info,
+                    targets,
+                    routed: false,
+                });
+            }
+            chains.push(nodes);
+        }
+        GraphDump { chains }
+    }
+
     pub fn process_header(mut self, mut header: Vec<Header>) -> ChopperResult<DataGraph> {
+        self.validate(header.len())?;
+
         // initialize an empty data_graph
         let mut data_graph = DataGraph::new(self.header_chains.len());
 
@@ -93,14 +253,15 @@ impl HeaderGraph {
         // process header for all the nodes in the chain, and get DataSinks
         for node in chain.nodes {
             let data_node: DataNode;
+            let info = node.info(Some(header));
             match node {
                 HeaderNode::HeaderSink(hs) => {
                     data_node = DataNode::DataSink(hs.process_header(header)?);
-                    data_graph.add_node(data_node, chain_id).unwrap();
+                    data_graph.add_node(data_node, info, chain_id).unwrap();
                 }
                 HeaderNode::MergeHeaderSink(mhs, _) => {
                     data_node = DataNode::DataSink(mhs.get_data_sink()?);
-                    data_graph.add_node(data_node, chain_id)?;
+                    data_graph.add_node(data_node, info, chain_id)?;
                 }
                 HeaderNode::SplitHeaderSink(mut shs, mut header_count_tracker) => {
                     if header_count_tracker.unprocessed_count <= 0 {
@@ -112,12 +273,24 @@ impl HeaderGraph {
                         self = self.match_remove_chain(data_graph, *i, &mut header.clone())?;
                         header_count_tracker.unprocessed_count -= 1;
                     }
-                    data_node = DataNode::Split(shs.chain_ids().clone());
-                    data_graph.add_node(data_node, chain_id)?;
+                    let router = shs.get_router(header)?;
+                    data_node = DataNode::Split(shs.chain_ids().clone(), router);
+                    data_graph.add_node(data_node, info, chain_id)?;
                 }
                 // move to the chain that has MergeHeaderSink
                 HeaderNode::Merge(new_chain_id) => {
-                    data_graph.add_node(DataNode::Merge(new_chain_id), chain_id)?;
+                    // check_merge_header below is going to process this input's header next
+                    let input_index = match self.merge_input_counts.get(new_chain_id) {
+                        Some(count) => *count,
+                        None => {
+                            return Err(Error::from(format!(
+                                "HeaderGraph -- ChainId[{}] index out of bounds",
+                                new_chain_id
+                            )))
+                        }
+                    };
+                    let data_node = DataNode::Merge(new_chain_id, input_index);
+                    data_graph.add_node(data_node, info, chain_id)?;
                     self = self.check_merge_header(data_graph, new_chain_id, header)?;
                 }
             }
@@ -131,7 +304,7 @@ impl HeaderGraph {
         chain_id: ChainId,
         header: &mut Header,
     ) -> ChopperResult<Self> {
-        let node: &mut HeaderNode = match self.get_mut_chain(chain_id) {
+        let node: &mut HeaderNode = match self.header_chains.get_mut(chain_id) {
             Some(c) => c.get_mut_nodes().get_mut(0).unwrap(),
             None => {
                 return Err(Error::from(format!(
@@ -150,6 +323,7 @@ impl HeaderGraph {
 
                 mhs.check_header(header)?;
                 header_count_tracker.unprocessed_count -= 1;
+                self.merge_input_counts[chain_id] += 1;
 
                 // finished processing all the headers
                 // next get DataSink for MergeHeaderSink and remove Merge node
@@ -163,3 +337,115 @@ impl HeaderGraph {
         Ok(self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::chopper::error::{Error, GraphError};
+    use crate::chopper::header_graph::{HeaderChain, HeaderGraph, HeaderNode};
+    use crate::chopper::types::{ChainId, FieldType, Header};
+    use crate::driver::merge_join::MergeJoin;
+    use crate::driver::split::Split;
+    use crate::write::vec_sink::VecSink;
+
+    fn sink() -> HeaderNode {
+        HeaderNode::HeaderSink(Box::new(VecSink::new()))
+    }
+
+    fn merge(input_count: usize) -> HeaderNode {
+        let merge = MergeJoin::new(input_count).unwrap();
+        let header_count_tracker = merge.get_new_header_count_tracker();
+        HeaderNode::MergeHeaderSink(merge, header_count_tracker)
+    }
+
+    fn split(chain_ids: Vec<ChainId>) -> HeaderNode {
+        let split = Split::new(chain_ids);
+        let header_count_tracker = split.get_new_header_count_tracker();
+        HeaderNode::SplitHeaderSink(split, header_count_tracker)
+    }
+
+    fn validate(chains: Vec<Vec<HeaderNode>>, source_count: usize) -> Result<(), GraphError> {
+        let chains = chains.into_iter().map(HeaderChain::new).collect();
+        HeaderGraph::new(chains).validate(source_count)
+    }
+
+    #[test]
+    fn test_validate() {
+        // two sources merged, then split into two sinks
+        let chains = vec![
+            vec![HeaderNode::Merge(2)],
+            vec![HeaderNode::Merge(2)],
+            vec![merge(2), split(vec![3, 4])],
+            vec![sink()],
+            vec![sink()],
+        ];
+        assert_eq!(validate(chains, 2), Ok(()));
+
+        let chains = vec![vec![split(vec![1, 5])], vec![sink()]];
+        assert_eq!(
+            validate(chains, 1),
+            Err(GraphError::DanglingChainId {
+                chain_id: 0,
+                node_id: 0,
+                target: 5,
+                chain_count: 2
+            })
+        );
+
+        let chains = vec![vec![HeaderNode::Merge(1)], vec![merge(2), sink()]];
+        assert_eq!(
+            validate(chains, 1),
+            Err(GraphError::MergeCountMismatch {
+                chain_id: 1,
+                expected: 2,
+                actual: 1
+            })
+        );
+
+        let chains = vec![
+            vec![split(vec![1])],
+            vec![split(vec![2])],
+            vec![split(vec![1])],
+        ];
+        assert_eq!(validate(chains, 1), Err(GraphError::Cycle(vec![1, 2])));
+
+        let chains = vec![vec![sink()], vec![sink()]];
+        assert_eq!(validate(chains, 1), Err(GraphError::Unreachable(1)));
+
+        let chains = vec![vec![split(vec![1])], vec![merge(1), sink()]];
+        assert_eq!(validate(chains, 1), Err(GraphError::SplitIntoMerge(1)));
+
+        let chains = vec![vec![HeaderNode::Merge(1)], vec![sink()]];
+        assert_eq!(validate(chains, 1), Err(GraphError::MergeIntoNonMerge(1)));
+
+        let chains = vec![vec![split(vec![1])], vec![sink()]];
+        assert_eq!(
+            validate(chains, 2),
+            Err(GraphError::MultipleInputs {
+                chain_id: 1,
+                count: 2
+            })
+        );
+
+        let chains = vec![vec![sink(), merge(1)]];
+        assert_eq!(
+            validate(chains, 1),
+            Err(GraphError::MisplacedMergeHeaderSink {
+                chain_id: 0,
+                node_id: 1
+            })
+        );
+
+        let chains = vec![vec![sink()], vec![]];
+        assert_eq!(validate(chains, 1), Err(GraphError::EmptyChain(1)));
+    }
+
+    #[test]
+    fn test_process_header_validates() {
+        let graph = HeaderGraph::new(vec![HeaderChain::new(vec![HeaderNode::Merge(3)])]);
+        let header = Header::new(vec!["a".to_string()], vec![FieldType::Int]);
+        match graph.process_header(vec![header]) {
+            Err(Error::Graph(GraphError::DanglingChainId { target: 3, .. })) => {}
+            _ => panic!("expected dangling chain id"),
+        }
+    }
+}
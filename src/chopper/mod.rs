@@ -1,6 +1,7 @@
 pub mod data_graph;
 pub mod driver;
 pub mod error;
+pub mod graph_dump;
 pub mod header_graph;
 pub mod sink;
 pub mod types;
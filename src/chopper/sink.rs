@@ -1,4 +1,7 @@
+use std::any::type_name;
+
 use crate::chopper::error::ChopperResult;
+use crate::chopper::graph_dump::short_type_name;
 use crate::chopper::header_graph::HeaderCountTracker;
 use crate::chopper::types::{ChainId, Header, Row};
 
@@ -8,6 +11,11 @@ pub trait TypedHeaderSink<D: DataSink> {
 
 pub trait DynHeaderSink {
     fn process_header(self: Box<Self>, header: &mut Header) -> ChopperResult<Box<dyn DataSink>>;
+
+    /// shown when the graph is dumped; default implementation returns the type name
+    fn name(&self) -> String {
+        short_type_name(type_name::<Self>())
+    }
 }
 
 pub trait DataSink {
@@ -20,7 +28,23 @@ pub trait DataSink {
     /// default implementation simply leaves the input row unchanged
     fn write_row(&mut self, io_rows: &mut Vec<Row>) -> ChopperResult<()>;
 
-    fn flush(&mut self) -> ChopperResult<()> {
+    /// same as write_row, but for the first data sink of a chain that multiple inputs are merged
+    /// into, i.e. the one of MergeHeaderSink; input_index is the position of the input among
+    /// all the merged inputs, in the order their headers were given to check_header;
+    /// default implementation ignores the input
+    fn write_merged_row(
+        &mut self,
+        _input_index: usize,
+        io_rows: &mut Vec<Row>,
+    ) -> ChopperResult<()> {
+        self.write_row(io_rows)
+    }
+
+    /// called once after the last row; io_rows vec is guaranteed to be empty as input and
+    /// data sink impl can output any rows it still holds, e.g. partially aggregated ones,
+    /// to be passed to the next node in chain before that node is flushed;
+    /// default implementation outputs nothing
+    fn flush(&mut self, _io_rows: &mut Vec<Row>) -> ChopperResult<()> {
         Ok(())
     }
 }
@@ -30,9 +54,34 @@ pub trait MergeHeaderSink {
     fn process_header(&mut self) -> Header;
     fn get_data_sink(self: Box<Self>) -> ChopperResult<Box<dyn DataSink>>;
     fn get_new_header_count_tracker(&self) -> HeaderCountTracker;
+
+    /// shown when the graph is dumped; default implementation returns the type name
+    fn name(&self) -> String {
+        short_type_name(type_name::<Self>())
+    }
 }
 
 pub trait SplitHeaderSink {
     fn chain_ids(&mut self) -> &mut Vec<ChainId>;
     fn get_new_header_count_tracker(&self) -> HeaderCountTracker;
+
+    /// called once with the header of rows coming into the split, after the headers of all
+    /// the chains are processed; default implementation returns none, in which case every row
+    /// goes to every chain
+    fn get_router(&mut self, _header: &Header) -> ChopperResult<Option<Box<dyn RowRouter>>> {
+        Ok(None)
+    }
+
+    /// shown when the graph is dumped; default implementation returns the type name
+    fn name(&self) -> String {
+        short_type_name(type_name::<Self>())
+    }
+}
+
+/// picks which chains of a split get each row
+pub trait RowRouter {
+    /// io_indexes vec is guaranteed to be empty as input and is to be filled with indexes into
+    /// the chain ids of the split, of chains that get the row; it can be left empty to drop
+    /// the row
+    fn route(&mut self, row: &Row, io_indexes: &mut Vec<usize>) -> ChopperResult<()>;
 }
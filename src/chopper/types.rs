@@ -1,5 +1,7 @@
 use std::cmp::Ordering;
 use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::mem;
 
 use ndarray::ArrayD;
 
@@ -120,6 +122,26 @@ pub enum FieldValue {
     None,
 }
 
+fn canonical_f64_bits(value: f64) -> u64 {
+    if value.is_nan() {
+        f64::NAN.to_bits()
+    } else if value == 0.0 {
+        0
+    } else {
+        value.to_bits()
+    }
+}
+
+fn canonical_f32_bits(value: f32) -> u32 {
+    if value.is_nan() {
+        f32::NAN.to_bits()
+    } else if value == 0.0 {
+        0
+    } else {
+        value.to_bits()
+    }
+}
+
 impl PartialOrd for FieldValue {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         match self {
@@ -205,6 +227,74 @@ impl PartialOrd for FieldValue {
     }
 }
 
+/// field value used as a key, e.g. when grouping rows; floats are compared and hashed by
+/// their canonical bit representation, which makes all NaNs equal to each other, while 0.0
+/// and -0.0 are still equal as well
+#[derive(Clone, Debug)]
+pub struct HashableFieldValue(pub FieldValue);
+
+impl From<FieldValue> for HashableFieldValue {
+    fn from(value: FieldValue) -> Self {
+        HashableFieldValue(value)
+    }
+}
+
+impl PartialEq for HashableFieldValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (FieldValue::Boolean(v), FieldValue::Boolean(o)) => v == o,
+            (FieldValue::Byte(v), FieldValue::Byte(o)) => v == o,
+            (FieldValue::ByteBuf(v), FieldValue::ByteBuf(o)) => v == o,
+            (FieldValue::Char(v), FieldValue::Char(o)) => v == o,
+            (FieldValue::Double(v), FieldValue::Double(o)) => {
+                canonical_f64_bits(*v) == canonical_f64_bits(*o)
+            }
+            (FieldValue::Float(v), FieldValue::Float(o)) => {
+                canonical_f32_bits(*v) == canonical_f32_bits(*o)
+            }
+            (FieldValue::Int(v), FieldValue::Int(o)) => v == o,
+            (FieldValue::Long(v), FieldValue::Long(o)) => v == o,
+            (FieldValue::Short(v), FieldValue::Short(o)) => v == o,
+            (FieldValue::String(v), FieldValue::String(o)) => v == o,
+            (FieldValue::MultiDimDoubleArray(v), FieldValue::MultiDimDoubleArray(o)) => {
+                v.shape() == o.shape()
+                    && v.iter()
+                        .zip(o.iter())
+                        .all(|(v, o)| canonical_f64_bits(*v) == canonical_f64_bits(*o))
+            }
+            (FieldValue::None, FieldValue::None) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for HashableFieldValue {}
+
+impl Hash for HashableFieldValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(&self.0).hash(state);
+        match &self.0 {
+            FieldValue::Boolean(v) => v.hash(state),
+            FieldValue::Byte(v) => v.hash(state),
+            FieldValue::ByteBuf(v) => v.hash(state),
+            FieldValue::Char(v) => v.hash(state),
+            FieldValue::Double(v) => canonical_f64_bits(*v).hash(state),
+            FieldValue::Float(v) => canonical_f32_bits(*v).hash(state),
+            FieldValue::Int(v) => v.hash(state),
+            FieldValue::Long(v) => v.hash(state),
+            FieldValue::Short(v) => v.hash(state),
+            FieldValue::String(v) => v.hash(state),
+            FieldValue::MultiDimDoubleArray(v) => {
+                v.shape().hash(state);
+                for value in v.iter() {
+                    canonical_f64_bits(*value).hash(state);
+                }
+            }
+            FieldValue::None => {}
+        }
+    }
+}
+
 impl fmt::Display for FieldValue {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match &self {
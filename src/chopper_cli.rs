@@ -1,29 +1,48 @@
 use std::collections::HashMap;
+use std::fs;
 
 use chrono_tz::Tz;
 use clap::{value_t, ArgMatches};
 
+use crate::aggregate::group_by::GroupBy;
+use crate::aggregate::resample::{FillPolicy, Resample};
+use crate::aggregate::rolling::Rolling;
 use crate::chopper::driver::ChopperDriver;
 use crate::chopper::error::ChopperResult;
 use crate::chopper::header_graph::{HeaderChain, HeaderGraph, HeaderNode};
+use crate::chopper::sink::{DynHeaderSink, MergeHeaderSink};
 use crate::chopper::types::{Header, TimestampRange};
 use crate::cli::util::YesNoAuto;
 use crate::cli_app::CliApp;
+use crate::compress::compress::CompressionFormat;
+use crate::driver::asof_join::AsOfJoin;
+use crate::driver::explain::{Explain, ExplainFormat};
+use crate::driver::tie_break::TieBreakPolicy;
 use crate::driver::{driver::Driver, merge_join::MergeJoin};
+use crate::filter::column_projection::ColumnProjection;
+use crate::filter::column_tag::{ColumnTag, TagValue};
+use crate::filter::row_filter_expression::RowFilterExpression;
 use crate::input::input::{Input, InputFormat, InputType};
 use crate::input::input_factory::InputFactoryBuilder;
-use crate::source::csv_input_config::CSVInputConfig;
+use crate::pipeline::config::PipelineConfig;
+use crate::source::columnar_input_config::ColumnarInputConfig;
+use crate::source::csv_input_config::{CSVInputConfig, CellParseErrorPolicy};
 use crate::source::csv_timestamp_config::{
     TimestampColConfig, TimestampConfig, TimestampFmtConfig,
 };
+use crate::source::json_input_config::JsonInputConfig;
+use crate::source::monotonic_source::OutOfOrderPolicy;
 use crate::source::source::Source;
 use crate::source::source_factory::SourceFactory;
 use crate::transport::streaming::streaming_transport::StreamingTransport;
+use crate::util::csv_util;
 use crate::util::dc_factory::DCFactory;
+use crate::util::interval::Interval;
 use crate::util::timestamp_units::TimestampUnits;
 use crate::util::tz::ChopperTz;
-use crate::write::csv_output_config::{CSVOutputConfig, TimestampStyle};
+use crate::write::csv_output_config::{CSVOutputConfig, QuoteStyle, TimestampStyle};
 use crate::write::factory::OutputFactory;
+use crate::write::json_output_config::JsonOutputConfig;
 
 pub struct ChopperCli {
     streaming_transports: Option<Vec<Box<dyn StreamingTransport>>>,
@@ -133,86 +152,220 @@ pub fn parse_cli_args(
     assert!(!inputs.is_empty());
 
     let output = matches.value_of("output");
+    let tie_break_policy = matches
+        .value_of("tie_break")
+        .unwrap()
+        .parse::<TieBreakPolicy>()?;
+    // processing nodes between the merged inputs and the output, in order
+    let mut transforms: Vec<Box<dyn DynHeaderSink>> = Vec::new();
+    if let Some(filter) = matches.value_of("filter") {
+        transforms.push(RowFilterExpression::new(filter)?);
+    }
+    let columns = matches.value_of("columns");
+    let drop = matches.value_of("drop");
+    let rename = matches.value_of("rename");
+    if columns.is_some() || drop.is_some() || rename.is_some() {
+        transforms.push(ColumnProjection::new(columns, drop, rename)?);
+    }
+    if let Some(window) = matches.value_of("rolling") {
+        transforms.push(Rolling::new(
+            window,
+            matches.value_of("rolling_stats").unwrap(),
+            matches.value_of("rolling_key"),
+        )?);
+    }
+    if let Some(interval) = matches.value_of("resample") {
+        let fill_policy = matches
+            .value_of("resample_fill")
+            .unwrap_or("none")
+            .parse::<FillPolicy>()?;
+        transforms.push(Resample::new(
+            interval,
+            matches.value_of("agg").unwrap(),
+            fill_policy,
+            timezone.clone(),
+        )?);
+    }
+    if let Some(keys) = matches.value_of("group_by") {
+        transforms.push(GroupBy::new(
+            keys,
+            matches.value_of("agg").unwrap(),
+            matches.value_of("group_every"),
+            timezone.clone(),
+        )?);
+    }
 
     // csv only
     let csv_input_config = parse_csv_input_config(&matches, timezone.clone())?;
-    let csv_output_config = parse_csv_output_config(&matches, timezone);
+    let columnar_input_config = parse_columnar_input_config(&matches);
+    let json_input_config = parse_json_input_config(&matches, timezone.clone())?;
+    let csv_output_config = parse_csv_output_config(&matches, timezone.clone())?;
+    let json_output_config = parse_json_output_config(&matches, timezone.clone());
+    let output_compression = match matches.value_of("output_compression") {
+        None => None,
+        Some(compression) => Some(compression.parse::<CompressionFormat>()?),
+    };
+    let output_factory = OutputFactory::new()
+        .with_csv_output_config(csv_output_config)
+        .with_json_output_config(json_output_config)
+        .with_output_compression(output_compression)
+        .with_dc_factory(dc_factory.clone())
+        .with_dry_run(matches.is_present("explain"));
+    let output_factory = match matches.is_present("partition") {
+        false => output_factory,
+        true => {
+            let max_open_files = value_t!(matches.value_of("max_open_files"), usize)?;
+            output_factory.with_partitioning(timezone.clone(), max_open_files)
+        }
+    };
+    let input_factory_builder = InputFactoryBuilder::new()
+        .with_csv_input_config(csv_input_config)
+        .with_columnar_input_config(columnar_input_config)
+        .with_json_input_config(json_input_config)
+        .with_user_source_factories(source_factories)
+        .with_user_streaming_transports(streaming_transports)
+        .with_dc_factory(dc_factory);
+    // same as with the library, timestamps are only checked if asked to
+    let input_factory_builder = match matches.value_of("out_of_order") {
+        None => input_factory_builder,
+        Some(policy) => {
+            input_factory_builder.with_out_of_order_policy(policy.parse::<OutOfOrderPolicy>()?)
+        }
+    };
 
-    setup_graph(
+    if let Some(path) = matches.value_of("pipeline") {
+        let driver = PipelineConfig::from_file(path)?.build_driver(
+            input_factory_builder,
+            &output_factory,
+            &timezone,
+            timestamp_range,
+        )?;
+        return finish_driver(&matches, driver.with_tie_break_policy(tie_break_policy));
+    }
+
+    // multiple inputs get merged into one
+    let merge = match inputs.len() {
+        1 => None,
+        _ if matches.is_present("asof") => {
+            let tolerance = match matches.value_of("asof_tolerance") {
+                None => None,
+                Some(tolerance) => Some(tolerance.parse::<Interval>()?.as_nanos()),
+            };
+            let prefixes = match matches.value_of("asof_prefixes") {
+                None => Vec::new(),
+                Some(prefixes) => prefixes.split(',').map(|p| p.to_string()).collect(),
+            };
+            Some(AsOfJoin::new(
+                inputs.len(),
+                matches.value_of("asof_key"),
+                tolerance,
+                prefixes,
+            )?)
+        }
+        // source column of a union comes from --tag-source
+        _ if matches.is_present("union") => Some(MergeJoin::new_union(inputs.len(), None)?),
+        _ => Some(MergeJoin::new(inputs.len())?),
+    };
+
+    // processing nodes of each input, before inputs are merged
+    let mut input_transforms: Vec<Vec<Box<dyn DynHeaderSink>>> =
+        inputs.iter().map(|_| Vec::new()).collect();
+    if matches.is_present("tag_source") {
+        let column_name = matches.value_of("tag_source").unwrap_or("source");
+        let tag_value = match matches.value_of("tag_labels") {
+            Some(labels) => TagValue::Labels(labels.split(',').map(|l| l.to_string()).collect()),
+            None => matches.value_of("tag_value").unwrap().parse::<TagValue>()?,
+        };
+        for (transforms, value) in input_transforms.iter_mut().zip(tag_value.values(&inputs)?) {
+            transforms.push(ColumnTag::new(column_name, &value));
+        }
+    }
+    let inputs = inputs.into_iter().zip(input_transforms).collect();
+
+    let driver = setup_graph(
         inputs,
         output,
-        streaming_transports,
-        source_factories,
         timestamp_range,
-        csv_input_config,
-        csv_output_config,
-        dc_factory,
-    )
+        input_factory_builder,
+        merge,
+        transforms,
+        output_factory,
+    )?;
+    finish_driver(&matches, driver.with_tie_break_policy(tie_break_policy))
+}
+
+/// with --explain, the graph is printed instead of being driven
+fn finish_driver(matches: &ArgMatches, driver: Driver) -> ChopperResult<Box<dyn ChopperDriver>> {
+    if !matches.is_present("explain") {
+        return Ok(Box::new(driver));
+    }
+    let format = match matches.value_of("explain") {
+        None => ExplainFormat::default(),
+        Some(format) => format.parse::<ExplainFormat>()?,
+    };
+    Ok(Explain::new(driver, format))
 }
 
 fn setup_graph(
-    inputs: Vec<Input>,
+    inputs: Vec<(Input, Vec<Box<dyn DynHeaderSink>>)>,
     output: Option<&str>,
-    streaming_transports: Option<Vec<Box<dyn StreamingTransport>>>,
-    source_factories: Option<Vec<Box<dyn SourceFactory>>>,
     timestamp_range: TimestampRange,
-    csv_input_config: CSVInputConfig,
-    csv_output_config: CSVOutputConfig,
-    dc_factory: Option<DCFactory>,
-) -> ChopperResult<Box<dyn ChopperDriver>> {
+    input_factory_builder: InputFactoryBuilder,
+    merge: Option<Box<dyn MergeHeaderSink>>,
+    transforms: Vec<Box<dyn DynHeaderSink>>,
+    output_factory: OutputFactory,
+) -> ChopperResult<Driver> {
     // get sources and headers
     let mut sources: Vec<Box<dyn Source>> = Vec::new();
     let mut headers: Vec<Header> = Vec::new();
-    let mut input_factory = InputFactoryBuilder::new()
-        .with_csv_input_config(csv_input_config)
-        .with_user_source_factories(source_factories)
-        .with_user_streaming_transports(streaming_transports)
-        .with_dc_factory(dc_factory.clone())
-        .build()?;
+    let mut input_factory = input_factory_builder.build()?;
 
     let mut header_nodes: Vec<HeaderNode> = Vec::new();
     let mut chains: Vec<HeaderChain> = Vec::new();
 
-    for input in &inputs {
+    let input_count = inputs.len();
+    for (input, input_transforms) in inputs {
         let source = input_factory.create_source_from_input(&input)?;
         headers.push(source.header().clone());
         sources.push(source);
 
+        let mut input_nodes: Vec<HeaderNode> = input_transforms
+            .into_iter()
+            .map(HeaderNode::HeaderSink)
+            .collect();
+
         // add Merge to chains if multiple input files;
         // there is one chain per input file with chain ids from 0 to (inputs.len()-1);
         // there is going to be last chain with id (inputs.len()) added later,
         // where the sink goes, so all the inputs will be merged into this last sink chain,
-        // hence inputs.len() as target chain id for the merge
-        if inputs.len() > 1 {
-            let merge = HeaderNode::Merge(inputs.len());
-            let chain = HeaderChain::new(vec![merge]);
-            chains.push(chain);
+        // hence inputs.len() as target chain id for the merge;
+        // with a single input, its processing nodes go first in the sink chain
+        if input_count > 1 {
+            input_nodes.push(HeaderNode::Merge(input_count));
+            chains.push(HeaderChain::new(input_nodes));
+        } else {
+            header_nodes.append(&mut input_nodes);
         }
     }
 
     // add MergeHeaderSink as first header node if multiple input files
-    if inputs.len() > 1 {
-        let merge = MergeJoin::new(inputs.len())?;
+    if let Some(merge) = merge {
         let header_count_tracker = merge.get_new_header_count_tracker();
         let node_merge_sink = HeaderNode::MergeHeaderSink(merge, header_count_tracker);
         header_nodes.push(node_merge_sink);
     }
 
-    let output_factory = OutputFactory::new()
-        .with_csv_output_config(csv_output_config)
-        .with_dc_factory(dc_factory);
+    for transform in transforms {
+        header_nodes.push(HeaderNode::HeaderSink(transform));
+    }
+
     let header_sink = output_factory.new_header_sink(output)?;
     let node_hs = HeaderNode::HeaderSink(header_sink);
     header_nodes.push(node_hs);
     chains.push(HeaderChain::new(header_nodes));
     let graph = HeaderGraph::new(chains);
 
-    Ok(Box::new(Driver::new(
-        sources,
-        graph,
-        timestamp_range,
-        headers,
-    )?))
+    Driver::new(sources, graph, timestamp_range, headers)
 }
 
 fn parse_csv_input_config(
@@ -221,6 +374,22 @@ fn parse_csv_input_config(
 ) -> ChopperResult<CSVInputConfig> {
     let input_delimiter = matches.value_of("csv_in_delimiter");
     let has_header = value_t!(matches, "csv_in_has_header", YesNoAuto)?;
+    let infer_types = matches.is_present("csv_in_infer_types");
+    let infer_types_row_count = match matches.is_present("csv_in_infer_rows") {
+        false => None,
+        true => Some(value_t!(matches.value_of("csv_in_infer_rows"), usize)?),
+    };
+    let cell_parse_error_policy = match matches.is_present("csv_in_parse_error") {
+        false => None,
+        true => Some(value_t!(
+            matches.value_of("csv_in_parse_error"),
+            CellParseErrorPolicy
+        )?),
+    };
+    let schema = match matches.value_of("csv_in_schema_file") {
+        None => matches.value_of("csv_in_schema").map(|s| s.to_string()),
+        Some(path) => Some(fs::read_to_string(path)?),
+    };
 
     let ts_fmt = match matches.value_of("csv_in_ts_fmt_date") {
         None => match matches.value_of("csv_in_ts_fmt") {
@@ -265,12 +434,98 @@ fn parse_csv_input_config(
 
     Ok(CSVInputConfig::new(ts_config)
         .with_delimiter(input_delimiter)?
-        .with_header(has_header))
+        .with_header(has_header)
+        .with_schema(schema.as_deref())?
+        .with_type_inference(infer_types, infer_types_row_count)
+        .with_cell_parse_error_policy(cell_parse_error_policy))
+}
+
+fn parse_columnar_input_config(matches: &ArgMatches) -> ColumnarInputConfig {
+    let ts_col = match matches.value_of("columnar_in_ts_col") {
+        None => TimestampColConfig::Auto,
+        Some(ts) => match ts.parse::<usize>() {
+            Ok(i) => TimestampColConfig::Index(i),
+            Err(_) => TimestampColConfig::Name(ts.to_string()),
+        },
+    };
+    ColumnarInputConfig::new().with_timestamp_col(ts_col)
 }
 
-fn parse_csv_output_config(matches: &ArgMatches, timezone: ChopperTz) -> CSVOutputConfig {
+fn parse_json_input_config(
+    matches: &ArgMatches,
+    timezone: ChopperTz,
+) -> ChopperResult<JsonInputConfig> {
+    let ts_col = match matches.value_of("json_in_ts_col") {
+        None => TimestampColConfig::Auto,
+        Some(key) => TimestampColConfig::Name(key.to_string()),
+    };
+    let ts_fmt = match matches.value_of("json_in_ts_fmt") {
+        None => TimestampFmtConfig::Auto,
+        Some(fmt) => TimestampFmtConfig::Explicit(fmt.to_string()),
+    };
+    let header_row_count = match matches.is_present("json_in_header_rows") {
+        false => None,
+        true => Some(value_t!(matches.value_of("json_in_header_rows"), usize)?),
+    };
+    let cell_parse_error_policy = match matches.is_present("csv_in_parse_error") {
+        false => None,
+        true => Some(value_t!(
+            matches.value_of("csv_in_parse_error"),
+            CellParseErrorPolicy
+        )?),
+    };
+    let ts_config = TimestampConfig::new(ts_col, ts_fmt, timezone);
+    Ok(JsonInputConfig::new(ts_config)
+        .with_header_row_count(header_row_count)
+        .with_cell_parse_error_policy(cell_parse_error_policy))
+}
+
+fn parse_csv_output_config(
+    matches: &ArgMatches,
+    timezone: ChopperTz,
+) -> ChopperResult<CSVOutputConfig> {
     let csv_out_delimiter = matches.value_of("csv_out_delimiter").unwrap();
-    let csv_out_print_time_col = match matches.value_of("csv_out_print_time_col").unwrap() {
+    let csv_out_quote = csv_util::parse_into_delimiter(matches.value_of("csv_out_quote").unwrap())?;
+    let csv_out_quote_style = value_t!(matches, "csv_out_quote_style", QuoteStyle)?;
+    let csv_out_line_terminator = matches
+        .value_of("csv_out_line_terminator")
+        .unwrap()
+        .replace(r"\r", "\r")
+        .replace(r"\n", "\n")
+        .replace(r"\t", "\t");
+    let (csv_out_print_time_col, time_col_name, time_col_style, time_col_units) =
+        parse_output_time_col(matches);
+
+    Ok(CSVOutputConfig::new(
+        csv_out_delimiter,
+        csv_out_print_time_col,
+        time_col_name,
+        time_col_style,
+        time_col_units,
+        timezone,
+    )
+    .with_quote(csv_out_quote)
+    .with_quote_style(csv_out_quote_style)
+    .with_line_terminator(&csv_out_line_terminator))
+}
+
+fn parse_json_output_config(matches: &ArgMatches, timezone: ChopperTz) -> JsonOutputConfig {
+    let (print_time_col, time_col_name, time_col_style, time_col_units) =
+        parse_output_time_col(matches);
+    JsonOutputConfig::new(
+        print_time_col,
+        time_col_name,
+        time_col_style,
+        time_col_units,
+        timezone,
+    )
+}
+
+/// time column settings are shared by all the text output formats
+fn parse_output_time_col(
+    matches: &ArgMatches,
+) -> (bool, Option<String>, TimestampStyle, TimestampUnits) {
+    let print_time_col = match matches.value_of("csv_out_print_time_col").unwrap() {
         "yes" => true,
         "no" => false,
         _ => unreachable!(),
@@ -292,12 +547,10 @@ fn parse_csv_output_config(matches: &ArgMatches, timezone: ChopperTz) -> CSVOutp
         Some(units) => TimestampUnits::from_str(units),
     };
 
-    CSVOutputConfig::new(
-        csv_out_delimiter,
-        csv_out_print_time_col,
+    (
+        print_time_col,
         time_col_name,
         time_col_style,
         time_col_units,
-        timezone,
     )
 }
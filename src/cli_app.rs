@@ -1,5 +1,5 @@
 use clap::crate_version;
-use clap::{App, Arg};
+use clap::{App, Arg, ArgGroup};
 
 pub struct CliApp;
 
@@ -21,6 +21,81 @@ impl CliApp {
                     .takes_value(true)
                     .value_name("file"),
             )
+            .arg(
+                Arg::with_name("pipeline")
+                    .long("pipeline")
+                    .help(
+                        "run the processing graph described by a .toml or .yaml file, with \
+                        named inputs, and named nodes that filter, project, aggregate, merge, \
+                        route and write rows; inputs and outputs come from the file, so it \
+                        cannot be used with input files, --output or the processing options",
+                    )
+                    .takes_value(true)
+                    .conflicts_with_all(&[
+                        "input",
+                        "output",
+                        "partition",
+                        "format",
+                        "filter",
+                        "columns",
+                        "drop",
+                        "rename",
+                        "rolling",
+                        "resample",
+                        "group_by",
+                        "tag_source",
+                        "union",
+                        "asof",
+                    ])
+                    .value_name("file"),
+            )
+            .arg(
+                Arg::with_name("explain")
+                    .long("explain")
+                    .help(
+                        "print chains of the processing graph, with the columns coming into \
+                        each node, instead of processing any rows; format is 'text' or 'dot' \
+                        for graphviz, defaulting to text; no output is written",
+                    )
+                    .takes_value(true)
+                    .min_values(0)
+                    .require_equals(true)
+                    .possible_values(&["text", "dot"])
+                    .value_name("format"),
+            )
+            .arg(
+                Arg::with_name("output_compression")
+                    .long("output-compression")
+                    .help("compress output, e.g. when writing to stdout; \
+                    compression suffix of the output file name takes precedence")
+                    .takes_value(true)
+                    .possible_values(&["gz", "lz4", "zst"])
+                    .value_name("arg"),
+            )
+            .arg(
+                Arg::with_name("partition")
+                    .long("partition")
+                    .help(
+                        "write rows into multiple files, with output file name being a \
+                        template such as out/{sym}/{date:%Y%m%d}.csv, where {column} is the \
+                        value of that column and {date:format} is the row timestamp formatted \
+                        as per strftime in --timezone; {date} is {date:%Y%m%d}",
+                    )
+                    .takes_value(false)
+                    .requires("output"),
+            )
+            .arg(
+                Arg::with_name("max_open_files")
+                    .long("max-open-files")
+                    .help(
+                        "max number of files --partition keeps open; when a closed file gets \
+                        more rows it is appended to, which only works for uncompressed csv \
+                        and json lines",
+                    )
+                    .takes_value(true)
+                    .default_value("64")
+                    .value_name("count"),
+            )
             .arg(
                 Arg::with_name("format")
                     .short("f")
@@ -38,6 +113,256 @@ impl CliApp {
                     .require_delimiter(true)
                     .value_name("f1[,f2[,etc]]"),
             )
+            .arg(
+                Arg::with_name("filter")
+                    .long("filter")
+                    .help(
+                        "only output rows for which expression is true, \
+                        e.g. 'price > 10 && sym in (\"A\", \"B\")'; supports arithmetic, \
+                        comparisons, and/or/not, in lists, regex match with =~ and !~, \
+                        is null and is not null; @timestamp is row timestamp in nanos, \
+                        and `name` is a column whose name is not a plain identifier",
+                    )
+                    .takes_value(true)
+                    .value_name("expression"),
+            )
+            .arg(
+                Arg::with_name("columns")
+                    .long("columns")
+                    .help(
+                        "output only these columns in this order; comma-separated list of \
+                        names, 0-based indexes, inclusive index ranges like 2-5 or 2-, \
+                        and glob patterns like bid_*; items starting with ! exclude columns \
+                        selected so far, or all columns if the list starts with one; \
+                        applied after --filter",
+                    )
+                    .takes_value(true)
+                    .value_name("list"),
+            )
+            .arg(
+                Arg::with_name("drop")
+                    .long("drop")
+                    .help("do not output these columns; same list format as --columns")
+                    .takes_value(true)
+                    .value_name("list"),
+            )
+            .arg(
+                Arg::with_name("rename")
+                    .long("rename")
+                    .help("rename output columns; comma-separated list of old=new pairs")
+                    .takes_value(true)
+                    .value_name("list"),
+            )
+            .arg(
+                Arg::with_name("rolling")
+                    .long("rolling")
+                    .help(
+                        "append --rolling-stats columns computed over a window of this many rows, \
+                        e.g. 20, or of this much time, e.g. 5m; time units are ns/us/ms/s/m/h/d; \
+                        applied after --filter and column options, before --resample and --group-by",
+                    )
+                    .takes_value(true)
+                    .requires("rolling_stats")
+                    .value_name("window"),
+            )
+            .arg(
+                Arg::with_name("rolling_stats")
+                    .long("rolling-stats")
+                    .help(
+                        "statistics appended by --rolling; comma-separated list of column:stat \
+                        pairs, e.g. px:mean,px:stddev; stats are mean, sum, min, max, stddev, \
+                        ewma and count; new columns are named like px_mean_5m",
+                    )
+                    .takes_value(true)
+                    .requires("rolling")
+                    .value_name("list"),
+            )
+            .arg(
+                Arg::with_name("rolling_key")
+                    .long("rolling-key")
+                    .help("compute --rolling windows separately for each value of this column")
+                    .takes_value(true)
+                    .requires("rolling")
+                    .value_name("column"),
+            )
+            .arg(
+                Arg::with_name("resample")
+                    .long("resample")
+                    .help(
+                        "output one row per time bucket of this width, e.g. 500ms, 5m or 1d; \
+                        units are ns/us/ms/s/m/h/d; day buckets start at midnight in --timezone; \
+                        applied after --filter and column options",
+                    )
+                    .takes_value(true)
+                    .requires("agg")
+                    .value_name("interval"),
+            )
+            .arg(
+                Arg::with_name("group_by")
+                    .long("group-by")
+                    .help(
+                        "output one row per distinct combination of values of these columns, \
+                        with these columns followed by --agg aggregates; comma-separated list \
+                        of column names; rows are output at the end unless --group-every is given; \
+                        applied after --filter and column options",
+                    )
+                    .takes_value(true)
+                    .requires("agg")
+                    .value_name("list"),
+            )
+            .arg(
+                Arg::with_name("group_every")
+                    .long("group-every")
+                    .help(
+                        "output --group-by rows whenever a time period of this length ends \
+                        and start over; same format as --resample interval",
+                    )
+                    .takes_value(true)
+                    .requires("group_by")
+                    .value_name("interval"),
+            )
+            .arg(
+                Arg::with_name("agg")
+                    .long("agg")
+                    .help(
+                        "aggregates output by --resample or --group-by; comma-separated list of \
+                        column:aggregate pairs, e.g. px:ohlc,qty:sum; aggregates are first, last, \
+                        min, max, sum, count, mean and ohlc",
+                    )
+                    .takes_value(true)
+                    .requires("aggregation")
+                    .value_name("list"),
+            )
+            // --agg needs exactly one of these
+            .group(ArgGroup::with_name("aggregation").args(&["resample", "group_by"]))
+            .arg(
+                Arg::with_name("resample_fill")
+                    .long("resample-fill")
+                    .help(
+                        "what to output for time buckets without rows; 'none' skips them, \
+                        'null' outputs nulls, 'previous' repeats last values; \
+                        sum and count are always 0 [default: none]",
+                    )
+                    .takes_value(true)
+                    .possible_values(&["none", "null", "previous"])
+                    .requires("resample")
+                    .value_name("policy"),
+            )
+            .arg(
+                Arg::with_name("tag_source")
+                    .long("tag-source")
+                    .visible_alias("source-column")
+                    .help(
+                        "add a column with the input each row came from, before inputs are \
+                        merged; column name defaults to 'source'",
+                    )
+                    .takes_value(true)
+                    .min_values(0)
+                    .require_equals(true)
+                    .value_name("name"),
+            )
+            .arg(
+                Arg::with_name("tag_value")
+                    .long("tag-value")
+                    .help(
+                        "what --tag-source puts in its column; 'path' is the input path, \
+                        'stem' is the file name without its last extension",
+                    )
+                    .takes_value(true)
+                    .possible_values(&["path", "stem"])
+                    .default_value("path")
+                    .value_name("value"),
+            )
+            .arg(
+                Arg::with_name("tag_labels")
+                    .long("tag-labels")
+                    .help(
+                        "labels that --tag-source puts in its column instead of paths, \
+                        one per input, in input order; comma-separated list",
+                    )
+                    .takes_value(true)
+                    .requires("tag_source")
+                    .value_name("list"),
+            )
+            .arg(
+                Arg::with_name("union")
+                    .long("union")
+                    .help(
+                        "interleave inputs with different columns; output has every column \
+                        of every input, matched by name, and values of columns that an input \
+                        doesn't have are null; integer columns of different sizes are widened, \
+                        and integer and floating point columns become double",
+                    )
+                    .takes_value(false)
+                    .conflicts_with("asof"),
+            )
+            .arg(
+                Arg::with_name("asof")
+                    .long("asof")
+                    .help(
+                        "instead of interleaving inputs, output each row of the first input \
+                        followed by columns of the latest row of every other input whose \
+                        timestamp is not later; values are null if there is no such row",
+                    )
+                    .takes_value(false),
+            )
+            .arg(
+                Arg::with_name("asof_key")
+                    .long("asof-key")
+                    .help("--asof joins only rows with equal values of this column")
+                    .takes_value(true)
+                    .requires("asof")
+                    .value_name("column"),
+            )
+            .arg(
+                Arg::with_name("asof_tolerance")
+                    .long("asof-tolerance")
+                    .help(
+                        "--asof joins only rows that are at most this much earlier, e.g. 500ms; \
+                        units are ns/us/ms/s/m/h/d",
+                    )
+                    .takes_value(true)
+                    .requires("asof")
+                    .value_name("interval"),
+            )
+            .arg(
+                Arg::with_name("asof_prefixes")
+                    .long("asof-prefixes")
+                    .help(
+                        "prefixes of column names of the inputs other than the first one, \
+                        in input order, for --asof; comma-separated list; default is \
+                        in1_,in2_,...",
+                    )
+                    .takes_value(true)
+                    .requires("asof")
+                    .value_name("list"),
+            )
+            .arg(
+                Arg::with_name("tie_break")
+                    .long("tie-break")
+                    .help(
+                        "order of rows with equal timestamps from different inputs; \
+                        'input' outputs earlier inputs first, 'reverse' outputs later inputs first, \
+                        'round-robin' makes inputs take turns, 'col:<name>' orders by the named \
+                        column and then by input order",
+                    )
+                    .takes_value(true)
+                    .default_value("input")
+                    .value_name("policy"),
+            )
+            .arg(
+                Arg::with_name("out_of_order")
+                    .long("out-of-order")
+                    .help(
+                        "what to do when timestamps of an input go backwards; \
+                        'error' fails, 'drop' skips such rows, 'clamp' sets their timestamp \
+                        to the previous one, 'reorder:<lateness>' sorts rows that are at most \
+                        lateness late, e.g. reorder:500ms, with lateness in the same format \
+                        as --resample; by default timestamps are not checked",
+                    )
+                    .takes_value(true)
+                    .value_name("policy"),
+            )
             .arg(
                 Arg::with_name("timezone")
                     .short("z")
@@ -76,7 +401,7 @@ impl CliApp {
             .arg(
                 Arg::with_name("csv_out_time_col_name")
                     .long("time-col-name")
-                    .help("csv output only: name override for the auto-generated time column; \
+                    .help("csv and json output only: name override for the auto-generated time column; \
                     default based on time column output format")
                     .takes_value(true)
             )
@@ -84,7 +409,7 @@ impl CliApp {
                 Arg::with_name("csv_out_time_fmt_epoch")
                     .short("E")
                     .long("epoch")
-                    .help("csv output only: time column has epoch timestamps")
+                    .help("csv and json output only: time column has epoch timestamps")
                     .takes_value(false)
                     .conflicts_with("human")
             )
@@ -92,7 +417,7 @@ impl CliApp {
                 Arg::with_name("csv_out_time_fmt_human")
                     .short("H")
                     .long("human")
-                    .help("csv output only: time column is human-readable; default")
+                    .help("csv and json output only: time column is human-readable; default")
                     .takes_value(false)
                     .conflicts_with("epoch")
             )
@@ -100,7 +425,7 @@ impl CliApp {
                 Arg::with_name("csv_out_time_col_units")
                     .short("g")
                     .long("granularity")
-                    .help("csv output only: units of the auto-generated time column")
+                    .help("csv and json output only: units of the auto-generated time column")
                     .takes_value(true)
                     .default_value("ns")
                     .possible_values(&["s", "ms", "us", "ns"])
@@ -121,6 +446,33 @@ impl CliApp {
                     .default_value(",")
                     .value_name("arg"),
             )
+            .arg(
+                Arg::with_name("csv_out_quote")
+                    .long("quote")
+                    .help("csv output only: quote character")
+                    .takes_value(true)
+                    .default_value("\"")
+                    .value_name("arg"),
+            )
+            .arg(
+                Arg::with_name("csv_out_quote_style")
+                    .long("quote-style")
+                    .help("csv output only: which values to quote; values containing delimiter, \
+                    quote or line break characters are always quoted")
+                    .takes_value(true)
+                    .default_value("minimal")
+                    .possible_values(&["minimal", "all", "nonnumeric"])
+                    .case_insensitive(true)
+                    .value_name("arg"),
+            )
+            .arg(
+                Arg::with_name("csv_out_line_terminator")
+                    .long("line-terminator")
+                    .help("csv output only: line terminator; \\r, \\n and \\t escapes are supported")
+                    .takes_value(true)
+                    .default_value("\\n")
+                    .value_name("arg"),
+            )
             .arg(
                 Arg::with_name("csv_in_has_header")
                     .long("csv-in-has-header")
@@ -134,7 +486,7 @@ impl CliApp {
             .arg(
                 Arg::with_name("csv_out_print_time_col")
                     .long("print-time")
-                    .help("csv output only: print time as first column")
+                    .help("csv and json output only: print time as first column")
                     .takes_value(true)
                     .default_value("yes")
                     .possible_values(&["yes", "no"])
@@ -207,7 +559,144 @@ impl CliApp {
                 .possible_values(&["s", "ms", "us", "ns"])
                 .value_name("arg")
                 .conflicts_with_all(&["csv_in_ts_col_date", "csv_in_ts_col_time", "csv_in_ts_fmt"])
+            )
+            .arg(Arg::with_name("csv_in_infer_types")
+                .long("csv-in-infer-types")
+                .help("csv input only: guess long/double/bool column types from leading rows \
+                instead of reading all columns as strings")
+                .takes_value(false)
+            )
+            .arg(Arg::with_name("csv_in_infer_rows")
+                .long("csv-in-infer-rows")
+                .help("csv input only: number of leading rows to use for guessing column types \
+                [default: rows that fit into the preview buffer]")
+                .takes_value(true)
+                .value_name("n")
+                .requires("csv_in_infer_types")
+            )
+            .arg(Arg::with_name("csv_in_schema")
+                .long("csv-in-schema")
+                .help("csv input only: declare column types using dc type names, \
+                e.g. 'price:f64,qty:i64,sym:utf8str'; supported types: bool, u8, i16, i32, i64, \
+                f32, f64, u16char, utf8str, [u8]; undeclared columns are read as strings \
+                or guessed if --csv-in-infer-types is set")
+                .takes_value(true)
+                .value_name("name:type[,etc]")
+                .conflicts_with("csv_in_schema_file")
+            )
+            .arg(Arg::with_name("csv_in_schema_file")
+                .long("csv-in-schema-file")
+                .help("csv input only: same as --csv-in-schema, but declarations are read \
+                from a file; declarations can be on separate lines and lines starting with # \
+                are ignored")
+                .takes_value(true)
+                .value_name("file")
+            )
+            .arg(Arg::with_name("csv_in_parse_error")
+                .long("csv-in-parse-error")
+                .help("csv and json input: what to do when a value doesn't parse into its column type \
+                [default: error for declared column types, null for guessed column types]")
+                .takes_value(true)
+                .possible_values(&["null", "error"])
+                .case_insensitive(true)
+                .value_name("arg")
+            )
+            .arg(Arg::with_name("columnar_in_ts_col")
+                .long("columnar-in-ts-col")
+                .help("parquet and arrow input only: specify the timestamp column name or index; \
+                will use first column of timestamp type, then try obvious names; \
+                integer columns are read as epoch nanos unless name ends with units, e.g. timeMillis")
+                .takes_value(true)
+                .value_name("arg")
+            )
+            .arg(Arg::with_name("json_in_ts_col")
+                .long("json-in-ts-col")
+                .help("json input only: specify the timestamp key; \
+                will try to guess using obvious names, then fall back to the first key")
+                .takes_value(true)
+                .value_name("key")
+            )
+            .arg(Arg::with_name("json_in_ts_fmt")
+                .long("json-in-ts-fmt")
+                .help("json input only: specify the timestamp format, same as --csv-in-ts-fmt")
+                .takes_value(true)
+                .value_name("format")
+            )
+            .arg(Arg::with_name("json_in_header_rows")
+                .long("json-in-header-rows")
+                .help("json input only: number of leading objects to collect keys and guess value \
+                types from [default: objects that fit into the preview buffer]")
+                .takes_value(true)
+                .value_name("n")
             );
         app
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use clap::value_t;
+
+    use crate::cli_app::CliApp;
+    use crate::write::csv_output_config::QuoteStyle;
+
+    fn parse(args: &[&str]) -> clap::Result<()> {
+        let args = std::iter::once("chopper").chain(args.iter().cloned());
+        CliApp
+            .create_cli_app()
+            .get_matches_from_safe(args)
+            .map(|_| ())
+    }
+
+    #[test]
+    fn test_aggregation_args() {
+        assert!(parse(&["in.csv"]).is_ok());
+        assert!(parse(&["in.csv", "--resample", "5m", "--agg", "px:last"]).is_ok());
+        assert!(parse(&["in.csv", "--group-by", "sym", "--agg", "px:last"]).is_ok());
+        assert!(parse(&["in.csv", "--agg", "px:last"]).is_err());
+        assert!(parse(&["in.csv", "--resample", "5m"]).is_err());
+        assert!(parse(&["in.csv", "--resample-fill", "null"]).is_err());
+        let args = [
+            "in.csv",
+            "--resample",
+            "5m",
+            "--agg",
+            "px:sum",
+            "--resample-fill",
+            "null",
+        ];
+        assert!(parse(&args).is_ok());
+        let args = [
+            "in.csv",
+            "--resample",
+            "5m",
+            "--group-by",
+            "sym",
+            "--agg",
+            "px:sum",
+        ];
+        assert!(parse(&args).is_err());
+    }
+
+    #[test]
+    fn test_quote_style() {
+        let args = ["chopper", "in.csv", "--quote-style", "NonNumeric"];
+        let matches = CliApp.create_cli_app().get_matches_from(args.iter());
+        let quote_style = value_t!(matches, "csv_out_quote_style", QuoteStyle).unwrap();
+        assert_eq!(quote_style, QuoteStyle::NonNumeric);
+        assert!(parse(&["in.csv", "--quote-style", "numeric"]).is_err());
+    }
+
+    #[test]
+    fn test_source_column_is_tag_source() {
+        let args = [
+            "chopper",
+            "a.csv",
+            "b.csv",
+            "--union",
+            "--source-column=src",
+        ];
+        let matches = CliApp.create_cli_app().get_matches_from(args.iter());
+        assert_eq!(matches.value_of("tag_source"), Some("src"));
+    }
+}
@@ -0,0 +1,164 @@
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use lz4_flex::frame::FrameEncoder;
+
+use crate::chopper::error::{ChopperResult, Error};
+
+static GZ: &str = ".gz";
+static LZ4: &str = ".lz4";
+static ZST: &str = ".zst";
+
+/// subset of decompression formats that can be written; lz4 is always frame format
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompressionFormat {
+    GZ,
+    LZ4,
+    ZST,
+}
+
+impl FromStr for CompressionFormat {
+    type Err = Error;
+
+    fn from_str(format: &str) -> ChopperResult<CompressionFormat> {
+        match format {
+            "gz" => Ok(CompressionFormat::GZ),
+            "lz4" => Ok(CompressionFormat::LZ4),
+            "zst" => Ok(CompressionFormat::ZST),
+            _ => Err(Error::from(format!(
+                "compression -- {} is not supported",
+                format
+            ))),
+        }
+    }
+}
+
+pub fn is_compressed_using_format(format: &str) -> Option<(CompressionFormat, String)> {
+    if let Some(stripped) = format.strip_suffix(GZ) {
+        return Some((CompressionFormat::GZ, stripped.to_owned()));
+    }
+    if let Some(stripped) = format.strip_suffix(LZ4) {
+        return Some((CompressionFormat::LZ4, stripped.to_owned()));
+    }
+    if let Some(stripped) = format.strip_suffix(ZST) {
+        return Some((CompressionFormat::ZST, stripped.to_owned()));
+    }
+    None
+}
+
+enum Encoder<W: Write> {
+    Gz(GzEncoder<W>),
+    Lz4(FrameEncoder<W>),
+    Zst(zstd::Encoder<'static, W>),
+}
+
+/// compressing writer; trailer is written by finish, which returns any error instead of
+/// losing it the way encoders that finish on drop do; dropping an unfinished writer still
+/// finishes it, but ignores errors
+pub struct CompressedWriter<W: Write> {
+    encoder: Encoder<W>,
+    finished: bool,
+}
+
+impl<W: Write> CompressedWriter<W> {
+    /// writes the trailer; nothing can be written afterwards
+    pub fn finish(&mut self) -> io::Result<()> {
+        if self.finished {
+            return Ok(());
+        }
+        self.finished = true;
+        match &mut self.encoder {
+            Encoder::Gz(encoder) => encoder.try_finish(),
+            Encoder::Lz4(encoder) => Ok(encoder.try_finish()?),
+            Encoder::Zst(encoder) => encoder.do_finish(),
+        }
+    }
+
+    fn encoder(&mut self) -> io::Result<&mut dyn Write> {
+        if self.finished {
+            return Err(io::Error::other(
+                "compression -- stream is already finished",
+            ));
+        }
+        Ok(match &mut self.encoder {
+            Encoder::Gz(encoder) => encoder,
+            Encoder::Lz4(encoder) => encoder,
+            Encoder::Zst(encoder) => encoder,
+        })
+    }
+}
+
+impl<W: Write> Write for CompressedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.encoder()?.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.finished {
+            true => Ok(()),
+            false => self.encoder()?.flush(),
+        }
+    }
+}
+
+impl<W: Write> Drop for CompressedWriter<W> {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+pub fn compress<W: Write>(
+    compression_format: CompressionFormat,
+    writer: W,
+) -> ChopperResult<CompressedWriter<W>> {
+    let encoder = match compression_format {
+        CompressionFormat::GZ => Encoder::Gz(GzEncoder::new(writer, Compression::default())),
+        CompressionFormat::LZ4 => Encoder::Lz4(FrameEncoder::new(writer)),
+        CompressionFormat::ZST => Encoder::Zst(zstd::Encoder::new(writer, 0)?),
+    };
+    Ok(CompressedWriter {
+        encoder,
+        finished: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read, Write};
+
+    use crate::compress::compress::{compress, CompressionFormat};
+    use crate::decompress::decompress;
+    use crate::util::reader::ChopperBufPreviewer;
+
+    #[test]
+    fn test_round_trip() {
+        let formats = vec![
+            CompressionFormat::GZ,
+            CompressionFormat::LZ4,
+            CompressionFormat::ZST,
+        ];
+        let data = "a,b\n1,2\n".repeat(1000);
+        for format in formats {
+            let path = std::env::temp_dir().join(format!("chopper_test_compress_{:?}", format));
+            {
+                let mut writer = compress(format, std::fs::File::create(&path).unwrap()).unwrap();
+                writer.write_all(data.as_bytes()).unwrap();
+                writer.finish().unwrap();
+                assert!(writer.write_all(data.as_bytes()).is_err());
+            }
+            let compressed = std::fs::read(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+
+            let reader: Box<dyn Read> = Box::new(Cursor::new(compressed));
+            let previewer = ChopperBufPreviewer::new(reader).unwrap();
+            let decompression_format =
+                decompress::is_compressed_using_previewer(&previewer).unwrap();
+            let mut reader = decompress::decompress(decompression_format, previewer).unwrap();
+            let mut output = String::new();
+            reader.read_to_string(&mut output).unwrap();
+            assert_eq!(output, data);
+        }
+    }
+}
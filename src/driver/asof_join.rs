@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+
+use crate::chopper::error::{ChopperResult, Error};
+use crate::chopper::header_graph::HeaderCountTracker;
+use crate::chopper::sink::{DataSink, MergeHeaderSink};
+use crate::chopper::types::{FieldType, FieldValue, HashableFieldValue, Header, Nanos, Row};
+
+/// joins each row of the first merged input with the most recent rows of the other inputs
+pub struct AsOfJoin {
+    merge_source_count: usize,
+    key: Option<String>,
+    tolerance: Option<Nanos>,
+    prefixes: Vec<String>,
+    headers: Vec<Header>,
+    /// per input, index of the key column
+    key_indexes: Vec<usize>,
+    /// per secondary input, latest row for each key; without key column everything is under
+    /// null key
+    latest_rows: Vec<HashMap<HashableFieldValue, Row>>,
+    /// rows of the first input that have the timestamp of the last row seen; they are held back
+    /// until a later row shows up, so that rows of other inputs with the same timestamp are
+    /// joined regardless of the order in which inputs with equal timestamps are merged
+    pending_rows: Vec<Row>,
+}
+
+impl AsOfJoin {
+    /// first input is the primary one and every other input is secondary; output has a row for
+    /// each primary row, with primary columns followed by columns of each secondary input,
+    /// whose names are prefixed with the corresponding prefix; prefixes default to in1_, in2_,
+    /// etc.; values of a secondary input come from its latest row with a timestamp that is not
+    /// later than that of the primary row, and, if given, not earlier by more than tolerance
+    /// and with the same value of key column; they are null if there is no such row
+    pub fn new(
+        merge_source_count: usize,
+        key: Option<&str>,
+        tolerance: Option<Nanos>,
+        prefixes: Vec<String>,
+    ) -> ChopperResult<Box<dyn MergeHeaderSink>> {
+        if merge_source_count < 2 {
+            return Err(Error::from(
+                "AsOfJoin -- number of inputs must be at least 2",
+            ));
+        }
+        let prefixes = match prefixes.is_empty() {
+            true => (1..merge_source_count)
+                .map(|i| format!("in{}_", i))
+                .collect(),
+            false => prefixes,
+        };
+        if prefixes.len() != merge_source_count - 1 {
+            return Err(Error::from(format!(
+                "AsOfJoin -- expected {} prefixes, one per secondary input, got {}",
+                merge_source_count - 1,
+                prefixes.len()
+            )));
+        }
+        let join = AsOfJoin {
+            merge_source_count,
+            key: key.map(|key| key.to_string()),
+            tolerance,
+            prefixes,
+            headers: Vec::new(),
+            key_indexes: Vec::new(),
+            latest_rows: vec![HashMap::new(); merge_source_count - 1],
+            pending_rows: Vec::new(),
+        };
+        Ok(Box::new(join) as Box<dyn MergeHeaderSink>)
+    }
+
+    fn key_value(&self, input_index: usize, row: &Row) -> HashableFieldValue {
+        HashableFieldValue(match self.key {
+            None => FieldValue::None,
+            Some(_) => row.field_values[self.key_indexes[input_index]].clone(),
+        })
+    }
+
+    fn output_pending_rows(&mut self, io_rows: &mut Vec<Row>) {
+        for mut row in self.pending_rows.split_off(0) {
+            let key = self.key_value(0, &row);
+            for (i, latest_rows) in self.latest_rows.iter().enumerate() {
+                let field_count = self.headers[i + 1].field_names().len();
+                match latest_rows.get(&key) {
+                    Some(latest_row)
+                        if self.tolerance.is_none_or(|tolerance| {
+                            latest_row.timestamp.saturating_add(tolerance) >= row.timestamp
+                        }) =>
+                    {
+                        row.field_values
+                            .extend(latest_row.field_values.iter().cloned())
+                    }
+                    _ => row
+                        .field_values
+                        .extend((0..field_count).map(|_| FieldValue::None)),
+                }
+            }
+            io_rows.push(row);
+        }
+    }
+}
+
+impl MergeHeaderSink for AsOfJoin {
+    fn check_header(&mut self, header: &Header) -> ChopperResult<()> {
+        if let Some(key) = &self.key {
+            let key_index = header.get_field_index(key)?;
+            // values of different types are never equal, so nothing would ever be joined
+            if let Some(first_header) = self.headers.first() {
+                let first_type = &first_header.field_types()[self.key_indexes[0]];
+                let key_type = &header.field_types()[key_index];
+                if key_type != first_type {
+                    return Err(Error::from(format!(
+                        "AsOfJoin -- key column {} is {:?} in the first input, but {:?} in input {}",
+                        key,
+                        first_type,
+                        key_type,
+                        self.headers.len() + 1
+                    )));
+                }
+            }
+            self.key_indexes.push(key_index);
+        }
+        self.headers.push(header.clone());
+        Ok(())
+    }
+
+    fn process_header(&mut self) -> Header {
+        let mut field_names: Vec<String> = self.headers[0].field_names().clone();
+        let mut field_types: Vec<FieldType> = self.headers[0].field_types().clone();
+        for (header, prefix) in self.headers[1..].iter().zip(&self.prefixes) {
+            for name in header.field_names() {
+                field_names.push(format!("{}{}", prefix, name));
+            }
+            field_types.extend(header.field_types().iter().cloned());
+        }
+        Header::new(field_names, field_types)
+    }
+
+    fn get_data_sink(self: Box<Self>) -> ChopperResult<Box<dyn DataSink>> {
+        if self.headers.len() != self.merge_source_count {
+            return Err(Error::from(
+                "AsOfJoin -- all the headers must be processed before returning DataSink",
+            ));
+        }
+        Ok(Box::new(*self))
+    }
+
+    fn get_new_header_count_tracker(&self) -> HeaderCountTracker {
+        HeaderCountTracker {
+            unprocessed_count: self.merge_source_count,
+        }
+    }
+}
+
+impl DataSink for AsOfJoin {
+    fn write_row(&mut self, _io_rows: &mut Vec<Row>) -> ChopperResult<()> {
+        Err(Error::from(
+            "AsOfJoin -- rows have to come from inputs merged into its chain",
+        ))
+    }
+
+    fn write_merged_row(
+        &mut self,
+        input_index: usize,
+        io_rows: &mut Vec<Row>,
+    ) -> ChopperResult<()> {
+        let row = io_rows.pop().unwrap();
+        if let Some(pending_row) = self.pending_rows.first() {
+            if row.timestamp > pending_row.timestamp {
+                self.output_pending_rows(io_rows);
+            }
+        }
+        match input_index {
+            0 => self.pending_rows.push(row),
+            _ => {
+                let key = self.key_value(input_index, &row);
+                self.latest_rows[input_index - 1].insert(key, row);
+            }
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self, io_rows: &mut Vec<Row>) -> ChopperResult<()> {
+        self.output_pending_rows(io_rows);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chopper::types::{FieldType, FieldValue, Header, Nanos, Row};
+    use crate::driver::asof_join::AsOfJoin;
+
+    fn row(timestamp: Nanos, sym: &str, value: i64) -> Row {
+        Row {
+            timestamp,
+            field_values: vec![FieldValue::String(sym.to_string()), FieldValue::Long(value)],
+        }
+    }
+
+    #[test]
+    fn test_asof_join() {
+        let header = Header::new(
+            vec!["sym".to_string(), "v".to_string()],
+            vec![FieldType::String, FieldType::Long],
+        );
+        let mut join = AsOfJoin::new(2, Some("sym"), Some(10), vec!["q_".to_string()]).unwrap();
+        join.check_header(&header).unwrap();
+        join.check_header(&header).unwrap();
+        let joined_header = join.process_header();
+        assert_eq!(
+            joined_header.field_names(),
+            &vec!["sym", "v", "q_sym", "q_v"]
+        );
+        let mut sink = join.get_data_sink().unwrap();
+
+        let input = vec![
+            (1, row(5, "A", 1)),
+            (1, row(8, "B", 2)),
+            (0, row(10, "A", 100)),
+            (1, row(10, "A", 3)),
+            (0, row(15, "B", 200)),
+            (0, row(30, "A", 300)),
+        ];
+        let mut output: Vec<Row> = Vec::new();
+        for (input_index, row) in input {
+            let mut io_rows = vec![row];
+            sink.write_merged_row(input_index, &mut io_rows).unwrap();
+            output.append(&mut io_rows);
+        }
+        let mut io_rows: Vec<Row> = Vec::new();
+        sink.flush(&mut io_rows).unwrap();
+        output.append(&mut io_rows);
+
+        let values: Vec<Vec<FieldValue>> = output.into_iter().map(|r| r.field_values).collect();
+        assert_eq!(
+            values,
+            vec![
+                // quote with the same timestamp is joined even though it came later
+                row(10, "A", 100)
+                    .field_values
+                    .into_iter()
+                    .chain(row(10, "A", 3).field_values)
+                    .collect::<Vec<FieldValue>>(),
+                row(15, "B", 200)
+                    .field_values
+                    .into_iter()
+                    .chain(row(8, "B", 2).field_values)
+                    .collect(),
+                // latest A quote is older than tolerance
+                vec![
+                    FieldValue::String("A".to_string()),
+                    FieldValue::Long(300),
+                    FieldValue::None,
+                    FieldValue::None,
+                ],
+            ]
+        );
+        assert!(AsOfJoin::new(3, None, None, vec!["q_".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_key_types_must_match() {
+        let header = |key_type: FieldType| {
+            Header::new(
+                vec!["sym".to_string(), "v".to_string()],
+                vec![key_type, FieldType::Long],
+            )
+        };
+        let mut join = AsOfJoin::new(2, Some("sym"), None, Vec::new()).unwrap();
+        join.check_header(&header(FieldType::String)).unwrap();
+        let error = join.check_header(&header(FieldType::Long)).err().unwrap();
+        assert!(error.to_string().contains("String"));
+        assert!(error.to_string().contains("Long"));
+    }
+}
@@ -1,15 +1,23 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
 use crate::chopper::data_graph::{DataGraph, DataNode};
 use crate::chopper::driver::ChopperDriver;
-use crate::chopper::error::{ChopperResult, Error};
+use crate::chopper::error::{ChopperResult, Error, GraphError};
+use crate::chopper::graph_dump::GraphDump;
 use crate::chopper::header_graph::HeaderGraph;
 use crate::chopper::types::{ChainId, Header, NodeId, Row, TimestampRange};
 use crate::driver::source_row_buffer::SourceRowBuffer;
+use crate::driver::tie_break::{MergeKey, TieBreakPolicy, TieBreaker};
 use crate::source::source::Source;
 
 pub struct Driver {
     sources: Vec<Box<dyn Source>>,
     data_graph: DataGraph,
     timestamp_range: TimestampRange,
+    tie_break_policy: TieBreakPolicy,
+    /// per chain, number of sources and chains feeding into it that are yet to be flushed
+    pending_flushes: Vec<usize>,
 }
 
 impl Driver {
@@ -20,66 +28,96 @@ impl Driver {
         headers: Vec<Header>,
     ) -> ChopperResult<Self> {
         if sources.len() > header_graph.len() {
-            return Err(Error::from(
-                "Driver -- not enough header chains for sources. \
-                each source should have at least one header chain.",
-            ));
+            return Err(Error::from(GraphError::TooFewChains {
+                source_count: sources.len(),
+                chain_count: header_graph.len(),
+            }));
         }
-        let data_graph = header_graph.process_header(headers)?;
+        let mut data_graph = header_graph.process_header(headers)?;
+        let pending_flushes = Self::count_chain_inputs(&mut data_graph, sources.len());
         Ok(Driver {
             sources,
             data_graph,
             timestamp_range,
+            tie_break_policy: TieBreakPolicy::default(),
+            pending_flushes,
         })
     }
 
+    fn count_chain_inputs(data_graph: &mut DataGraph, source_count: usize) -> Vec<usize> {
+        let mut input_counts: Vec<usize> = vec![0; data_graph.len()];
+        for count in input_counts.iter_mut().take(source_count) {
+            *count += 1;
+        }
+        for chain_id in 0..data_graph.len() {
+            for node in data_graph.get_mut_chain(chain_id).nodes() {
+                match node {
+                    DataNode::DataSink(_) => {}
+                    DataNode::Merge(next_chain_id, _) => input_counts[*next_chain_id] += 1,
+                    DataNode::Split(chain_ids, _) => {
+                        for next_chain_id in chain_ids {
+                            input_counts[*next_chain_id] += 1;
+                        }
+                    }
+                }
+            }
+        }
+        input_counts
+    }
+
+    pub fn with_tie_break_policy(mut self, tie_break_policy: TieBreakPolicy) -> Self {
+        self.tie_break_policy = tie_break_policy;
+        self
+    }
+
+    /// data graph with the header coming into each node
+    pub fn dump(&self) -> GraphDump {
+        self.data_graph.dump()
+    }
+
     fn drive(&mut self) -> ChopperResult<()> {
+        let headers: Vec<&Header> = self.sources.iter().map(|s| s.header()).collect();
+        let mut tie_breaker = TieBreaker::new(self.tie_break_policy.clone(), &headers)?;
         let mut row_buffers = self.get_row_buffers()?;
 
         // sort and output
-        // all the sources are processed at the same time, but a row with min timestamp is output first
-        let mut buffer_len = row_buffers.len();
-        while buffer_len > 0 {
-            // get the row with min timestamp and write
-            let buffer_index = Self::get_next_buffer_index(&row_buffers);
+        // all the sources are processed at the same time, but a row with min timestamp is output
+        // first; rows with equal timestamps are ordered according to the tie break policy
+        let mut heap: BinaryHeap<Reverse<MergeKey>> = BinaryHeap::with_capacity(row_buffers.len());
+        for (index, row_buffer) in row_buffers.iter_mut().enumerate() {
+            match row_buffer.row() {
+                Some(row) => heap.push(Reverse(tie_breaker.key(index, row))),
+                None => self.flush_input(row_buffer.chain_id())?,
+            }
+        }
+
+        while let Some(Reverse(key)) = heap.pop() {
+            let buffer_index = key.index;
             let next_row_buffer = &mut row_buffers[buffer_index];
-            let row = next_row_buffer.row().clone().unwrap();
+            let row = next_row_buffer.take_row().unwrap();
             let chain_id = next_row_buffer.chain_id();
             self.process_row(chain_id, 0, row)?;
 
-            // remove the row buffer if it reaches the end of the file
-            loop {
-                if !row_buffers[buffer_index].has_next(&self.timestamp_range)? {
-                    self.flush(chain_id, 0)?;
-                    row_buffers.remove(buffer_index);
-                }
-                break;
+            // the row buffer goes back into the heap unless it reached the end of the file
+            let next_row_buffer = &mut row_buffers[buffer_index];
+            if next_row_buffer.has_next(&self.timestamp_range)? {
+                let row = next_row_buffer.row().as_ref().unwrap();
+                heap.push(Reverse(tie_breaker.key(buffer_index, row)));
+            } else {
+                self.flush_input(chain_id)?;
             }
-            // update row buffer length
-            buffer_len = row_buffers.len();
         }
         Ok(())
     }
 
     fn get_row_buffers(&mut self) -> ChopperResult<Vec<SourceRowBuffer>> {
         let mut row_buffers: Vec<SourceRowBuffer> = Vec::with_capacity(self.sources.len());
-        for i in 0..self.sources.len() {
-            let source = self.sources.pop().unwrap();
+        for (i, source) in self.sources.drain(..).enumerate() {
             row_buffers.push(SourceRowBuffer::new(source, i, &self.timestamp_range)?);
         }
         Ok(row_buffers)
     }
 
-    // index of the row buffer that has a row with min timestamp
-    fn get_next_buffer_index(row_buffers: &Vec<SourceRowBuffer>) -> usize {
-        let min = row_buffers
-            .iter()
-            .enumerate()
-            .min_by(|&(_, i1), &(_, i2)| i1.timestamp().cmp(&i2.timestamp()))
-            .unwrap();
-        min.0
-    }
-
     fn process_row(&mut self, chain_id: ChainId, node_id: NodeId, row: Row) -> ChopperResult<()> {
         // support data sinks returning more than one row
         let mut rows: Vec<Row> = vec![row];
@@ -101,13 +139,22 @@ impl Driver {
                         }
                     }
                 }
-                DataNode::Merge(next_chain_id) => {
-                    let next_chain_id = *next_chain_id;
-                    self.process_row(next_chain_id, 0, rows.get(0).unwrap().clone())?;
+                DataNode::Merge(next_chain_id, input_index) => {
+                    let (next_chain_id, input_index) = (*next_chain_id, *input_index);
+                    let row = rows.get(0).unwrap().clone();
+                    self.process_merged_row(next_chain_id, input_index, row)?;
                     // that's right, continue processing current chain to support "tees"
                 }
-                DataNode::Split(chain_ids) => {
-                    for next_chain_id in chain_ids.clone() {
+                DataNode::Split(chain_ids, router) => {
+                    let next_chain_ids: Vec<ChainId> = match router {
+                        None => chain_ids.clone(),
+                        Some(router) => {
+                            let mut indexes: Vec<usize> = Vec::new();
+                            router.route(rows.first().unwrap(), &mut indexes)?;
+                            indexes.into_iter().map(|i| chain_ids[i]).collect()
+                        }
+                    };
+                    for next_chain_id in next_chain_ids {
                         self.process_row(next_chain_id, 0, rows.get(0).unwrap().clone())?;
                     }
                     // that's right, continue processing current chain to support "tees"
@@ -117,21 +164,59 @@ impl Driver {
         Ok(())
     }
 
+    fn process_merged_row(
+        &mut self,
+        chain_id: ChainId,
+        input_index: usize,
+        row: Row,
+    ) -> ChopperResult<()> {
+        let mut rows: Vec<Row> = vec![row];
+        match self.data_graph.get_chain_node_mut(chain_id, 0) {
+            DataNode::DataSink(sink) => sink.write_merged_row(input_index, &mut rows)?,
+            _ => {
+                return Err(Error::from(
+                    "Driver -- chain with merged inputs must start with a data sink",
+                ))
+            }
+        }
+        for row in rows {
+            self.process_row(chain_id, 1, row)?;
+        }
+        Ok(())
+    }
+
+    /// a chain is flushed only once, after everything that feeds into it is done,
+    /// so that e.g. a merged chain doesn't get flushed when just one of its inputs ends
+    fn flush_input(&mut self, chain_id: ChainId) -> ChopperResult<()> {
+        let pending = &mut self.pending_flushes[chain_id];
+        *pending = pending.saturating_sub(1);
+        match *pending {
+            0 => self.flush(chain_id, 0),
+            _ => Ok(()),
+        }
+    }
+
     fn flush(&mut self, chain_id: ChainId, node_id: NodeId) -> ChopperResult<()> {
         let chain_node_count = self.data_graph.get_chain_node_count(chain_id);
         for node_id in node_id..chain_node_count {
             match self.data_graph.get_chain_node_mut(chain_id, node_id) {
                 DataNode::DataSink(sink) => {
-                    sink.flush()?;
+                    // rows held back by the sink have to reach the rest of the chain
+                    // before it is flushed
+                    let mut rows: Vec<Row> = Vec::new();
+                    sink.flush(&mut rows)?;
+                    for row in rows {
+                        self.process_row(chain_id, node_id + 1, row)?;
+                    }
                 }
-                DataNode::Merge(next_chain_id) => {
+                DataNode::Merge(next_chain_id, _) => {
                     let next_chain_id = *next_chain_id;
-                    self.flush(next_chain_id, 0)?;
+                    self.flush_input(next_chain_id)?;
                     // that's right, continue processing current chain to support "tees"
                 }
-                DataNode::Split(chain_ids) => {
+                DataNode::Split(chain_ids, _) => {
                     for next_chain_id in chain_ids.clone() {
-                        self.flush(next_chain_id, 0)?;
+                        self.flush_input(next_chain_id)?;
                     }
                     // that's right, continue processing current chain to support "tees"
                 }
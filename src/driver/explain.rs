@@ -0,0 +1,56 @@
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use crate::chopper::driver::ChopperDriver;
+use crate::chopper::error::{ChopperResult, Error};
+use crate::driver::driver::Driver;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ExplainFormat {
+    /// indented text
+    #[default]
+    Text,
+    /// graphviz dot
+    Dot,
+}
+
+impl FromStr for ExplainFormat {
+    type Err = Error;
+
+    fn from_str(format: &str) -> ChopperResult<ExplainFormat> {
+        match format {
+            "text" => Ok(ExplainFormat::Text),
+            "dot" => Ok(ExplainFormat::Dot),
+            _ => Err(Error::from(format!(
+                "Explain -- {} is not a valid format, expected text or dot",
+                format
+            ))),
+        }
+    }
+}
+
+/// prints the graph of a driver to stdout instead of driving it
+pub struct Explain {
+    plan: String,
+}
+
+impl Explain {
+    /// driver is dropped without reading any rows
+    pub fn new(driver: Driver, format: ExplainFormat) -> Box<dyn ChopperDriver> {
+        let dump = driver.dump();
+        let plan = match format {
+            ExplainFormat::Text => dump.to_text(),
+            ExplainFormat::Dot => dump.to_dot(),
+        };
+        Box::new(Explain { plan }) as Box<dyn ChopperDriver>
+    }
+}
+
+impl ChopperDriver for Explain {
+    fn drive(&mut self) -> ChopperResult<()> {
+        let mut stdout = io::stdout();
+        stdout.write_all(self.plan.as_bytes())?;
+        stdout.flush()?;
+        Ok(())
+    }
+}
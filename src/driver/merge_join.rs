@@ -1,11 +1,23 @@
 use crate::chopper::error::{ChopperResult, Error};
 use crate::chopper::header_graph::HeaderCountTracker;
 use crate::chopper::sink::{DataSink, MergeHeaderSink};
-use crate::chopper::types::{Header, Row};
+use crate::chopper::types::{FieldType, FieldValue, Header, Row};
+use crate::util::column_map::{widen_type, ColumnMap};
 
 pub struct MergeJoin {
     merge_source_count: usize,
     header: Option<Header>,
+    union: Option<UnionMerge>,
+}
+
+/// state of schema union mode
+struct UnionMerge {
+    /// name of the synthesized column and its value for each input
+    source_column: Option<(String, Vec<String>)>,
+    /// input headers in the order they were checked
+    headers: Vec<Header>,
+    /// per input, map from its columns to those of the union header
+    column_maps: Vec<ColumnMap>,
 }
 
 impl MergeJoin {
@@ -18,6 +30,43 @@ impl MergeJoin {
         let merge = MergeJoin {
             merge_source_count,
             header: None,
+            union: None,
+        };
+        Ok(Box::new(merge) as Box<dyn MergeHeaderSink>)
+    }
+
+    /// inputs don't need to have the same headers; output header has all the columns of the
+    /// first input, followed by columns of later inputs that aren't there yet, and values of
+    /// missing columns are null; columns of the same name have to have the same type, or one
+    /// that can be widened, e.g. int and long columns become long and int and double columns
+    /// become double; if source column is given, a string column of that name is appended, with
+    /// one value for each input, in the order inputs are merged, e.g. input file names
+    pub fn new_union(
+        merge_source_count: usize,
+        source_column: Option<(String, Vec<String>)>,
+    ) -> ChopperResult<Box<dyn MergeHeaderSink>> {
+        if merge_source_count == 0 {
+            return Err(Error::from(
+                "MergeJoin -- number of inputs must be at least 1",
+            ));
+        }
+        if let Some((_, values)) = &source_column {
+            if values.len() != merge_source_count {
+                return Err(Error::from(format!(
+                    "MergeJoin -- expected {} source column values, one per input, got {}",
+                    merge_source_count,
+                    values.len()
+                )));
+            }
+        }
+        let merge = MergeJoin {
+            merge_source_count,
+            header: None,
+            union: Some(UnionMerge {
+                source_column,
+                headers: Vec::new(),
+                column_maps: Vec::new(),
+            }),
         };
         Ok(Box::new(merge) as Box<dyn MergeHeaderSink>)
     }
@@ -27,8 +76,71 @@ impl MergeJoin {
     }
 }
 
+impl UnionMerge {
+    fn add_header(
+        &mut self,
+        union_header: &mut Option<Header>,
+        header: &Header,
+    ) -> ChopperResult<()> {
+        if let Some((name, _)) = &self.source_column {
+            if header.field_names().contains(name) {
+                return Err(Error::from(format!(
+                    "MergeJoin -- input already has a column named {:?}",
+                    name
+                )));
+            }
+        }
+        self.headers.push(header.clone());
+        let union_header = match union_header {
+            None => {
+                *union_header = Some(header.clone());
+                return Ok(());
+            }
+            Some(union_header) => union_header,
+        };
+        for (i, name) in header.field_names().iter().enumerate() {
+            let field_type = header.field_types()[i];
+            // duplicate names are matched in order, same as in ColumnMap
+            let occurrence = header.field_names()[..i]
+                .iter()
+                .filter(|n| *n == name)
+                .count();
+            let union_index = union_header
+                .field_names()
+                .iter()
+                .enumerate()
+                .filter(|(_, n)| *n == name)
+                .nth(occurrence)
+                .map(|(j, _)| j);
+            match union_index {
+                None => {
+                    union_header.field_names_mut().push(name.clone());
+                    union_header.field_types_mut().push(field_type);
+                }
+                Some(j) => {
+                    let union_type = union_header.field_types()[j];
+                    match widen_type(union_type, field_type) {
+                        Some(widened_type) => union_header.field_types_mut()[j] = widened_type,
+                        None => {
+                            return Err(Error::from(format!(
+                                "MergeJoin -- column {:?} has type {:?} in one input and {:?} \
+                                in another",
+                                name, union_type, field_type
+                            )))
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
 impl MergeHeaderSink for MergeJoin {
     fn check_header(&mut self, header: &Header) -> ChopperResult<()> {
+        if let Some(union) = &mut self.union {
+            return union.add_header(&mut self.header, header);
+        }
         match &self.header {
             Some(h) => {
                 if !header.eq(h) {
@@ -41,7 +153,20 @@ impl MergeHeaderSink for MergeJoin {
     }
 
     fn process_header(&mut self) -> Header {
-        self.header.take().unwrap()
+        let mut header = self.header.take().unwrap();
+        if let Some(union) = &mut self.union {
+            for input_header in &union.headers {
+                let column_map = ColumnMap::new(&header, input_header)
+                    .with_widening(&header, input_header)
+                    .unwrap();
+                union.column_maps.push(column_map);
+            }
+            if let Some((name, _)) = &union.source_column {
+                header.field_names_mut().push(name.clone());
+                header.field_types_mut().push(FieldType::String);
+            }
+        }
+        header
     }
 
     fn get_data_sink(self: Box<Self>) -> ChopperResult<Box<dyn DataSink>> {
@@ -63,6 +188,98 @@ impl MergeHeaderSink for MergeJoin {
 //TODO figure out if this even needs to be a DataSink
 impl DataSink for MergeJoin {
     fn write_row(&mut self, _io_rows: &mut Vec<Row>) -> ChopperResult<()> {
+        match self.union {
+            None => Ok(()),
+            Some(_) => Err(Error::from(
+                "MergeJoin -- in union mode, rows have to come from inputs merged into its chain",
+            )),
+        }
+    }
+
+    fn write_merged_row(
+        &mut self,
+        input_index: usize,
+        io_rows: &mut Vec<Row>,
+    ) -> ChopperResult<()> {
+        if let Some(union) = &self.union {
+            let row = io_rows.first_mut().unwrap();
+            union.column_maps[input_index].map_row(row);
+            if let Some((_, values)) = &union.source_column {
+                row.field_values
+                    .push(FieldValue::String(values[input_index].clone()));
+            }
+        }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::chopper::types::{FieldType, FieldValue, Header, Row};
+    use crate::driver::merge_join::MergeJoin;
+
+    #[test]
+    fn test_union_merge() {
+        let header_1 = Header::new(
+            vec!["a".to_string(), "b".to_string()],
+            vec![FieldType::Int, FieldType::String],
+        );
+        let header_2 = Header::new(
+            vec!["c".to_string(), "a".to_string()],
+            vec![FieldType::Boolean, FieldType::Long],
+        );
+        let source_column = Some((
+            "source".to_string(),
+            vec!["one".to_string(), "two".to_string()],
+        ));
+        let mut merge = MergeJoin::new_union(2, source_column).unwrap();
+        merge.check_header(&header_1).unwrap();
+        merge.check_header(&header_2).unwrap();
+        let header = merge.process_header();
+        assert_eq!(header.field_names(), &vec!["a", "b", "c", "source"]);
+        assert_eq!(
+            header.field_types(),
+            &vec![
+                FieldType::Long,
+                FieldType::String,
+                FieldType::Boolean,
+                FieldType::String
+            ]
+        );
+        let mut sink = merge.get_data_sink().unwrap();
+
+        let mut io_rows = vec![Row {
+            timestamp: 0,
+            field_values: vec![FieldValue::Int(1), FieldValue::String("x".to_string())],
+        }];
+        sink.write_merged_row(0, &mut io_rows).unwrap();
+        assert_eq!(
+            io_rows[0].field_values,
+            vec![
+                FieldValue::Long(1),
+                FieldValue::String("x".to_string()),
+                FieldValue::None,
+                FieldValue::String("one".to_string()),
+            ]
+        );
+        let mut io_rows = vec![Row {
+            timestamp: 0,
+            field_values: vec![FieldValue::Boolean(true), FieldValue::Long(2)],
+        }];
+        sink.write_merged_row(1, &mut io_rows).unwrap();
+        assert_eq!(
+            io_rows[0].field_values,
+            vec![
+                FieldValue::Long(2),
+                FieldValue::None,
+                FieldValue::Boolean(true),
+                FieldValue::String("two".to_string()),
+            ]
+        );
+
+        let mut merge = MergeJoin::new_union(2, None).unwrap();
+        merge.check_header(&header_1).unwrap();
+        let header_3 = Header::new(vec!["b".to_string()], vec![FieldType::Double]);
+        assert!(merge.check_header(&header_3).is_err());
+    }
+}
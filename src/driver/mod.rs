@@ -1,4 +1,7 @@
+pub mod asof_join;
 pub mod driver;
+pub mod explain;
 pub mod merge_join;
 mod source_row_buffer;
 pub mod split;
+pub mod tie_break;
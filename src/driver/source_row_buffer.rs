@@ -5,7 +5,6 @@ use crate::source::source::Source;
 pub struct SourceRowBuffer {
     source: Box<dyn Source + 'static>,
     chain_id: ChainId,
-    timestamp: Nanos,
     row: Option<Row>,
 }
 
@@ -15,33 +14,27 @@ impl SourceRowBuffer {
         chain_id: ChainId,
         timestamp_range: &TimestampRange,
     ) -> ChopperResult<Self> {
-        let mut row = match_next_row(&mut source, timestamp_range)?;
-        let timestamp = match &mut row {
-            Some(r) => r.timestamp,
-            None => 0 as Nanos,
-        };
+        let row = match_next_row(&mut source, timestamp_range)?;
         Ok(SourceRowBuffer {
             source,
             chain_id,
-            timestamp,
             row,
         })
     }
 
-    pub fn timestamp(&self) -> Nanos {
-        self.timestamp
-    }
-
     pub fn row(&self) -> &Option<Row> {
         &self.row
     }
 
+    pub fn take_row(&mut self) -> Option<Row> {
+        self.row.take()
+    }
+
     pub fn chain_id(&mut self) -> ChainId {
         self.chain_id
     }
 
     fn update_record(&mut self, next_row: Row) {
-        self.timestamp = next_row.timestamp;
         self.row = Some(next_row);
     }
 
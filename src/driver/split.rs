@@ -1,6 +1,10 @@
+use std::collections::HashMap;
+
+use crate::chopper::error::{ChopperResult, Error};
 use crate::chopper::header_graph::HeaderCountTracker;
-use crate::chopper::sink::SplitHeaderSink;
-use crate::chopper::types::ChainId;
+use crate::chopper::sink::{RowRouter, SplitHeaderSink};
+use crate::chopper::types::{ChainId, FieldType, FieldValue, HashableFieldValue, Header, Row};
+use crate::filter::expression::{CompiledExpr, ParsedExpr};
 
 pub struct Split {
     chain_ids: Vec<ChainId>,
@@ -24,3 +28,257 @@ impl SplitHeaderSink for Split {
         }
     }
 }
+
+/// routes with targets as indexes into chain ids
+enum Routes {
+    Predicates(Vec<(ParsedExpr, usize)>),
+    Keys(String, HashMap<HashableFieldValue, Vec<usize>>),
+}
+
+pub struct RoutingSplit {
+    chain_ids: Vec<ChainId>,
+    /// taken when the router is created
+    routes: Option<Routes>,
+    default_index: Option<usize>,
+}
+
+impl RoutingSplit {
+    /// each row goes to every chain whose expression is true for it, see Expr for the syntax,
+    /// and rows that match no expression go to the default chain, if any, or are dropped;
+    /// the same chain can be given for more than one expression
+    pub fn new_predicates(
+        routes: Vec<(&str, ChainId)>,
+        default_chain_id: Option<ChainId>,
+    ) -> ChopperResult<Box<dyn SplitHeaderSink>> {
+        let mut chain_ids: Vec<ChainId> = Vec::new();
+        let mut predicates: Vec<(ParsedExpr, usize)> = Vec::new();
+        for (expression, chain_id) in routes {
+            let index = Self::chain_index(&mut chain_ids, chain_id);
+            predicates.push((ParsedExpr::parse(expression)?, index));
+        }
+        Self::new_routing_split(chain_ids, Routes::Predicates(predicates), default_chain_id)
+    }
+
+    /// each row goes to every chain given for its value of the key column, and rows with
+    /// other values go to the default chain, if any, or are dropped; values have to be of
+    /// the type of the key column, e.g. FieldValue::Long for a long column
+    pub fn new_keys(
+        column_name: &str,
+        routes: Vec<(FieldValue, ChainId)>,
+        default_chain_id: Option<ChainId>,
+    ) -> ChopperResult<Box<dyn SplitHeaderSink>> {
+        let mut chain_ids: Vec<ChainId> = Vec::new();
+        let mut keys: HashMap<HashableFieldValue, Vec<usize>> = HashMap::new();
+        for (key, chain_id) in routes {
+            let index = Self::chain_index(&mut chain_ids, chain_id);
+            let indexes = keys.entry(HashableFieldValue(key)).or_default();
+            if !indexes.contains(&index) {
+                indexes.push(index);
+            }
+        }
+        let routes = Routes::Keys(column_name.to_string(), keys);
+        Self::new_routing_split(chain_ids, routes, default_chain_id)
+    }
+
+    fn new_routing_split(
+        mut chain_ids: Vec<ChainId>,
+        routes: Routes,
+        default_chain_id: Option<ChainId>,
+    ) -> ChopperResult<Box<dyn SplitHeaderSink>> {
+        let default_index = match default_chain_id {
+            None => None,
+            Some(chain_id) if chain_ids.contains(&chain_id) => {
+                return Err(Error::from(format!(
+                    "RoutingSplit -- default chain {} is also the chain of a route",
+                    chain_id
+                )))
+            }
+            Some(chain_id) => Some(Self::chain_index(&mut chain_ids, chain_id)),
+        };
+        if chain_ids.is_empty() {
+            return Err(Error::from("RoutingSplit -- no chains to route rows to"));
+        }
+        let split = RoutingSplit {
+            chain_ids,
+            routes: Some(routes),
+            default_index,
+        };
+        Ok(Box::new(split) as Box<dyn SplitHeaderSink>)
+    }
+
+    fn chain_index(chain_ids: &mut Vec<ChainId>, chain_id: ChainId) -> usize {
+        match chain_ids.iter().position(|&id| id == chain_id) {
+            Some(index) => index,
+            None => {
+                chain_ids.push(chain_id);
+                chain_ids.len() - 1
+            }
+        }
+    }
+}
+
+fn is_of_type(value: &FieldValue, field_type: FieldType) -> bool {
+    match value {
+        FieldValue::Boolean(_) => field_type == FieldType::Boolean,
+        FieldValue::Byte(_) => field_type == FieldType::Byte,
+        FieldValue::ByteBuf(_) => field_type == FieldType::ByteBuf,
+        FieldValue::Char(_) => field_type == FieldType::Char,
+        FieldValue::Double(_) => field_type == FieldType::Double,
+        FieldValue::Float(_) => field_type == FieldType::Float,
+        FieldValue::Int(_) => field_type == FieldType::Int,
+        FieldValue::Long(_) => field_type == FieldType::Long,
+        FieldValue::Short(_) => field_type == FieldType::Short,
+        FieldValue::String(_) => field_type == FieldType::String,
+        FieldValue::MultiDimDoubleArray(_) => field_type == FieldType::MultiDimDoubleArray,
+        FieldValue::None => true,
+    }
+}
+
+impl SplitHeaderSink for RoutingSplit {
+    fn chain_ids(&mut self) -> &mut Vec<usize> {
+        &mut self.chain_ids
+    }
+
+    fn get_new_header_count_tracker(&self) -> HeaderCountTracker {
+        HeaderCountTracker {
+            unprocessed_count: self.chain_ids.len(),
+        }
+    }
+
+    fn get_router(&mut self, header: &Header) -> ChopperResult<Option<Box<dyn RowRouter>>> {
+        let router: Box<dyn RowRouter> = match self.routes.take().unwrap() {
+            Routes::Predicates(predicates) => {
+                let mut compiled: Vec<(CompiledExpr, usize)> = Vec::new();
+                for (expression, index) in predicates {
+                    compiled.push((expression.compile(header)?, index));
+                }
+                Box::new(PredicateRouter {
+                    predicates: compiled,
+                    default_index: self.default_index,
+                })
+            }
+            Routes::Keys(column_name, keys) => {
+                let key_index = header.get_field_index(&column_name)?;
+                let field_type = header.field_types()[key_index];
+                if let Some(key) = keys.keys().find(|key| !is_of_type(&key.0, field_type)) {
+                    return Err(Error::from(format!(
+                        "RoutingSplit -- key {} does not match type {:?} of column {}",
+                        key.0, field_type, column_name
+                    )));
+                }
+                Box::new(KeyRouter {
+                    key_index,
+                    keys,
+                    default_index: self.default_index,
+                })
+            }
+        };
+        Ok(Some(router))
+    }
+}
+
+struct PredicateRouter {
+    predicates: Vec<(CompiledExpr, usize)>,
+    default_index: Option<usize>,
+}
+
+impl RowRouter for PredicateRouter {
+    fn route(&mut self, row: &Row, io_indexes: &mut Vec<usize>) -> ChopperResult<()> {
+        for (predicate, index) in &self.predicates {
+            if predicate.is_true(row)? && !io_indexes.contains(index) {
+                io_indexes.push(*index);
+            }
+        }
+        if io_indexes.is_empty() {
+            io_indexes.extend(self.default_index);
+        }
+        Ok(())
+    }
+}
+
+struct KeyRouter {
+    key_index: usize,
+    keys: HashMap<HashableFieldValue, Vec<usize>>,
+    default_index: Option<usize>,
+}
+
+impl RowRouter for KeyRouter {
+    fn route(&mut self, row: &Row, io_indexes: &mut Vec<usize>) -> ChopperResult<()> {
+        let key = HashableFieldValue(row.field_values[self.key_index].clone());
+        match self.keys.get(&key) {
+            Some(indexes) => io_indexes.extend(indexes),
+            None => io_indexes.extend(self.default_index),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chopper::types::{FieldType, FieldValue, Header, Row};
+    use crate::driver::split::RoutingSplit;
+
+    fn header() -> Header {
+        Header::new(
+            vec!["level".to_string(), "code".to_string()],
+            vec![FieldType::String, FieldType::Long],
+        )
+    }
+
+    fn row(level: &str, code: i64) -> Row {
+        Row {
+            timestamp: 0,
+            field_values: vec![
+                FieldValue::String(level.to_string()),
+                FieldValue::Long(code),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_predicate_routing() {
+        let mut split = RoutingSplit::new_predicates(
+            vec![
+                ("level == 'error'", 1),
+                ("code >= 500", 2),
+                ("code == 0", 1),
+            ],
+            Some(3),
+        )
+        .unwrap();
+        assert_eq!(split.chain_ids(), &vec![1, 2, 3]);
+        let mut router = split.get_router(&header()).unwrap().unwrap();
+        let mut route = |row: Row| {
+            let mut indexes: Vec<usize> = Vec::new();
+            router.route(&row, &mut indexes).unwrap();
+            indexes
+        };
+        assert_eq!(route(row("error", 503)), vec![0, 1]);
+        assert_eq!(route(row("info", 0)), vec![0]);
+        assert_eq!(route(row("info", 200)), vec![2]);
+
+        assert!(RoutingSplit::new_predicates(vec![("level ==", 1)], None).is_err());
+        assert!(RoutingSplit::new_predicates(vec![("code > 1", 1)], Some(1)).is_err());
+    }
+
+    #[test]
+    fn test_key_routing() {
+        let routes = vec![
+            (FieldValue::Long(404), 1),
+            (FieldValue::Long(500), 2),
+            (FieldValue::Long(503), 2),
+        ];
+        let mut split = RoutingSplit::new_keys("code", routes, None).unwrap();
+        let mut router = split.get_router(&header()).unwrap().unwrap();
+        let mut indexes: Vec<usize> = Vec::new();
+        router.route(&row("error", 503), &mut indexes).unwrap();
+        assert_eq!(indexes, vec![1]);
+        indexes.clear();
+        router.route(&row("info", 200), &mut indexes).unwrap();
+        assert!(indexes.is_empty());
+
+        let routes = vec![(FieldValue::Int(404), 1)];
+        let mut split = RoutingSplit::new_keys("code", routes, None).unwrap();
+        assert!(split.get_router(&header()).is_err());
+    }
+}
@@ -0,0 +1,120 @@
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+use crate::chopper::error::{ChopperResult, Error};
+use crate::chopper::types::{FieldValue, Header, Nanos, Row};
+
+/// order in which Driver outputs rows that have equal timestamps but come from different inputs
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum TieBreakPolicy {
+    /// earlier input goes first
+    #[default]
+    InputOrder,
+    /// later input goes first
+    ReverseInputOrder,
+    /// inputs take turns; input that just had its row output goes after the other inputs
+    /// with the same timestamp
+    RoundRobin,
+    /// ascending order of the named column among the current rows of the inputs, then input
+    /// order; values that cannot be compared to each other, e.g. of different types, are
+    /// treated as equal
+    Column(String),
+}
+
+impl FromStr for TieBreakPolicy {
+    type Err = Error;
+
+    fn from_str(policy: &str) -> ChopperResult<TieBreakPolicy> {
+        match policy {
+            "input" => Ok(TieBreakPolicy::InputOrder),
+            "reverse" => Ok(TieBreakPolicy::ReverseInputOrder),
+            "round-robin" => Ok(TieBreakPolicy::RoundRobin),
+            _ => match policy.strip_prefix("col:") {
+                Some(name) if !name.is_empty() => Ok(TieBreakPolicy::Column(name.to_string())),
+                _ => Err(Error::from(format!(
+                    "tie break -- {} is not a valid policy",
+                    policy
+                ))),
+            },
+        }
+    }
+}
+
+/// position of a source in the Driver merge heap
+pub(crate) struct MergeKey {
+    timestamp: Nanos,
+    value: Option<FieldValue>,
+    rank: usize,
+    pub index: usize,
+}
+
+impl PartialEq for MergeKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for MergeKey {}
+
+impl PartialOrd for MergeKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MergeKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.timestamp
+            .cmp(&other.timestamp)
+            .then_with(|| match (&self.value, &other.value) {
+                (Some(v), Some(o)) => v.partial_cmp(o).unwrap_or(Ordering::Equal),
+                _ => Ordering::Equal,
+            })
+            .then_with(|| self.rank.cmp(&other.rank))
+    }
+}
+
+pub(crate) struct TieBreaker {
+    policy: TieBreakPolicy,
+    column_indexes: Vec<usize>,
+    sequence: usize,
+}
+
+impl TieBreaker {
+    pub fn new(policy: TieBreakPolicy, headers: &[&Header]) -> ChopperResult<Self> {
+        let mut column_indexes: Vec<usize> = Vec::new();
+        if let TieBreakPolicy::Column(name) = &policy {
+            for header in headers {
+                column_indexes.push(header.get_field_index(name)?);
+            }
+        }
+        Ok(TieBreaker {
+            policy,
+            column_indexes,
+            sequence: 0,
+        })
+    }
+
+    pub fn key(&mut self, index: usize, row: &Row) -> MergeKey {
+        let mut value = None;
+        let rank = match &self.policy {
+            TieBreakPolicy::InputOrder => index,
+            TieBreakPolicy::ReverseInputOrder => usize::MAX - index,
+            TieBreakPolicy::RoundRobin => {
+                // rows with equal timestamps come out in the order they went in
+                self.sequence += 1;
+                self.sequence
+            }
+            TieBreakPolicy::Column(_) => {
+                value = Some(row.field_values[self.column_indexes[index]].clone());
+                index
+            }
+        };
+        MergeKey {
+            timestamp: row.timestamp,
+            value,
+            rank,
+            index,
+        }
+    }
+}
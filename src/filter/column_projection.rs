@@ -0,0 +1,288 @@
+use std::mem;
+
+use crate::chopper::error::{ChopperResult, Error};
+use crate::chopper::sink::{DataSink, DynHeaderSink};
+use crate::chopper::types::{FieldValue, Header, Row};
+
+/// one element of a column list; indexes are 0-based and ranges are inclusive
+#[derive(Clone, Debug, PartialEq)]
+enum ColumnSelector {
+    Name(String),
+    Index(usize),
+    Range(usize, Option<usize>),
+    Glob(String),
+}
+
+impl ColumnSelector {
+    fn parse(selector: &str) -> ChopperResult<ColumnSelector> {
+        if selector.is_empty() {
+            return Err(Error::from("ColumnProjection -- empty column selector"));
+        }
+        if let Ok(i) = selector.parse::<usize>() {
+            return Ok(ColumnSelector::Index(i));
+        }
+        if let Some((begin, end)) = selector.split_once('-') {
+            if let Ok(begin) = begin.parse::<usize>() {
+                if end.is_empty() {
+                    return Ok(ColumnSelector::Range(begin, None));
+                }
+                if let Ok(end) = end.parse::<usize>() {
+                    return Ok(ColumnSelector::Range(begin, Some(end)));
+                }
+            }
+        }
+        if selector.contains(['*', '?']) {
+            return Ok(ColumnSelector::Glob(selector.to_string()));
+        }
+        Ok(ColumnSelector::Name(selector.to_string()))
+    }
+
+    /// exact names and indexes have to exist, while ranges and globs can match nothing
+    fn select(&self, header: &Header) -> ChopperResult<Vec<usize>> {
+        let field_names = header.field_names();
+        let column_count = field_names.len();
+        match self {
+            ColumnSelector::Name(name) => Ok(vec![header.get_field_index(name)?]),
+            ColumnSelector::Index(i) if *i < column_count => Ok(vec![*i]),
+            ColumnSelector::Index(i) => Err(Error::from(format!(
+                "ColumnProjection -- column index {} is out of range, there are {} columns",
+                i, column_count
+            ))),
+            ColumnSelector::Range(begin, end) => {
+                let end = match end {
+                    None => column_count,
+                    Some(end) => (end + 1).min(column_count),
+                };
+                Ok((*begin..end).collect())
+            }
+            ColumnSelector::Glob(pattern) => Ok((0..column_count)
+                .filter(|&i| glob_match(pattern.as_bytes(), field_names[i].as_bytes()))
+                .collect()),
+        }
+    }
+}
+
+/// '*' matches any number of characters and '?' matches exactly one
+fn glob_match(pattern: &[u8], name: &[u8]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    // position of the last '*' in pattern and of the name character it currently ends at
+    let mut backtrack: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == b'?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == b'*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = backtrack {
+            backtrack = Some((star_p, star_n + 1));
+            p = star_p + 1;
+            n = star_n + 1;
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+fn parse_selectors(list: &str) -> ChopperResult<Vec<(bool, ColumnSelector)>> {
+    let mut selectors: Vec<(bool, ColumnSelector)> = Vec::new();
+    for item in list.split(',') {
+        let item = item.trim();
+        selectors.push(match item.strip_prefix('!') {
+            Some(excluded) => (true, ColumnSelector::parse(excluded)?),
+            None => (false, ColumnSelector::parse(item)?),
+        });
+    }
+    Ok(selectors)
+}
+
+pub struct ColumnProjectionConfig {
+    columns: Option<Vec<(bool, ColumnSelector)>>,
+    drop: Vec<(bool, ColumnSelector)>,
+    renames: Vec<(String, String)>,
+}
+
+pub struct ColumnProjection {
+    column_indexes: Vec<usize>,
+}
+
+impl ColumnProjection {
+    /// columns and drop are comma-separated lists of column names, indexes, inclusive index
+    /// ranges like 2-5 or 2-, and glob patterns like bid_*; in columns list, columns are output
+    /// in the listed order and items starting with '!' remove columns selected so far,
+    /// or all columns if the list starts with one; renames are comma-separated old=new pairs
+    /// and are applied after selection
+    pub fn new(
+        columns: Option<&str>,
+        drop: Option<&str>,
+        renames: Option<&str>,
+    ) -> ChopperResult<Box<dyn DynHeaderSink>> {
+        let columns = match columns {
+            None => None,
+            Some(columns) => Some(parse_selectors(columns)?),
+        };
+        let drop = match drop {
+            None => Vec::new(),
+            Some(drop) => parse_selectors(drop)?,
+        };
+        let mut rename_pairs: Vec<(String, String)> = Vec::new();
+        if let Some(renames) = renames {
+            for rename in renames.split(',') {
+                match rename.split_once('=') {
+                    Some((old, new)) if !old.trim().is_empty() && !new.trim().is_empty() => {
+                        rename_pairs.push((old.trim().to_string(), new.trim().to_string()))
+                    }
+                    _ => {
+                        return Err(Error::from(format!(
+                            "ColumnProjection -- rename [{}] is not in old=new form",
+                            rename
+                        )))
+                    }
+                }
+            }
+        }
+        let config = ColumnProjectionConfig {
+            columns,
+            drop,
+            renames: rename_pairs,
+        };
+        Ok(Box::new(config) as Box<dyn DynHeaderSink>)
+    }
+}
+
+impl ColumnProjectionConfig {
+    fn select(&self, header: &Header) -> ChopperResult<Vec<usize>> {
+        let mut column_indexes: Vec<usize> = match &self.columns {
+            Some(columns) if !columns.first().unwrap().0 => Vec::new(),
+            _ => (0..header.field_names().len()).collect(),
+        };
+        if let Some(columns) = &self.columns {
+            for (excluded, selector) in columns {
+                let selected = selector.select(header)?;
+                match excluded {
+                    true => column_indexes.retain(|i| !selected.contains(i)),
+                    false => {
+                        for i in selected {
+                            if !column_indexes.contains(&i) {
+                                column_indexes.push(i);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        for (_, selector) in &self.drop {
+            let dropped = selector.select(header)?;
+            column_indexes.retain(|i| !dropped.contains(i));
+        }
+        Ok(column_indexes)
+    }
+}
+
+impl DynHeaderSink for ColumnProjectionConfig {
+    fn process_header(self: Box<Self>, header: &mut Header) -> ChopperResult<Box<dyn DataSink>> {
+        let column_indexes = self.select(header)?;
+
+        let mut field_names: Vec<String> = column_indexes
+            .iter()
+            .map(|&i| header.field_names()[i].clone())
+            .collect();
+        let field_types = column_indexes
+            .iter()
+            .map(|&i| header.field_types()[i])
+            .collect();
+        for (old, new) in &self.renames {
+            match field_names.iter().position(|name| name == old) {
+                None => return Err(Error::ColumnMissing(old.clone())),
+                Some(i) => field_names[i] = new.clone(),
+            }
+        }
+        header.update_field_names(field_names);
+        header.update_field_types(field_types);
+
+        Ok(Box::new(ColumnProjection { column_indexes }))
+    }
+}
+
+impl DataSink for ColumnProjection {
+    fn write_row(&mut self, io_rows: &mut Vec<Row>) -> ChopperResult<()> {
+        let row = io_rows.first_mut().unwrap();
+        // every column is selected at most once, so values can be moved out
+        let mut field_values = mem::take(&mut row.field_values);
+        row.field_values = self
+            .column_indexes
+            .iter()
+            .map(|&i| mem::replace(&mut field_values[i], FieldValue::None))
+            .collect();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chopper::types::{FieldType, FieldValue, Header, Row};
+    use crate::filter::column_projection::{glob_match, ColumnProjection};
+
+    fn project(columns: Option<&str>, drop: Option<&str>, renames: Option<&str>) -> Vec<String> {
+        let names = vec!["sym", "bid_px", "bid_size", "ask_px", "ask_size", "venue"];
+        let mut header = Header::new(
+            names.iter().map(|s| s.to_string()).collect(),
+            vec![FieldType::String; names.len()],
+        );
+        let projection = ColumnProjection::new(columns, drop, renames).unwrap();
+        let mut sink = projection.process_header(&mut header).unwrap();
+
+        let mut rows = vec![Row {
+            timestamp: 0,
+            field_values: names
+                .iter()
+                .map(|s| FieldValue::String(s.to_string()))
+                .collect(),
+        }];
+        sink.write_row(&mut rows).unwrap();
+        let values: Vec<String> = rows[0]
+            .field_values
+            .iter()
+            .map(|v| match v {
+                FieldValue::String(s) => s.clone(),
+                _ => unreachable!(),
+            })
+            .collect();
+        // renames aside, values have to follow the header
+        if renames.is_none() {
+            assert_eq!(&values, header.field_names());
+        }
+        header.field_names().clone()
+    }
+
+    #[test]
+    fn test_column_projection() {
+        assert_eq!(project(Some("venue,sym"), None, None), vec!["venue", "sym"]);
+        assert_eq!(
+            project(Some("bid_*,!*_size,0"), None, None),
+            vec!["bid_px", "sym"]
+        );
+        assert_eq!(
+            project(Some("!ask_*"), Some("0"), None),
+            vec!["bid_px", "bid_size", "venue"]
+        );
+        assert_eq!(project(Some("3-,1-2"), None, None).len(), 5);
+        assert_eq!(
+            project(None, Some("1-4"), Some("sym=symbol")),
+            vec!["symbol", "venue"]
+        );
+        assert!(ColumnProjection::new(None, None, Some("a")).is_err());
+        assert!(ColumnProjection::new(Some("a,,b"), None, None).is_err());
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match(b"bid_*", b"bid_px"));
+        assert!(glob_match(b"*_px", b"bid_px"));
+        assert!(glob_match(b"b?d*x", b"bid_px"));
+        assert!(glob_match(b"*", b""));
+        assert!(!glob_match(b"bid_?", b"bid_px"));
+        assert!(!glob_match(b"ask*", b"bid_px"));
+    }
+}
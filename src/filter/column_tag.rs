@@ -0,0 +1,160 @@
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::chopper::error::{ChopperResult, Error};
+use crate::chopper::sink::{DataSink, DynHeaderSink};
+use crate::chopper::types::{FieldType, FieldValue, Header, Row};
+use crate::input::input::{Input, InputType};
+
+/// what rows of each input are tagged with
+#[derive(Clone, Debug, PartialEq)]
+pub enum TagValue {
+    /// input path as given, or stdin
+    Path,
+    /// file name of the input without its last extension, or stdin
+    Stem,
+    /// one label per input, in input order
+    Labels(Vec<String>),
+}
+
+impl FromStr for TagValue {
+    type Err = Error;
+
+    fn from_str(value: &str) -> ChopperResult<TagValue> {
+        match value {
+            "path" => Ok(TagValue::Path),
+            "stem" => Ok(TagValue::Stem),
+            _ => Err(Error::from(format!(
+                "ColumnTag -- {} is not a valid tag value, expected path or stem",
+                value
+            ))),
+        }
+    }
+}
+
+impl TagValue {
+    /// tag of each input, in input order
+    pub fn values(&self, inputs: &[Input]) -> ChopperResult<Vec<String>> {
+        if let TagValue::Labels(labels) = self {
+            if labels.len() != inputs.len() {
+                return Err(Error::from(format!(
+                    "ColumnTag -- expected {} labels, one per input, got {}",
+                    inputs.len(),
+                    labels.len()
+                )));
+            }
+            return Ok(labels.clone());
+        }
+        let values = inputs
+            .iter()
+            .map(|input| match (&input.input, self) {
+                (InputType::StdIn, _) => "stdin".to_string(),
+                (InputType::Path(path), TagValue::Stem) => match Path::new(path).file_stem() {
+                    Some(stem) => stem.to_string_lossy().to_string(),
+                    None => path.clone(),
+                },
+                (InputType::Path(path), _) => path.clone(),
+            })
+            .collect();
+        Ok(values)
+    }
+}
+
+pub struct ColumnTagConfig {
+    column_name: String,
+    value: String,
+}
+
+pub struct ColumnTag {
+    value: FieldValue,
+}
+
+impl ColumnTag {
+    /// appends a string column with the same value in every row; it is meant to go right
+    /// before a merge, to tell which input each merged row came from
+    pub fn new(column_name: &str, value: &str) -> Box<dyn DynHeaderSink> {
+        let config = ColumnTagConfig {
+            column_name: column_name.to_string(),
+            value: value.to_string(),
+        };
+        Box::new(config) as Box<dyn DynHeaderSink>
+    }
+}
+
+impl DynHeaderSink for ColumnTagConfig {
+    fn process_header(self: Box<Self>, header: &mut Header) -> ChopperResult<Box<dyn DataSink>> {
+        if header.field_names().contains(&self.column_name) {
+            return Err(Error::from(format!(
+                "ColumnTag -- input already has a column named [{}]",
+                self.column_name
+            )));
+        }
+        header.field_names_mut().push(self.column_name);
+        header.field_types_mut().push(FieldType::String);
+        Ok(Box::new(ColumnTag {
+            value: FieldValue::String(self.value),
+        }))
+    }
+}
+
+impl DataSink for ColumnTag {
+    fn write_row(&mut self, io_rows: &mut Vec<Row>) -> ChopperResult<()> {
+        let row = io_rows.get_mut(0).unwrap();
+        row.field_values.push(self.value.clone());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chopper::types::{FieldType, FieldValue, Header, Row};
+    use crate::filter::column_tag::{ColumnTag, TagValue};
+    use crate::input::input::{Input, InputFormat, InputType};
+
+    #[test]
+    fn test_column_tag() {
+        let mut header = Header::new(vec!["a".to_string()], vec![FieldType::Int]);
+        let mut sink = ColumnTag::new("source", "host1")
+            .process_header(&mut header)
+            .unwrap();
+        assert_eq!(header.field_names(), &vec!["a", "source"]);
+        assert_eq!(header.field_types()[1], FieldType::String);
+        let mut io_rows = vec![Row {
+            timestamp: 0,
+            field_values: vec![FieldValue::Int(1)],
+        }];
+        sink.write_row(&mut io_rows).unwrap();
+        assert_eq!(
+            io_rows[0].field_values,
+            vec![FieldValue::Int(1), FieldValue::String("host1".to_string())]
+        );
+        assert!(ColumnTag::new("a", "x")
+            .process_header(&mut header)
+            .is_err());
+    }
+
+    #[test]
+    fn test_tag_value() {
+        let inputs = vec![
+            Input {
+                input: InputType::Path("logs/host1.csv.gz".to_string()),
+                format: InputFormat::Auto,
+            },
+            Input {
+                input: InputType::StdIn,
+                format: InputFormat::Auto,
+            },
+        ];
+        assert_eq!(
+            TagValue::Path.values(&inputs).unwrap(),
+            vec!["logs/host1.csv.gz", "stdin"]
+        );
+        assert_eq!(
+            TagValue::Stem.values(&inputs).unwrap(),
+            vec!["host1.csv", "stdin"]
+        );
+        assert!(TagValue::Labels(vec!["a".to_string()])
+            .values(&inputs)
+            .is_err());
+    }
+}
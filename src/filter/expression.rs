@@ -0,0 +1,958 @@
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+
+use regex::Regex;
+
+use crate::chopper::error::{ChopperResult, Error};
+use crate::chopper::types::{FieldType, FieldValue, Header, Row};
+
+/// small expression language over row fields;
+/// columns are referenced by name, or by `quoted name` if name is not a plain identifier,
+/// and `@timestamp` is row timestamp in nanos;
+/// supports literals (42, 1.5, "str" or 'str', true, false, null), arithmetic (+ - * / %),
+/// comparisons (== = != < <= > >=), `x in (a, b)`, `x not in (a, b)`, regex match
+/// (`x =~ "re"`, `x !~ "re"`), `x is null`, `x is not null`, and boolean logic
+/// (&& || ! or and/or/not);
+/// null propagates through arithmetic and comparisons like in sql, and rows for which
+/// the whole expression is not true are filtered out;
+/// types are checked when compiling against a header, and string columns used as numbers,
+/// e.g. of csv input without type inference, are parsed and are null if not a number
+#[derive(Clone, Debug)]
+pub enum Expr<C> {
+    Literal(Literal),
+    Column(C),
+    Timestamp,
+    Negate(Box<Expr<C>>),
+    Arithmetic(ArithmeticOp, Box<Expr<C>>, Box<Expr<C>>),
+    Compare(CompareOp, Box<Expr<C>>, Box<Expr<C>>),
+    In(Box<Expr<C>>, Vec<Expr<C>>, bool),
+    Match(Box<Expr<C>>, Regex, bool),
+    IsNull(Box<Expr<C>>, bool),
+    Not(Box<Expr<C>>),
+    And(Box<Expr<C>>, Box<Expr<C>>),
+    Or(Box<Expr<C>>, Box<Expr<C>>),
+    /// string parsed as a number, only added by compile
+    ToNumber(Box<Expr<C>>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Literal {
+    Null,
+    Bool(bool),
+    Long(i64),
+    Double(f64),
+    String(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArithmeticOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// expression with column names, as parsed
+pub type ParsedExpr = Expr<String>;
+/// expression with column indexes, as compiled against a header
+pub type CompiledExpr = Expr<usize>;
+
+impl ParsedExpr {
+    pub fn parse(text: &str) -> ChopperResult<ParsedExpr> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.parse_or()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => Err(expression_error(
+                Phase::Parse,
+                format!("unexpected {:?}", token),
+            )),
+        }
+    }
+
+    /// compiled expression is a predicate, so it has to be boolean
+    pub fn compile(self, header: &Header) -> ChopperResult<CompiledExpr> {
+        let (expr, expr_type) = self.compile_typed(header)?;
+        expect_bool(expr_type)?;
+        Ok(expr)
+    }
+
+    fn compile_typed(self, header: &Header) -> ChopperResult<(CompiledExpr, ExprType)> {
+        let compile = |e: Box<ParsedExpr>| e.compile_typed(header);
+        let boxed = |e: CompiledExpr| Box::new(e);
+        Ok(match self {
+            Expr::Literal(l) => {
+                let expr_type = match &l {
+                    Literal::Null => ExprType::Null,
+                    Literal::Bool(_) => ExprType::Bool,
+                    Literal::Long(_) | Literal::Double(_) => ExprType::Number,
+                    Literal::String(_) => ExprType::Str,
+                };
+                (Expr::Literal(l), expr_type)
+            }
+            Expr::Column(name) => {
+                let index = header.get_field_index(&name)?;
+                let expr_type = ExprType::from_field_type(header.field_types()[index]);
+                (Expr::Column(index), expr_type)
+            }
+            Expr::Timestamp => (Expr::Timestamp, ExprType::Number),
+            Expr::Negate(e) => (
+                Expr::Negate(boxed(to_number(compile(e)?)?)),
+                ExprType::Number,
+            ),
+            Expr::Arithmetic(op, l, r) => {
+                let l = to_number(compile(l)?)?;
+                let r = to_number(compile(r)?)?;
+                (Expr::Arithmetic(op, boxed(l), boxed(r)), ExprType::Number)
+            }
+            Expr::Compare(op, l, r) => {
+                let r = compile(r)?;
+                let (l, l_type) = coerce_for(compile(l)?, &[r.1])?;
+                let r = coerce_to(l_type, r)?;
+                (Expr::Compare(op, boxed(l), boxed(r)), ExprType::Bool)
+            }
+            Expr::In(e, list, negated) => {
+                let mut items: Vec<(CompiledExpr, ExprType)> = Vec::with_capacity(list.len());
+                for item in list {
+                    items.push(item.compile_typed(header)?);
+                }
+                let item_types: Vec<ExprType> = items.iter().map(|(_, t)| *t).collect();
+                let (e, e_type) = coerce_for(compile(e)?, &item_types)?;
+                let mut compiled: Vec<CompiledExpr> = Vec::with_capacity(items.len());
+                for item in items {
+                    compiled.push(coerce_to(e_type, item)?);
+                }
+                (Expr::In(boxed(e), compiled, negated), ExprType::Bool)
+            }
+            Expr::Match(e, regex, negated) => {
+                let (e, e_type) = compile(e)?;
+                if e_type != ExprType::Str && e_type != ExprType::Null {
+                    return Err(expression_error(
+                        Phase::Compile,
+                        format!("cannot match {:?} against regex", e_type),
+                    ));
+                }
+                (Expr::Match(boxed(e), regex, negated), ExprType::Bool)
+            }
+            Expr::IsNull(e, negated) => {
+                (Expr::IsNull(boxed(compile(e)?.0), negated), ExprType::Bool)
+            }
+            Expr::Not(e) => (Expr::Not(boxed(to_bool(compile(e)?)?)), ExprType::Bool),
+            Expr::And(l, r) => {
+                let l = to_bool(compile(l)?)?;
+                let r = to_bool(compile(r)?)?;
+                (Expr::And(boxed(l), boxed(r)), ExprType::Bool)
+            }
+            Expr::Or(l, r) => {
+                let l = to_bool(compile(l)?)?;
+                let r = to_bool(compile(r)?)?;
+                (Expr::Or(boxed(l), boxed(r)), ExprType::Bool)
+            }
+            Expr::ToNumber(e) => (Expr::ToNumber(boxed(compile(e)?.0)), ExprType::Number),
+        })
+    }
+}
+
+/// type of an expression as known when compiling; all integer and floating point types
+/// are numbers, same as in Value
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ExprType {
+    /// null literal, goes with any type
+    Null,
+    Bool,
+    Number,
+    Str,
+    Unsupported,
+}
+
+impl ExprType {
+    fn from_field_type(field_type: FieldType) -> ExprType {
+        match field_type {
+            FieldType::Boolean => ExprType::Bool,
+            FieldType::Byte
+            | FieldType::Char
+            | FieldType::Double
+            | FieldType::Float
+            | FieldType::Int
+            | FieldType::Long
+            | FieldType::Short => ExprType::Number,
+            FieldType::String => ExprType::Str,
+            FieldType::ByteBuf | FieldType::MultiDimDoubleArray => ExprType::Unsupported,
+        }
+    }
+}
+
+fn expect_bool(expr_type: ExprType) -> ChopperResult<()> {
+    match expr_type {
+        ExprType::Bool | ExprType::Null => Ok(()),
+        t => Err(expression_error(
+            Phase::Compile,
+            format!("expected boolean, got {:?}", t),
+        )),
+    }
+}
+
+fn to_bool((expr, expr_type): (CompiledExpr, ExprType)) -> ChopperResult<CompiledExpr> {
+    expect_bool(expr_type)?;
+    Ok(expr)
+}
+
+/// only string columns are parsed, string literals have to be written as numbers instead
+fn to_number((expr, expr_type): (CompiledExpr, ExprType)) -> ChopperResult<CompiledExpr> {
+    match expr_type {
+        ExprType::Number | ExprType::Null => Ok(expr),
+        ExprType::Str if matches!(expr, Expr::Column(_)) => Ok(Expr::ToNumber(Box::new(expr))),
+        t => Err(expression_error(
+            Phase::Compile,
+            format!("expected number, got {:?}", t),
+        )),
+    }
+}
+
+/// left side of a comparison becomes a number if a string column is compared to numbers
+fn coerce_for(
+    (expr, expr_type): (CompiledExpr, ExprType),
+    other_types: &[ExprType],
+) -> ChopperResult<(CompiledExpr, ExprType)> {
+    match expr_type == ExprType::Str && other_types.contains(&ExprType::Number) {
+        true => Ok((to_number((expr, expr_type))?, ExprType::Number)),
+        false => Ok((expr, expr_type)),
+    }
+}
+
+/// right side of a comparison, which has to be of the same type as left side
+fn coerce_to(
+    target: ExprType,
+    (expr, expr_type): (CompiledExpr, ExprType),
+) -> ChopperResult<CompiledExpr> {
+    match (target, expr_type) {
+        (ExprType::Null, _) | (_, ExprType::Null) => Ok(expr),
+        (ExprType::Number, ExprType::Str) => to_number((expr, expr_type)),
+        (t, e) if t == e && t != ExprType::Unsupported => Ok(expr),
+        (t, e) => Err(expression_error(
+            Phase::Compile,
+            format!("cannot compare {:?} and {:?}", t, e),
+        )),
+    }
+}
+
+impl CompiledExpr {
+    /// null counts as false
+    pub fn is_true(&self, row: &Row) -> ChopperResult<bool> {
+        match self.eval(row)? {
+            Value::Bool(b) => Ok(b),
+            Value::Null => Ok(false),
+            v => Err(expression_error(
+                Phase::Eval,
+                format!("expected boolean, got {:?}", v),
+            )),
+        }
+    }
+
+    fn eval<'a>(&'a self, row: &'a Row) -> ChopperResult<Value<'a>> {
+        Ok(match self {
+            Expr::Literal(l) => Value::from_literal(l),
+            Expr::Column(i) => Value::from_field_value(&row.field_values[*i]),
+            Expr::Timestamp => Value::Long(i64::try_from(row.timestamp).map_err(|_| {
+                expression_error(
+                    Phase::Eval,
+                    format!("timestamp {} is too large", row.timestamp),
+                )
+            })?),
+            Expr::Negate(e) => match e.eval(row)? {
+                Value::Null => Value::Null,
+                Value::Long(x) => Value::Long(x.checked_neg().ok_or_else(overflow)?),
+                Value::Double(x) => Value::Double(-x),
+                v => {
+                    return Err(expression_error(
+                        Phase::Eval,
+                        format!("cannot negate {:?}", v),
+                    ))
+                }
+            },
+            Expr::Arithmetic(op, l, r) => arithmetic(*op, l.eval(row)?, r.eval(row)?)?,
+            Expr::Compare(op, l, r) => match compare(&l.eval(row)?, &r.eval(row)?)? {
+                None => Value::Null,
+                Some(ordering) => Value::Bool(match op {
+                    CompareOp::Eq => ordering == Ordering::Equal,
+                    CompareOp::Ne => ordering != Ordering::Equal,
+                    CompareOp::Lt => ordering == Ordering::Less,
+                    CompareOp::Le => ordering != Ordering::Greater,
+                    CompareOp::Gt => ordering == Ordering::Greater,
+                    CompareOp::Ge => ordering != Ordering::Less,
+                }),
+            },
+            Expr::In(e, list, negated) => {
+                let value = e.eval(row)?;
+                if let Value::Null = value {
+                    return Ok(Value::Null);
+                }
+                let mut found = false;
+                for item in list {
+                    if compare(&value, &item.eval(row)?)? == Some(Ordering::Equal) {
+                        found = true;
+                        break;
+                    }
+                }
+                Value::Bool(found != *negated)
+            }
+            Expr::Match(e, regex, negated) => match e.eval(row)? {
+                Value::Null => Value::Null,
+                Value::Str(s) => Value::Bool(regex.is_match(s) != *negated),
+                v => {
+                    return Err(expression_error(
+                        Phase::Eval,
+                        format!("cannot match {:?} against regex", v),
+                    ))
+                }
+            },
+            Expr::IsNull(e, negated) => {
+                Value::Bool(matches!(e.eval(row)?, Value::Null) != *negated)
+            }
+            Expr::Not(e) => match e.eval(row)?.to_bool()? {
+                None => Value::Null,
+                Some(b) => Value::Bool(!b),
+            },
+            Expr::And(l, r) => match l.eval(row)?.to_bool()? {
+                Some(false) => Value::Bool(false),
+                left => match (left, r.eval(row)?.to_bool()?) {
+                    (_, Some(false)) => Value::Bool(false),
+                    (Some(true), Some(true)) => Value::Bool(true),
+                    _ => Value::Null,
+                },
+            },
+            Expr::Or(l, r) => match l.eval(row)?.to_bool()? {
+                Some(true) => Value::Bool(true),
+                left => match (left, r.eval(row)?.to_bool()?) {
+                    (_, Some(true)) => Value::Bool(true),
+                    (Some(false), Some(false)) => Value::Bool(false),
+                    _ => Value::Null,
+                },
+            },
+            Expr::ToNumber(e) => match e.eval(row)? {
+                Value::Str(s) => parse_number(s),
+                v => v,
+            },
+        })
+    }
+}
+
+/// all integer types are widened to Long and floating point types to Double
+#[derive(Debug)]
+enum Value<'a> {
+    Null,
+    Bool(bool),
+    Long(i64),
+    Double(f64),
+    Str(&'a str),
+    /// byte buffers and arrays can only be checked for null
+    Unsupported,
+}
+
+impl<'a> Value<'a> {
+    fn from_literal(literal: &'a Literal) -> Value<'a> {
+        match literal {
+            Literal::Null => Value::Null,
+            Literal::Bool(x) => Value::Bool(*x),
+            Literal::Long(x) => Value::Long(*x),
+            Literal::Double(x) => Value::Double(*x),
+            Literal::String(x) => Value::Str(x),
+        }
+    }
+
+    fn from_field_value(value: &'a FieldValue) -> Value<'a> {
+        match value {
+            FieldValue::Boolean(x) => Value::Bool(*x),
+            FieldValue::Byte(x) => Value::Long(*x as i64),
+            FieldValue::Char(x) => Value::Long(*x as i64),
+            FieldValue::Double(x) => Value::Double(*x),
+            FieldValue::Float(x) => Value::Double(*x as f64),
+            FieldValue::Int(x) => Value::Long(*x as i64),
+            FieldValue::Long(x) => Value::Long(*x),
+            FieldValue::Short(x) => Value::Long(*x as i64),
+            FieldValue::String(x) => Value::Str(x),
+            FieldValue::None => Value::Null,
+            FieldValue::ByteBuf(_) | FieldValue::MultiDimDoubleArray(_) => Value::Unsupported,
+        }
+    }
+
+    fn to_bool(&self) -> ChopperResult<Option<bool>> {
+        match self {
+            Value::Null => Ok(None),
+            Value::Bool(b) => Ok(Some(*b)),
+            v => Err(expression_error(
+                Phase::Eval,
+                format!("expected boolean, got {:?}", v),
+            )),
+        }
+    }
+}
+
+/// None means that one of the values is null or that doubles are not comparable, i.e. NaN
+fn compare(left: &Value, right: &Value) -> ChopperResult<Option<Ordering>> {
+    Ok(match (left, right) {
+        (Value::Null, _) | (_, Value::Null) => None,
+        (Value::Bool(l), Value::Bool(r)) => Some(l.cmp(r)),
+        (Value::Long(l), Value::Long(r)) => Some(l.cmp(r)),
+        (Value::Long(l), Value::Double(r)) => (*l as f64).partial_cmp(r),
+        (Value::Double(l), Value::Long(r)) => l.partial_cmp(&(*r as f64)),
+        (Value::Double(l), Value::Double(r)) => l.partial_cmp(r),
+        (Value::Str(l), Value::Str(r)) => Some(l.cmp(r)),
+        (l, r) => {
+            return Err(expression_error(
+                Phase::Eval,
+                format!("cannot compare {:?} and {:?}", l, r),
+            ))
+        }
+    })
+}
+
+fn arithmetic<'a>(op: ArithmeticOp, left: Value<'a>, right: Value<'a>) -> ChopperResult<Value<'a>> {
+    Ok(match (left, right) {
+        (Value::Null, _) | (_, Value::Null) => Value::Null,
+        (Value::Long(l), Value::Long(r)) => {
+            let result = match op {
+                ArithmeticOp::Add => l.checked_add(r),
+                ArithmeticOp::Sub => l.checked_sub(r),
+                ArithmeticOp::Mul => l.checked_mul(r),
+                // division by zero is null rather than an error
+                ArithmeticOp::Div | ArithmeticOp::Rem if r == 0 => return Ok(Value::Null),
+                ArithmeticOp::Div => l.checked_div(r),
+                ArithmeticOp::Rem => l.checked_rem(r),
+            };
+            Value::Long(result.ok_or_else(overflow)?)
+        }
+        (Value::Long(l), Value::Double(r)) => Value::Double(double_arithmetic(op, l as f64, r)),
+        (Value::Double(l), Value::Long(r)) => Value::Double(double_arithmetic(op, l, r as f64)),
+        (Value::Double(l), Value::Double(r)) => Value::Double(double_arithmetic(op, l, r)),
+        (l, r) => {
+            return Err(expression_error(
+                Phase::Eval,
+                format!("cannot apply {:?} to {:?} and {:?}", op, l, r),
+            ))
+        }
+    })
+}
+
+fn parse_number<'a>(s: &str) -> Value<'a> {
+    let s = s.trim();
+    if let Ok(x) = s.parse::<i64>() {
+        Value::Long(x)
+    } else if let Ok(x) = s.parse::<f64>() {
+        Value::Double(x)
+    } else {
+        Value::Null
+    }
+}
+
+fn double_arithmetic(op: ArithmeticOp, left: f64, right: f64) -> f64 {
+    match op {
+        ArithmeticOp::Add => left + right,
+        ArithmeticOp::Sub => left - right,
+        ArithmeticOp::Mul => left * right,
+        ArithmeticOp::Div => left / right,
+        ArithmeticOp::Rem => left % right,
+    }
+}
+
+fn overflow() -> Error {
+    expression_error(Phase::Eval, "integer overflow".to_string())
+}
+
+/// where an expression went wrong
+#[derive(Clone, Copy, Debug)]
+enum Phase {
+    Parse,
+    Compile,
+    Eval,
+}
+
+fn expression_error(phase: Phase, msg: String) -> Error {
+    let kind = match phase {
+        Phase::Parse => "syntax error",
+        Phase::Compile => "type error",
+        Phase::Eval => "evaluation error",
+    };
+    Error::from(format!("filter expression -- {}: {}", kind, msg))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Column(String),
+    Timestamp,
+    Str(String),
+    Long(i64),
+    Double(f64),
+    Op(&'static str),
+    LParen,
+    RParen,
+    Comma,
+}
+
+/// longer operators go first, so that e.g. <= is not read as <
+const OPERATORS: [&str; 18] = [
+    "&&", "||", "==", "!=", "<=", ">=", "=~", "!~", "!", "=", "<", ">", "+", "-", "*", "/", "%",
+    "@",
+];
+
+fn tokenize(text: &str) -> ChopperResult<Vec<Token>> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '(' || c == ')' || c == ',' {
+            tokens.push(match c {
+                '(' => Token::LParen,
+                ')' => Token::RParen,
+                _ => Token::Comma,
+            });
+            i += 1;
+        } else if c == '"' || c == '\'' || c == '`' {
+            let (s, next) = read_quoted(&chars, i)?;
+            tokens.push(match c {
+                '`' => Token::Column(s),
+                _ => Token::Str(s),
+            });
+            i = next;
+        } else if c.is_ascii_digit()
+            || (c == '.' && i + 1 < chars.len() && chars[i + 1].is_ascii_digit())
+        {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_ascii_alphanumeric()
+                    || chars[i] == '.'
+                    || ((chars[i] == '+' || chars[i] == '-')
+                        && (chars[i - 1] == 'e' || chars[i - 1] == 'E')))
+            {
+                i += 1;
+            }
+            let s: String = chars[start..i].iter().collect();
+            tokens.push(match s.parse::<i64>() {
+                Ok(x) => Token::Long(x),
+                Err(_) => match s.parse::<f64>() {
+                    Ok(x) => Token::Double(x),
+                    Err(_) => {
+                        return Err(expression_error(
+                            Phase::Parse,
+                            format!("invalid number {}", s),
+                        ))
+                    }
+                },
+            });
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '.')
+            {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            match OPERATORS.iter().find(|op| rest.starts_with(*op)) {
+                Some(&"@") => match tokenize_timestamp(&chars, i) {
+                    Some(next) => {
+                        tokens.push(Token::Timestamp);
+                        i = next;
+                    }
+                    None => {
+                        return Err(expression_error(
+                            Phase::Parse,
+                            "expected @timestamp".to_string(),
+                        ))
+                    }
+                },
+                Some(op) => {
+                    tokens.push(Token::Op(op));
+                    i += op.len();
+                }
+                None => {
+                    return Err(expression_error(
+                        Phase::Parse,
+                        format!("unexpected character '{}'", c),
+                    ))
+                }
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn tokenize_timestamp(chars: &[char], start: usize) -> Option<usize> {
+    let name = "timestamp";
+    let end = start + 1 + name.len();
+    if end > chars.len() || chars[start + 1..end].iter().collect::<String>() != name {
+        return None;
+    }
+    match chars.get(end) {
+        Some(c) if c.is_alphanumeric() || *c == '_' => None,
+        _ => Some(end),
+    }
+}
+
+/// backslash escapes the next character, with \n, \t and \r meaning the usual
+fn read_quoted(chars: &[char], start: usize) -> ChopperResult<(String, usize)> {
+    let quote = chars[start];
+    let mut s = String::new();
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if i + 1 < chars.len() => {
+                s.push(match chars[i + 1] {
+                    'n' => '\n',
+                    't' => '\t',
+                    'r' => '\r',
+                    c => c,
+                });
+                i += 2;
+            }
+            c if c == quote => return Ok((s, i + 1)),
+            c => {
+                s.push(c);
+                i += 1;
+            }
+        }
+    }
+    Err(expression_error(
+        Phase::Parse,
+        format!("missing closing {}", quote),
+    ))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> ChopperResult<Token> {
+        match self.tokens.get(self.pos) {
+            None => Err(expression_error(
+                Phase::Parse,
+                "unexpected end of expression".to_string(),
+            )),
+            Some(token) => {
+                self.pos += 1;
+                Ok(token.clone())
+            }
+        }
+    }
+
+    /// consumes next token if it is given operator or keyword; keywords are case-insensitive
+    fn accept(&mut self, ops: &[&str]) -> bool {
+        let found = match self.peek() {
+            Some(Token::Op(op)) => ops.contains(op),
+            Some(Token::Ident(ident)) => ops.iter().any(|op| op.eq_ignore_ascii_case(ident)),
+            _ => false,
+        };
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, expected: Token) -> ChopperResult<()> {
+        let token = self.next()?;
+        match token == expected {
+            true => Ok(()),
+            false => Err(expression_error(
+                Phase::Parse,
+                format!("expected {:?}, got {:?}", expected, token),
+            )),
+        }
+    }
+
+    fn parse_or(&mut self) -> ChopperResult<ParsedExpr> {
+        let mut left = self.parse_and()?;
+        while self.accept(&["||", "or"]) {
+            left = Expr::Or(Box::new(left), Box::new(self.parse_and()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> ChopperResult<ParsedExpr> {
+        let mut left = self.parse_not()?;
+        while self.accept(&["&&", "and"]) {
+            left = Expr::And(Box::new(left), Box::new(self.parse_not()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_not(&mut self) -> ChopperResult<ParsedExpr> {
+        match self.accept(&["!", "not"]) {
+            true => Ok(Expr::Not(Box::new(self.parse_not()?))),
+            false => self.parse_comparison(),
+        }
+    }
+
+    fn parse_comparison(&mut self) -> ChopperResult<ParsedExpr> {
+        let left = Box::new(self.parse_additive()?);
+        let compare_ops = [
+            ("==", CompareOp::Eq),
+            ("=", CompareOp::Eq),
+            ("!=", CompareOp::Ne),
+            ("<", CompareOp::Lt),
+            ("<=", CompareOp::Le),
+            (">", CompareOp::Gt),
+            (">=", CompareOp::Ge),
+        ];
+        for (op, compare_op) in compare_ops.iter() {
+            if self.accept(&[op]) {
+                return Ok(Expr::Compare(
+                    *compare_op,
+                    left,
+                    Box::new(self.parse_additive()?),
+                ));
+            }
+        }
+        if self.accept(&["in"]) {
+            return Ok(Expr::In(left, self.parse_list()?, false));
+        }
+        if self.accept(&["=~"]) {
+            return Ok(Expr::Match(left, self.parse_regex()?, false));
+        }
+        if self.accept(&["!~"]) {
+            return Ok(Expr::Match(left, self.parse_regex()?, true));
+        }
+        if self.accept(&["is"]) {
+            let negated = self.accept(&["not"]);
+            return match self.accept(&["null"]) {
+                true => Ok(Expr::IsNull(left, negated)),
+                false => Err(expression_error(
+                    Phase::Parse,
+                    "expected null after is".to_string(),
+                )),
+            };
+        }
+        // only "not in" can follow an operand, since "not" is otherwise a prefix
+        if let (Some(Token::Ident(not)), Some(Token::Ident(in_))) =
+            (self.tokens.get(self.pos), self.tokens.get(self.pos + 1))
+        {
+            if not.eq_ignore_ascii_case("not") && in_.eq_ignore_ascii_case("in") {
+                self.pos += 2;
+                return Ok(Expr::In(left, self.parse_list()?, true));
+            }
+        }
+        Ok(*left)
+    }
+
+    fn parse_list(&mut self) -> ChopperResult<Vec<ParsedExpr>> {
+        self.expect(Token::LParen)?;
+        let mut list = vec![self.parse_additive()?];
+        while self.peek() == Some(&Token::Comma) {
+            self.pos += 1;
+            list.push(self.parse_additive()?);
+        }
+        self.expect(Token::RParen)?;
+        Ok(list)
+    }
+
+    fn parse_regex(&mut self) -> ChopperResult<Regex> {
+        match self.next()? {
+            Token::Str(s) => match Regex::new(&s) {
+                Ok(regex) => Ok(regex),
+                Err(e) => Err(expression_error(
+                    Phase::Parse,
+                    format!("invalid regex {} -- {}", s, e),
+                )),
+            },
+            token => Err(expression_error(
+                Phase::Parse,
+                format!("expected regex string, got {:?}", token),
+            )),
+        }
+    }
+
+    fn parse_additive(&mut self) -> ChopperResult<ParsedExpr> {
+        let mut left = self.parse_multiplicative()?;
+        loop {
+            let op = if self.accept(&["+"]) {
+                ArithmeticOp::Add
+            } else if self.accept(&["-"]) {
+                ArithmeticOp::Sub
+            } else {
+                return Ok(left);
+            };
+            left = Expr::Arithmetic(op, Box::new(left), Box::new(self.parse_multiplicative()?));
+        }
+    }
+
+    fn parse_multiplicative(&mut self) -> ChopperResult<ParsedExpr> {
+        let mut left = self.parse_unary()?;
+        loop {
+            let op = if self.accept(&["*"]) {
+                ArithmeticOp::Mul
+            } else if self.accept(&["/"]) {
+                ArithmeticOp::Div
+            } else if self.accept(&["%"]) {
+                ArithmeticOp::Rem
+            } else {
+                return Ok(left);
+            };
+            left = Expr::Arithmetic(op, Box::new(left), Box::new(self.parse_unary()?));
+        }
+    }
+
+    fn parse_unary(&mut self) -> ChopperResult<ParsedExpr> {
+        match self.accept(&["-"]) {
+            true => Ok(Expr::Negate(Box::new(self.parse_unary()?))),
+            false => self.parse_primary(),
+        }
+    }
+
+    fn parse_primary(&mut self) -> ChopperResult<ParsedExpr> {
+        Ok(match self.next()? {
+            Token::LParen => {
+                let expr = self.parse_or()?;
+                self.expect(Token::RParen)?;
+                expr
+            }
+            Token::Str(s) => Expr::Literal(Literal::String(s)),
+            Token::Long(x) => Expr::Literal(Literal::Long(x)),
+            Token::Double(x) => Expr::Literal(Literal::Double(x)),
+            Token::Timestamp => Expr::Timestamp,
+            Token::Column(name) => Expr::Column(name),
+            Token::Ident(ident) => match ident.to_ascii_lowercase().as_str() {
+                "null" => Expr::Literal(Literal::Null),
+                "true" => Expr::Literal(Literal::Bool(true)),
+                "false" => Expr::Literal(Literal::Bool(false)),
+                _ => Expr::Column(ident),
+            },
+            token => {
+                return Err(expression_error(
+                    Phase::Parse,
+                    format!("unexpected {:?}", token),
+                ))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chopper::types::{FieldType, FieldValue, Header, Row};
+    use crate::filter::expression::ParsedExpr;
+
+    fn eval(text: &str) -> bool {
+        let header = Header::new(
+            vec![
+                "sym".to_string(),
+                "price".to_string(),
+                "size".to_string(),
+                "missing".to_string(),
+                "my col".to_string(),
+            ],
+            vec![
+                FieldType::String,
+                FieldType::Double,
+                FieldType::Int,
+                FieldType::Long,
+                FieldType::Boolean,
+            ],
+        );
+        let row = Row {
+            timestamp: 1_000,
+            field_values: vec![
+                FieldValue::String("AAPL".to_string()),
+                FieldValue::Double(10.5),
+                FieldValue::Int(300),
+                FieldValue::None,
+                FieldValue::Boolean(true),
+            ],
+        };
+        let expr = ParsedExpr::parse(text).unwrap().compile(&header).unwrap();
+        expr.is_true(&row).unwrap()
+    }
+
+    #[test]
+    fn test_expression() {
+        assert!(eval("price > 10 && sym in (\"AAPL\", \"MSFT\")"));
+        assert!(!eval("price > 10 and sym not in ('AAPL')"));
+        assert!(eval("price * size >= 3150 || false"));
+        assert!(eval("size % 7 == 6 && -size < 0 && size / 0 is null"));
+        assert!(eval("sym =~ \"^AA\" && (sym !~ 'L$') == false"));
+        assert!(eval(
+            "missing is null and missing is not null or @timestamp = 1000"
+        ));
+        assert!(!eval("missing > 1 || missing = null"));
+        assert!(eval("(not (missing > 1)) is null"));
+        assert!(eval("`my col` && !(1 + 2 * 3 != 7)"));
+        assert!(eval("1.5e1 > 1e1 AND .5 < 1"));
+    }
+
+    #[test]
+    fn test_expression_errors() {
+        let header = Header::new(vec!["a".to_string()], vec![FieldType::Long]);
+        assert!(ParsedExpr::parse("a >").is_err());
+        assert!(ParsedExpr::parse("a > 1)").is_err());
+        assert!(ParsedExpr::parse("a =~ '('").is_err());
+        assert!(ParsedExpr::parse("\"abc").is_err());
+        assert!(ParsedExpr::parse("@time > 1").is_err());
+        assert!(ParsedExpr::parse("b > 1")
+            .unwrap()
+            .compile(&header)
+            .is_err());
+
+        // type errors are found before any row is seen
+        let compile = |text: &str| ParsedExpr::parse(text).unwrap().compile(&header);
+        assert!(compile("a > 'x'").is_err());
+        assert!(compile("a + 1").is_err());
+        assert!(compile("a in (1, 'x')").is_err());
+        assert!(compile("a =~ 'x'").is_err());
+        assert!(compile("a > 1 && 'x'").is_err());
+        assert!(compile("-'x' < 1").is_err());
+
+        let expr = compile("@timestamp > 0").unwrap();
+        let row = Row {
+            timestamp: u64::MAX,
+            field_values: vec![FieldValue::Long(1)],
+        };
+        assert!(expr.is_true(&row).is_err());
+    }
+
+    #[test]
+    fn test_string_columns_as_numbers() {
+        let header = Header::new(
+            vec!["price".to_string(), "sym".to_string()],
+            vec![FieldType::String, FieldType::String],
+        );
+        let row = |price: &str| Row {
+            timestamp: 0,
+            field_values: vec![
+                FieldValue::String(price.to_string()),
+                FieldValue::String("10".to_string()),
+            ],
+        };
+        let is_true = |text: &str, price: &str| {
+            let expr = ParsedExpr::parse(text).unwrap().compile(&header).unwrap();
+            expr.is_true(&row(price)).unwrap()
+        };
+        assert!(is_true("price > 10", "10.5"));
+        assert!(!is_true("price > 10", "9"));
+        assert!(is_true("price * 2 = 22 && -price < 0", "11"));
+        assert!(is_true("price in (1, 2.5)", " 2.5"));
+        assert!(is_true("(price > 10) is null", "n/a"));
+        assert!(is_true("price > sym", "11"));
+        assert!(is_true("price = '11'", "11"));
+    }
+}
@@ -1,6 +1,10 @@
 #[allow(dead_code)]
 pub mod column_filter_delete_col;
+pub mod column_projection;
+pub mod column_tag;
+pub mod expression;
 #[allow(dead_code)]
 pub mod row_filter_equal_value;
+pub mod row_filter_expression;
 #[allow(dead_code)]
 pub mod row_filter_greater_value;
@@ -0,0 +1,41 @@
+use crate::chopper::error::ChopperResult;
+use crate::chopper::sink::{DataSink, DynHeaderSink};
+use crate::chopper::types::{Header, Row};
+use crate::filter::expression::{CompiledExpr, ParsedExpr};
+
+pub struct RowFilterExpressionConfig {
+    expression: ParsedExpr,
+}
+
+pub struct RowFilterExpression {
+    expression: CompiledExpr,
+}
+
+impl RowFilterExpression {
+    /// expression is parsed right away, so syntax errors show up before any input is read;
+    /// see Expr for the syntax
+    pub fn new(expression: &str) -> ChopperResult<Box<dyn DynHeaderSink>> {
+        let config = RowFilterExpressionConfig {
+            expression: ParsedExpr::parse(expression)?,
+        };
+        Ok(Box::new(config) as Box<dyn DynHeaderSink>)
+    }
+}
+
+impl DynHeaderSink for RowFilterExpressionConfig {
+    fn process_header(self: Box<Self>, header: &mut Header) -> ChopperResult<Box<dyn DataSink>> {
+        let filter = RowFilterExpression {
+            expression: self.expression.compile(header)?,
+        };
+        Ok(Box::new(filter))
+    }
+}
+
+impl DataSink for RowFilterExpression {
+    fn write_row(&mut self, io_rows: &mut Vec<Row>) -> ChopperResult<()> {
+        if !self.expression.is_true(io_rows.first().unwrap())? {
+            io_rows.clear();
+        }
+        Ok(())
+    }
+}
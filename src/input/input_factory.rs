@@ -5,8 +5,14 @@ use crate::chopper::error::{ChopperResult, Error};
 use crate::input::files_in_dir_provider::FilesInDirPathProvider;
 use crate::input::input::{Input, InputFormat, InputType};
 use crate::input::single_file::SingleFileInputFactory;
+use crate::source::arrow_source_factory::ArrowSourceFactory;
+use crate::source::columnar_input_config::ColumnarInputConfig;
 use crate::source::csv_input_config::CSVInputConfig;
+use crate::source::json_input_config::JsonInputConfig;
+use crate::source::json_source_factory::JsonSourceFactory;
+use crate::source::monotonic_source::{MonotonicSource, OutOfOrderPolicy};
 use crate::source::multi_file_source::SerialMultiFileSource;
+use crate::source::parquet_source_factory::ParquetSourceFactory;
 use crate::source::source::Source;
 use crate::source::{
     csv_source_factory::CSVSourceFactory, dc_source_factory::DCSourceFactory,
@@ -27,6 +33,9 @@ use crate::util::reader::ChopperBufPreviewer;
 pub struct InputFactoryBuilder {
     dc_factory: Option<DCFactory>,
     csv_input_config: Option<CSVInputConfig>,
+    columnar_input_config: ColumnarInputConfig,
+    json_input_config: Option<JsonInputConfig>,
+    out_of_order_policy: Option<OutOfOrderPolicy>,
     user_source_factories: Option<Vec<Box<dyn SourceFactory>>>,
     user_streaming_transports: Option<Vec<Box<dyn StreamingTransport>>>,
 }
@@ -36,6 +45,9 @@ impl InputFactoryBuilder {
         InputFactoryBuilder {
             dc_factory: None,
             csv_input_config: None,
+            columnar_input_config: ColumnarInputConfig::new(),
+            json_input_config: None,
+            out_of_order_policy: None,
             user_source_factories: None,
             user_streaming_transports: None,
         }
@@ -51,6 +63,25 @@ impl InputFactoryBuilder {
         self
     }
 
+    pub fn with_columnar_input_config(
+        mut self,
+        columnar_input_config: ColumnarInputConfig,
+    ) -> Self {
+        self.columnar_input_config = columnar_input_config;
+        self
+    }
+
+    pub fn with_json_input_config(mut self, json_input_config: JsonInputConfig) -> Self {
+        self.json_input_config = Some(json_input_config);
+        self
+    }
+
+    /// sources are not checked for timestamp order unless policy is set
+    pub fn with_out_of_order_policy(mut self, out_of_order_policy: OutOfOrderPolicy) -> Self {
+        self.out_of_order_policy = Some(out_of_order_policy);
+        self
+    }
+
     pub fn with_user_source_factories(
         mut self,
         user_source_factories: Option<Vec<Box<dyn SourceFactory>>>,
@@ -71,6 +102,9 @@ impl InputFactoryBuilder {
         InputFactory::new(
             self.dc_factory,
             self.csv_input_config,
+            self.columnar_input_config,
+            self.json_input_config,
+            self.out_of_order_policy,
             self.user_source_factories,
             self.user_streaming_transports,
         )
@@ -80,12 +114,16 @@ impl InputFactoryBuilder {
 pub struct InputFactory {
     dir_transports: Vec<Box<dyn DirTransport>>,
     single_file_input_factory: SingleFileInputFactory,
+    out_of_order_policy: Option<OutOfOrderPolicy>,
 }
 
 impl InputFactory {
     fn new(
         dc_factory: Option<DCFactory>,
         csv_input_config: Option<CSVInputConfig>,
+        columnar_input_config: ColumnarInputConfig,
+        json_input_config: Option<JsonInputConfig>,
+        out_of_order_policy: Option<OutOfOrderPolicy>,
         user_source_factories: Option<Vec<Box<dyn SourceFactory>>>,
         user_streaming_transports: Option<Vec<Box<dyn StreamingTransport>>>,
     ) -> ChopperResult<Self> {
@@ -109,8 +147,12 @@ impl InputFactory {
         let dir_transports = create_default_dir_transports();
 
         // source factories
-        let mut default_source_factories =
-            create_default_source_factories(dc_factory, csv_input_config);
+        let mut default_source_factories = create_default_source_factories(
+            dc_factory,
+            csv_input_config,
+            columnar_input_config,
+            json_input_config,
+        );
         let source_factories = match user_source_factories {
             Some(mut s) => {
                 s.append(&mut default_source_factories);
@@ -128,6 +170,7 @@ impl InputFactory {
         Ok(InputFactory {
             dir_transports,
             single_file_input_factory,
+            out_of_order_policy,
         })
     }
 
@@ -139,6 +182,23 @@ impl InputFactory {
     }
 
     pub fn create_source_from_input(&mut self, input: &Input) -> ChopperResult<Box<dyn Source>> {
+        let source = self.create_unchecked_source_from_input(input)?;
+        match self.out_of_order_policy {
+            None => Ok(source),
+            Some(policy) => {
+                let name = match &input.input {
+                    InputType::Path(path) => path.clone(),
+                    InputType::StdIn => "stdin".to_string(),
+                };
+                Ok(Box::new(MonotonicSource::new(source, name, policy)))
+            }
+        }
+    }
+
+    fn create_unchecked_source_from_input(
+        &mut self,
+        input: &Input,
+    ) -> ChopperResult<Box<dyn Source>> {
         // first get stdin out of the way, since it doesn't need a transport
         let path = match &input.input {
             InputType::Path(path) => path,
@@ -188,8 +248,18 @@ impl InputFactory {
 fn create_default_source_factories(
     dc_factory: Option<DCFactory>,
     csv_input_config: Option<CSVInputConfig>,
+    columnar_input_config: ColumnarInputConfig,
+    json_input_config: Option<JsonInputConfig>,
 ) -> Vec<Box<dyn SourceFactory>> {
     let mut source_factories: Vec<Box<dyn SourceFactory>> = Vec::new();
+    // binary formats with magic numbers go first, since csv accepts pretty much anything
+    source_factories.push(Box::new(ParquetSourceFactory::new(
+        columnar_input_config.clone(),
+    )));
+    source_factories.push(Box::new(ArrowSourceFactory::new(columnar_input_config)));
+    if let Some(json_input_config) = json_input_config {
+        source_factories.push(Box::new(JsonSourceFactory::new(json_input_config)));
+    }
     if let Some(csv_input_config) = csv_input_config {
         source_factories.push(Box::new(CSVSourceFactory::new(csv_input_config)));
     }
@@ -1,5 +1,7 @@
-use std::io::Read;
+use std::cell::RefCell;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
 use std::path::Path;
+use std::rc::Rc;
 
 use crate::chopper::error::{ChopperResult, Error};
 use crate::decompress::decompress;
@@ -9,6 +11,7 @@ use crate::input::input::InputFormat;
 use crate::source::source::Source;
 use crate::source::source_factory::SourceFactory;
 use crate::transport::seekable::seekable_factory::SeekableTransportFactory;
+use crate::transport::seekable::ReadSeek;
 use crate::transport::streaming::previewer_factory::PreviewerTransportFactory;
 use crate::util::path::get_file_name;
 use crate::util::reader::ChopperBufPreviewer;
@@ -26,6 +29,14 @@ enum Format {
     DetectUsingFileContents,
 }
 
+struct SharedReader(Rc<RefCell<BufReader<Box<dyn ReadSeek>>>>);
+
+impl Read for SharedReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.borrow_mut().read(buf)
+    }
+}
+
 #[derive(Clone)]
 pub struct SingleFileInputFactory {
     seekable_transport_factory: SeekableTransportFactory,
@@ -65,6 +76,12 @@ impl SingleFileInputFactory {
                     &input_format,
                 )?));
             }
+            seekable.seek(SeekFrom::Start(0))?;
+            return Ok(Some(self.create_source_from_seekable(
+                seekable,
+                get_file_name(path),
+                input_format,
+            )?));
         }
 
         // try the non-seekable streaming transports
@@ -89,14 +106,7 @@ impl SingleFileInputFactory {
         input_format: &InputFormat,
     ) -> ChopperResult<Box<dyn Source>> {
         let format = match input_format {
-            InputFormat::Extension(extension) => {
-                let extension = if extension.starts_with(".") {
-                    extension.to_owned()
-                } else {
-                    ".".to_owned() + extension
-                };
-                Format::UserSpecified(extension)
-            }
+            InputFormat::Extension(extension) => Format::UserSpecified(Self::format(extension)),
             InputFormat::Auto => match file_name {
                 None => Format::DetectUsingFileContents,
                 Some(file_name) => Format::DetectUsingFileNameThenContents(file_name),
@@ -148,6 +158,66 @@ impl SingleFileInputFactory {
         }
     }
 
+    fn format(extension: &str) -> String {
+        if extension.starts_with(".") {
+            extension.to_owned()
+        } else {
+            ".".to_owned() + extension
+        }
+    }
+
+    /// formats that need random access read seekable inputs directly and the rest are read as
+    /// streams; either way the input is opened and previewed at most once
+    fn create_source_from_seekable(
+        &mut self,
+        seekable: BufReader<Box<dyn ReadSeek>>,
+        file_name: Option<String>,
+        input_format: &InputFormat,
+    ) -> ChopperResult<Box<dyn Source>> {
+        let format = match input_format {
+            InputFormat::Extension(extension) => Some(Self::format(extension)),
+            InputFormat::Auto => file_name.clone(),
+        };
+        // compressed inputs are always streamed
+        let format =
+            format.filter(|format| decompress::is_compressed_using_format(format).is_none());
+        let format_factory = format.as_ref().and_then(|format| {
+            self.source_factories
+                .iter()
+                .position(|sf| sf.can_create_from_format(format))
+        });
+        if let Some(index) = format_factory {
+            if self.source_factories[index].can_create_from_seekable() {
+                return self.source_factories[index].create_source_from_seekable(seekable);
+            }
+        }
+
+        // previewer shares the input, so that it can still be handed over as seekable
+        let seekable = Rc::new(RefCell::new(seekable));
+        let previewer =
+            ChopperBufPreviewer::new(Box::new(SharedReader(seekable.clone())) as Box<dyn Read>)?;
+        // same as with streaming, contents are looked at only if file name didn't help
+        let contents_factory = match (input_format, format_factory) {
+            (InputFormat::Auto, None)
+                if decompress::is_compressed_using_previewer(&previewer).is_none() =>
+            {
+                self.source_factories
+                    .iter()
+                    .position(|sf| sf.can_create_from_previewer(&previewer))
+            }
+            _ => None,
+        };
+        if let Some(index) = contents_factory {
+            if self.source_factories[index].can_create_from_seekable() {
+                drop(previewer);
+                let mut seekable = Rc::try_unwrap(seekable).ok().unwrap().into_inner();
+                seekable.seek(SeekFrom::Start(0))?;
+                return self.source_factories[index].create_source_from_seekable(seekable);
+            }
+        }
+        self.create_source_from_previewer(previewer, file_name, input_format)
+    }
+
     fn decompress_using_format(
         previewer: ChopperBufPreviewer<Box<dyn Read>>,
         format: String,
@@ -1,11 +1,14 @@
+pub mod aggregate;
 pub mod chopper;
 pub mod chopper_cli;
 pub mod cli;
 pub mod cli_app;
+pub mod compress;
 pub mod decompress;
 pub mod driver;
 pub mod filter;
 pub mod input;
+pub mod pipeline;
 pub mod serde;
 pub mod source;
 pub mod transport;
@@ -0,0 +1,392 @@
+use std::mem;
+
+use crate::chopper::driver::ChopperDriver;
+use crate::chopper::error::{ChopperResult, Error};
+use crate::chopper::types::{TimestampRange, TIMESTAMP_RANGE_ALL};
+use crate::driver::tie_break::TieBreakPolicy;
+use crate::input::input_factory::InputFactoryBuilder;
+use crate::pipeline::config::{
+    ColumnsOp, GroupByOp, InputConfig, MergeMode, MergeOp, Names, NodeConfig, PipelineConfig,
+    ResampleOp, RollingOp, TagOp,
+};
+use crate::util::tz::ChopperTz;
+use crate::write::factory::OutputFactory;
+
+/// what the pipeline as a whole is run with
+struct Settings {
+    input_factory_builder: InputFactoryBuilder,
+    output_factory: OutputFactory,
+    timezone: ChopperTz,
+    timestamp_range: TimestampRange,
+    tie_break_policy: TieBreakPolicy,
+}
+
+impl Settings {
+    fn new() -> Self {
+        Settings {
+            input_factory_builder: InputFactoryBuilder::new(),
+            output_factory: OutputFactory::new(),
+            timezone: ChopperTz::new_always_fails(),
+            timestamp_range: TIMESTAMP_RANGE_ALL,
+            tie_break_policy: TieBreakPolicy::default(),
+        }
+    }
+}
+
+/// rows of one input on their way to a merge
+#[derive(Clone)]
+struct Stream {
+    /// input or node the next step takes rows from
+    head: String,
+    /// path of the input the rows come from
+    label: String,
+}
+
+/// builds a PipelineConfig one step at a time, so that chains are laid out by the pipeline
+/// planner, e.g.
+///
+/// ```no_run
+/// # use chopper::pipeline::builder::Pipeline;
+/// let mut driver = Pipeline::from_paths(&["a.csv", "b.csv"])
+///     .filter("qty > 0")
+///     .merge()
+///     .split(|big| big.filter("qty >= 100").to_path("big.csv"))
+///     .columns("sym,px")
+///     .to_path("out.csv")
+///     .build()
+///     .unwrap();
+/// ```
+///
+/// with more than one input, steps before a merge apply to each input on its own; split
+/// passes every row to the given branch as well as to the steps after it; every stream has
+/// to end with to_path; errors, e.g. of an invalid expression, are reported by build
+pub struct Pipeline {
+    config: PipelineConfig,
+    streams: Vec<Stream>,
+    settings: Settings,
+    /// first misuse of the builder, reported by build
+    error: Option<String>,
+}
+
+impl Pipeline {
+    /// inputs are named by their paths, so each path can only be given once
+    pub fn from_paths<S: AsRef<str>>(paths: &[S]) -> Pipeline {
+        let mut pipeline = Pipeline {
+            config: PipelineConfig::default(),
+            streams: Vec::new(),
+            settings: Settings::new(),
+            error: None,
+        };
+        for path in paths {
+            let path = path.as_ref().to_string();
+            pipeline.config.inputs.push(InputConfig {
+                name: path.clone(),
+                path: path.clone(),
+                format: None,
+            });
+            pipeline.streams.push(Stream {
+                head: path.clone(),
+                label: path,
+            });
+        }
+        pipeline
+    }
+
+    /// settings apply to the whole pipeline, even if set inside of a split
+    pub fn with_input_factory_builder(
+        mut self,
+        input_factory_builder: InputFactoryBuilder,
+    ) -> Self {
+        self.settings.input_factory_builder = input_factory_builder;
+        self
+    }
+
+    pub fn with_output_factory(mut self, output_factory: OutputFactory) -> Self {
+        self.settings.output_factory = output_factory;
+        self
+    }
+
+    /// used by resample, group_by and output date formats
+    pub fn with_timezone(mut self, timezone: ChopperTz) -> Self {
+        self.settings.timezone = timezone;
+        self
+    }
+
+    pub fn with_timestamp_range(mut self, timestamp_range: TimestampRange) -> Self {
+        self.settings.timestamp_range = timestamp_range;
+        self
+    }
+
+    pub fn with_tie_break_policy(mut self, tie_break_policy: TieBreakPolicy) -> Self {
+        self.settings.tie_break_policy = tie_break_policy;
+        self
+    }
+
+    /// only rows for which expression is true, see Expr for the syntax
+    pub fn filter(self, expression: &str) -> Self {
+        self.step(|node| node.filter = Some(expression.to_string()))
+    }
+
+    /// same list format as --columns
+    pub fn columns(self, columns: &str) -> Self {
+        self.columns_op(Some(columns), None, None)
+    }
+
+    /// same list format as --drop
+    pub fn drop_columns(self, columns: &str) -> Self {
+        self.columns_op(None, Some(columns), None)
+    }
+
+    /// comma-separated list of old=new pairs
+    pub fn rename(self, renames: &str) -> Self {
+        self.columns_op(None, None, Some(renames))
+    }
+
+    /// appends a string column with the same value in every row
+    pub fn tag(self, column_name: &str, value: &str) -> Self {
+        self.step(|node| {
+            node.tag = Some(TagOp {
+                column: column_name.to_string(),
+                value: value.to_string(),
+            })
+        })
+    }
+
+    /// same as --rolling, --rolling-stats and --rolling-key
+    pub fn rolling(self, window: &str, stats: &str, key: Option<&str>) -> Self {
+        self.step(|node| {
+            node.rolling = Some(RollingOp {
+                window: window.to_string(),
+                stats: stats.to_string(),
+                key: key.map(|k| k.to_string()),
+            })
+        })
+    }
+
+    /// same as --resample, --agg and --resample-fill
+    pub fn resample(self, interval: &str, agg: &str, fill: Option<&str>) -> Self {
+        self.step(|node| {
+            node.resample = Some(ResampleOp {
+                interval: interval.to_string(),
+                agg: agg.to_string(),
+                fill: fill.map(|f| f.to_string()),
+            })
+        })
+    }
+
+    /// same as --group-by, --agg and --group-every
+    pub fn group_by(self, keys: &str, agg: &str, every: Option<&str>) -> Self {
+        self.step(|node| {
+            node.group_by = Some(GroupByOp {
+                keys: keys.to_string(),
+                agg: agg.to_string(),
+                every: every.map(|e| e.to_string()),
+            })
+        })
+    }
+
+    /// interleaves inputs, which have to have the same columns
+    pub fn merge(self) -> Self {
+        self.merge_op(MergeOp::default())
+    }
+
+    /// same as --union and --source-column, with input paths in the source column
+    pub fn merge_union(self, source_column: Option<&str>) -> Self {
+        let labels = self.streams.iter().map(|s| s.label.clone()).collect();
+        let op = MergeOp {
+            mode: MergeMode::Union,
+            source_column: source_column.map(|c| c.to_string()),
+            labels: source_column.map(|_| labels),
+            ..MergeOp::default()
+        };
+        self.merge_op(op)
+    }
+
+    /// same as --asof, with the first input being the primary one; prefixes are the same as
+    /// --asof-prefixes, one per secondary input, and default to in1_, in2_ and so on
+    pub fn merge_asof(self, key: Option<&str>, tolerance: Option<&str>, prefixes: &[&str]) -> Self {
+        let prefixes: Vec<String> = match prefixes.is_empty() {
+            true => (1..self.streams.len())
+                .map(|i| format!("in{}_", i))
+                .collect(),
+            false => prefixes.iter().map(|p| p.to_string()).collect(),
+        };
+        let op = MergeOp {
+            mode: MergeMode::Asof,
+            key: key.map(|k| k.to_string()),
+            tolerance: tolerance.map(|t| t.to_string()),
+            prefixes: Some(prefixes),
+            ..MergeOp::default()
+        };
+        self.merge_op(op)
+    }
+
+    /// rows go to branch as well as to the steps that follow; branch has to end with to_path
+    pub fn split<F: FnOnce(Pipeline) -> Pipeline>(mut self, branch: F) -> Self {
+        if self.streams.len() > 1 {
+            self.fail("split needs a single stream; merge inputs first");
+            return self;
+        }
+        let pipeline = Pipeline {
+            config: mem::take(&mut self.config),
+            streams: self.streams.clone(),
+            settings: mem::replace(&mut self.settings, Settings::new()),
+            error: self.error.take(),
+        };
+        let pipeline = branch(pipeline);
+        self.config = pipeline.config;
+        self.settings = pipeline.settings;
+        self.error = pipeline.error;
+        if let Some(stream) = pipeline.streams.first() {
+            self.fail(&format!(
+                "split branch taking rows from {} has no to_path",
+                stream.head
+            ));
+        }
+        self
+    }
+
+    /// writes rows to a file, whose type and compression follow from the path; this ends the
+    /// pipeline, or the split branch it is in
+    pub fn to_path(mut self, path: &str) -> Self {
+        if self.streams.len() > 1 {
+            self.fail("to_path needs a single stream; merge inputs first");
+            return self;
+        }
+        let pipeline = self.step(|node| node.output = Some(path.to_string()));
+        Pipeline {
+            streams: Vec::new(),
+            ..pipeline
+        }
+    }
+
+    /// driver with the pipeline set up; inputs are opened and outputs created right away
+    pub fn build(self) -> ChopperResult<Box<dyn ChopperDriver>> {
+        if let Some(error) = self.error {
+            return Err(Error::from(format!("Pipeline -- {}", error)));
+        }
+        if let Some(stream) = self.streams.first() {
+            return Err(Error::from(format!(
+                "Pipeline -- rows of {} go nowhere; end the pipeline with to_path",
+                stream.head
+            )));
+        }
+        let settings = self.settings;
+        let driver = self.config.build_driver(
+            settings.input_factory_builder,
+            &settings.output_factory,
+            &settings.timezone,
+            settings.timestamp_range,
+        )?;
+        Ok(Box::new(
+            driver.with_tie_break_policy(settings.tie_break_policy),
+        ))
+    }
+
+    /// config the pipeline comes down to, e.g. to see what it looks like
+    pub fn config(&self) -> &PipelineConfig {
+        &self.config
+    }
+
+    fn fail(&mut self, error: &str) {
+        if self.error.is_none() {
+            self.error = Some(error.to_string());
+        }
+    }
+
+    /// adds a node with the operation set by set_op after each stream
+    fn step<F: Fn(&mut NodeConfig)>(mut self, set_op: F) -> Self {
+        for stream in &mut self.streams {
+            let mut node = new_node(self.config.nodes.len(), Names::One(stream.head.clone()));
+            set_op(&mut node);
+            stream.head = node.name.clone();
+            self.config.nodes.push(node);
+        }
+        self
+    }
+
+    fn columns_op(self, keep: Option<&str>, drop: Option<&str>, rename: Option<&str>) -> Self {
+        self.step(|node| {
+            node.columns = Some(ColumnsOp {
+                keep: keep.map(|k| k.to_string()),
+                drop: drop.map(|d| d.to_string()),
+                rename: rename.map(|r| r.to_string()),
+            })
+        })
+    }
+
+    fn merge_op(mut self, op: MergeOp) -> Self {
+        let from = self.streams.drain(..).map(|s| s.head).collect();
+        let mut node = new_node(self.config.nodes.len(), Names::Many(from));
+        node.merge = Some(op);
+        self.streams.push(Stream {
+            head: node.name.clone(),
+            label: node.name.clone(),
+        });
+        self.config.nodes.push(node);
+        self
+    }
+}
+
+/// nodes are numbered in the order they are added; # keeps their names apart from input paths
+fn new_node(index: usize, from: Names) -> NodeConfig {
+    NodeConfig {
+        name: format!("#{}", index),
+        from,
+        when: None,
+        otherwise: false,
+        filter: None,
+        columns: None,
+        tag: None,
+        rolling: None,
+        resample: None,
+        group_by: None,
+        merge: None,
+        output: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pipeline::builder::Pipeline;
+    use crate::pipeline::config::{MergeMode, Names};
+
+    #[test]
+    fn test_pipeline_config() {
+        let pipeline = Pipeline::from_paths(&["a.csv", "b.csv"])
+            .filter("x > 0")
+            .merge_union(Some("src"))
+            .split(|b| b.columns("x").to_path("x.csv"))
+            .to_path("out.csv");
+        let config = pipeline.config();
+        let names: Vec<&str> = config.nodes.iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, vec!["#0", "#1", "#2", "#3", "#4", "#5"]);
+        assert_eq!(config.nodes[0].from, Names::One("a.csv".to_string()));
+        assert_eq!(config.nodes[1].from, Names::One("b.csv".to_string()));
+        let merge = config.nodes[2].merge.as_ref().unwrap();
+        assert_eq!(merge.mode, MergeMode::Union);
+        assert_eq!(
+            merge.labels,
+            Some(vec!["a.csv".to_string(), "b.csv".to_string()])
+        );
+        assert_eq!(config.nodes[3].from, Names::One("#2".to_string()));
+        assert_eq!(config.nodes[4].output, Some("x.csv".to_string()));
+        assert_eq!(config.nodes[5].from, Names::One("#2".to_string()));
+        assert_eq!(config.nodes[5].output, Some("out.csv".to_string()));
+    }
+
+    #[test]
+    fn test_pipeline_misuse() {
+        let error = |pipeline: Pipeline| pipeline.build().err().unwrap().to_string();
+        assert!(
+            error(Pipeline::from_paths(&["a.csv", "b.csv"]).to_path("out.csv"))
+                .contains("merge inputs first")
+        );
+        assert!(error(Pipeline::from_paths(&["a.csv"]).filter("x > 0"))
+            .contains("rows of #0 go nowhere"));
+        assert!(
+            error(Pipeline::from_paths(&["a.csv"]).split(|b| b.filter("x > 0")))
+                .contains("has no to_path")
+        );
+    }
+}
@@ -0,0 +1,324 @@
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::chopper::error::{ChopperResult, Error};
+
+/// processing graph described by a toml or yaml file, with named inputs and named nodes that
+/// take rows from inputs or other nodes; e.g. in toml:
+///
+/// ```toml
+/// [[input]]
+/// name = "trades"
+/// path = "trades.csv"
+///
+/// [[node]]
+/// name = "big"
+/// from = "trades"
+/// filter = "qty >= 100"
+///
+/// [[node]]
+/// name = "out"
+/// from = "big"
+/// output = "big_trades.csv"
+/// ```
+///
+/// every node has exactly one of filter, columns, tag, rolling, resample, group_by, merge and
+/// output; rows of an input or node that more than one node takes from go to all of them,
+/// unless those nodes have when (an expression) or otherwise set, in which case each row only
+/// goes to the nodes whose expression is true for it, or, if there are none, to the otherwise
+/// node; merge is the only node that can take from more than one input or node
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineConfig {
+    #[serde(default, rename = "input")]
+    pub inputs: Vec<InputConfig>,
+    #[serde(default, rename = "node")]
+    pub nodes: Vec<NodeConfig>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct InputConfig {
+    pub name: String,
+    pub path: String,
+    /// same as --format, e.g. csv; default is to go by the path
+    #[serde(default)]
+    pub format: Option<String>,
+}
+
+/// one name or a list of names
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum Names {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Names {
+    pub fn to_vec(&self) -> Vec<String> {
+        match self {
+            Names::One(name) => vec![name.clone()],
+            Names::Many(names) => names.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct NodeConfig {
+    pub name: String,
+    /// names of inputs or nodes this node takes rows from
+    pub from: Names,
+    #[serde(default)]
+    pub when: Option<String>,
+    #[serde(default)]
+    pub otherwise: bool,
+
+    /// expression, see Expr for the syntax
+    #[serde(default)]
+    pub filter: Option<String>,
+    #[serde(default)]
+    pub columns: Option<ColumnsOp>,
+    #[serde(default)]
+    pub tag: Option<TagOp>,
+    #[serde(default)]
+    pub rolling: Option<RollingOp>,
+    #[serde(default)]
+    pub resample: Option<ResampleOp>,
+    #[serde(default)]
+    pub group_by: Option<GroupByOp>,
+    #[serde(default)]
+    pub merge: Option<MergeOp>,
+    /// output file path; type and compression follow from the path
+    #[serde(default)]
+    pub output: Option<String>,
+}
+
+/// same as --columns, --drop and --rename
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ColumnsOp {
+    #[serde(default)]
+    pub keep: Option<String>,
+    #[serde(default)]
+    pub drop: Option<String>,
+    #[serde(default)]
+    pub rename: Option<String>,
+}
+
+/// appends a string column with the same value in every row
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct TagOp {
+    #[serde(default = "TagOp::default_column")]
+    pub column: String,
+    pub value: String,
+}
+
+impl TagOp {
+    fn default_column() -> String {
+        "source".to_string()
+    }
+}
+
+/// same as --rolling, --rolling-stats and --rolling-key
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RollingOp {
+    pub window: String,
+    pub stats: String,
+    #[serde(default)]
+    pub key: Option<String>,
+}
+
+/// same as --resample, --agg and --resample-fill
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ResampleOp {
+    pub interval: String,
+    pub agg: String,
+    #[serde(default)]
+    pub fill: Option<String>,
+}
+
+/// same as --group-by, --agg and --group-every
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct GroupByOp {
+    pub keys: String,
+    pub agg: String,
+    #[serde(default)]
+    pub every: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeMode {
+    /// inputs have to have the same columns
+    #[default]
+    Strict,
+    /// same as --union
+    Union,
+    /// same as --asof, with the first input of from being the primary one
+    Asof,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct MergeOp {
+    #[serde(default)]
+    pub mode: MergeMode,
+    /// union only; column with the name of the input or node each row came from
+    #[serde(default)]
+    pub source_column: Option<String>,
+    /// union only; what goes into source_column for rows of each input or node, in the order
+    /// of from; default is their names
+    #[serde(default)]
+    pub labels: Option<Vec<String>>,
+    /// asof only
+    #[serde(default)]
+    pub key: Option<String>,
+    /// asof only, e.g. 500ms
+    #[serde(default)]
+    pub tolerance: Option<String>,
+    /// asof only; one per secondary input, in the order of from; default is the input or node
+    /// name followed by an underscore
+    #[serde(default)]
+    pub prefixes: Option<Vec<String>>,
+}
+
+impl PipelineConfig {
+    /// file type goes by the extension, .toml, .yaml or .yml; relative input and output paths
+    /// are relative to the directory of the file, so that it runs the same from anywhere
+    pub fn from_file(path: &str) -> ChopperResult<Self> {
+        let text = fs::read_to_string(path)?;
+        let mut config = if path.ends_with(".toml") {
+            Self::from_toml_str(&text)?
+        } else if path.ends_with(".yaml") || path.ends_with(".yml") {
+            Self::from_yaml_str(&text)?
+        } else {
+            return Err(Error::from(format!(
+                "Pipeline -- {} is not a .toml, .yaml or .yml file",
+                path
+            )));
+        };
+        if let Some(dir) = Path::new(path).parent() {
+            config.resolve_paths(dir);
+        }
+        Ok(config)
+    }
+
+    pub fn from_toml_str(text: &str) -> ChopperResult<Self> {
+        toml::from_str(text).map_err(|e| Error::from(format!("Pipeline -- {}", e)))
+    }
+
+    pub fn from_yaml_str(text: &str) -> ChopperResult<Self> {
+        serde_yaml_ng::from_str(text).map_err(|e| Error::from(format!("Pipeline -- {}", e)))
+    }
+
+    /// urls are left alone
+    fn resolve_paths(&mut self, dir: &Path) {
+        let resolve = |path: &mut String| {
+            if Path::new(path.as_str()).is_relative() && !path.contains("://") {
+                *path = dir.join(path.as_str()).to_string_lossy().into_owned();
+            }
+        };
+        for input in &mut self.inputs {
+            resolve(&mut input.path);
+        }
+        for output in self
+            .nodes
+            .iter_mut()
+            .filter_map(|node| node.output.as_mut())
+        {
+            resolve(output);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use crate::pipeline::config::{MergeMode, Names, PipelineConfig};
+
+    #[test]
+    fn test_parse_toml_and_yaml() {
+        let toml = r#"
+            [[input]]
+            name = "a"
+            path = "a.csv"
+
+            [[input]]
+            name = "b"
+            path = "b.csv"
+            format = "csv"
+
+            [[node]]
+            name = "ab"
+            from = ["a", "b"]
+            merge = { mode = "union", source_column = "src" }
+
+            [[node]]
+            name = "out"
+            from = "ab"
+            output = "out.csv"
+        "#;
+        let yaml = r#"
+            input:
+              - name: a
+                path: a.csv
+              - name: b
+                path: b.csv
+                format: csv
+            node:
+              - name: ab
+                from: [a, b]
+                merge:
+                  mode: union
+                  source_column: src
+              - name: out
+                from: ab
+                output: out.csv
+        "#;
+        let config = PipelineConfig::from_toml_str(toml).unwrap();
+        assert_eq!(config, PipelineConfig::from_yaml_str(yaml).unwrap());
+        assert_eq!(config.inputs[1].format, Some("csv".to_string()));
+        assert_eq!(
+            config.nodes[0].from,
+            Names::Many(vec!["a".to_string(), "b".to_string()])
+        );
+        assert_eq!(
+            config.nodes[0].merge.as_ref().unwrap().mode,
+            MergeMode::Union
+        );
+        assert_eq!(config.nodes[1].from, Names::One("ab".to_string()));
+
+        let unknown_field = "[[node]]\nname = \"x\"\nfrom = \"a\"\nfliter = \"a > 1\"\n";
+        assert!(PipelineConfig::from_toml_str(unknown_field).is_err());
+    }
+
+    #[test]
+    fn test_paths_are_relative_to_file() {
+        let dir = std::env::temp_dir().join("chopper_test_pipeline_paths");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("p.yaml");
+        let yaml = "input:\n  - name: a\n    path: in/a.csv\n  - name: b\n    path: /data/b.csv\n\
+            node:\n  - name: out\n    from: a\n    output: out.csv\n";
+        fs::write(&path, yaml).unwrap();
+        let config = PipelineConfig::from_file(path.to_str().unwrap()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            Path::new(&config.inputs[0].path),
+            dir.join("in").join("a.csv")
+        );
+        assert_eq!(config.inputs[1].path, "/data/b.csv");
+        assert_eq!(
+            Path::new(config.nodes[0].output.as_ref().unwrap()),
+            dir.join("out.csv")
+        );
+    }
+}
@@ -0,0 +1,3 @@
+pub mod builder;
+pub mod config;
+pub mod plan;
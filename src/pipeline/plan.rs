@@ -0,0 +1,632 @@
+use std::collections::HashMap;
+
+use crate::aggregate::group_by::GroupBy;
+use crate::aggregate::resample::{FillPolicy, Resample};
+use crate::aggregate::rolling::Rolling;
+use crate::chopper::error::{ChopperResult, Error};
+use crate::chopper::header_graph::{HeaderChain, HeaderGraph, HeaderNode};
+use crate::chopper::sink::{DynHeaderSink, MergeHeaderSink, SplitHeaderSink};
+use crate::chopper::types::{ChainId, Header, TimestampRange};
+use crate::driver::asof_join::AsOfJoin;
+use crate::driver::driver::Driver;
+use crate::driver::merge_join::MergeJoin;
+use crate::driver::split::{RoutingSplit, Split};
+use crate::filter::column_projection::ColumnProjection;
+use crate::filter::column_tag::ColumnTag;
+use crate::filter::row_filter_expression::RowFilterExpression;
+use crate::input::input::{Input, InputFormat, InputType};
+use crate::input::input_factory::InputFactoryBuilder;
+use crate::pipeline::config::{MergeMode, MergeOp, NodeConfig, PipelineConfig};
+use crate::source::source::Source;
+use crate::util::interval::Interval;
+use crate::util::tz::ChopperTz;
+use crate::write::factory::OutputFactory;
+
+/// inputs and nodes are both entities, with inputs first, followed by nodes, in config order
+type EntityId = usize;
+
+/// header node to be created
+enum PlanNode {
+    /// index into config nodes
+    Node(usize),
+    /// target chain and the entity whose rows are merged
+    Merge(ChainId, EntityId),
+    /// target chains and the entity whose rows are split
+    Split(Vec<ChainId>, EntityId),
+}
+
+/// validated pipeline, with chains laid out the way HeaderGraph wants them, i.e. inputs first
+struct Plan {
+    /// per node, entities it takes rows from
+    producers: Vec<Vec<EntityId>>,
+    /// per chain, header nodes
+    chains: Vec<Vec<PlanNode>>,
+    /// per entity, its chain
+    entity_chains: Vec<ChainId>,
+    /// per merge node, entities in the order their headers reach the merge
+    merge_arrivals: HashMap<usize, Vec<EntityId>>,
+}
+
+fn pipeline_error<T>(msg: String) -> ChopperResult<T> {
+    Err(Error::from(format!("Pipeline -- {}", msg)))
+}
+
+impl NodeConfig {
+    fn op_count(&self) -> usize {
+        [
+            self.filter.is_some(),
+            self.columns.is_some(),
+            self.tag.is_some(),
+            self.rolling.is_some(),
+            self.resample.is_some(),
+            self.group_by.is_some(),
+            self.merge.is_some(),
+            self.output.is_some(),
+        ]
+        .iter()
+        .filter(|&&is_set| is_set)
+        .count()
+    }
+
+    fn is_routed(&self) -> bool {
+        self.when.is_some() || self.otherwise
+    }
+
+    fn header_sink(
+        &self,
+        timezone: &ChopperTz,
+        output_factory: &OutputFactory,
+    ) -> ChopperResult<Box<dyn DynHeaderSink>> {
+        if let Some(filter) = &self.filter {
+            RowFilterExpression::new(filter)
+        } else if let Some(op) = &self.columns {
+            ColumnProjection::new(op.keep.as_deref(), op.drop.as_deref(), op.rename.as_deref())
+        } else if let Some(op) = &self.tag {
+            Ok(ColumnTag::new(&op.column, &op.value))
+        } else if let Some(op) = &self.rolling {
+            Rolling::new(&op.window, &op.stats, op.key.as_deref())
+        } else if let Some(op) = &self.resample {
+            let fill_policy = match &op.fill {
+                None => FillPolicy::default(),
+                Some(fill) => fill.parse::<FillPolicy>()?,
+            };
+            Resample::new(&op.interval, &op.agg, fill_policy, timezone.clone())
+        } else if let Some(op) = &self.group_by {
+            GroupBy::new(&op.keys, &op.agg, op.every.as_deref(), timezone.clone())
+        } else if let Some(output) = &self.output {
+            output_factory.new_header_sink(Some(output))
+        } else {
+            unreachable!()
+        }
+    }
+}
+
+impl MergeOp {
+    fn validate(&self) -> ChopperResult<()> {
+        let is_union = self.mode == MergeMode::Union;
+        let is_asof = self.mode == MergeMode::Asof;
+        if self.source_column.is_some() && !is_union {
+            return pipeline_error("source_column is only for union merges".to_string());
+        }
+        if self.labels.is_some() && self.source_column.is_none() {
+            return pipeline_error("labels are only for union merges with source_column".into());
+        }
+        if (self.key.is_some() || self.tolerance.is_some() || self.prefixes.is_some()) && !is_asof {
+            return pipeline_error("key, tolerance and prefixes are only for asof merges".into());
+        }
+        Ok(())
+    }
+}
+
+impl PipelineConfig {
+    /// name of an input or node
+    fn entity_name(&self, entity: EntityId) -> &str {
+        match entity.checked_sub(self.inputs.len()) {
+            None => &self.inputs[entity].name,
+            Some(node) => &self.nodes[node].name,
+        }
+    }
+
+    fn plan(&self) -> ChopperResult<Plan> {
+        let input_count = self.inputs.len();
+        if input_count == 0 {
+            return pipeline_error("there has to be at least one input".to_string());
+        }
+
+        let mut entities: HashMap<&str, EntityId> = HashMap::new();
+        let names = self.inputs.iter().map(|i| &i.name);
+        for (entity, name) in names.chain(self.nodes.iter().map(|n| &n.name)).enumerate() {
+            if name.is_empty() {
+                return pipeline_error("input and node names cannot be empty".to_string());
+            }
+            if entities.insert(name, entity).is_some() {
+                return pipeline_error(format!(
+                    "there is more than one input or node named {}",
+                    name
+                ));
+            }
+        }
+
+        // edges
+        let mut producers: Vec<Vec<EntityId>> = Vec::with_capacity(self.nodes.len());
+        let mut consumers: Vec<Vec<usize>> = vec![Vec::new(); input_count + self.nodes.len()];
+        for (i, node) in self.nodes.iter().enumerate() {
+            let context = |msg: &str| pipeline_error(format!("node {} {}", node.name, msg));
+            if node.op_count() != 1 {
+                return context(
+                    "has to have exactly one of filter, columns, tag, rolling, resample, \
+                    group_by, merge and output",
+                );
+            }
+            if let Some(merge) = &node.merge {
+                if let Err(e) = merge.validate() {
+                    return context(&format!("is invalid: {}", e));
+                }
+                if node.is_routed() {
+                    return context("is a merge, so it cannot have when or otherwise");
+                }
+            }
+            if node.when.is_some() && node.otherwise {
+                return context("cannot have both when and otherwise");
+            }
+            let from = node.from.to_vec();
+            if from.is_empty() || (from.len() > 1 && node.merge.is_none()) {
+                return context(
+                    "has to take rows from exactly one input or node, unless it is a merge",
+                );
+            }
+            let mut node_producers: Vec<EntityId> = Vec::new();
+            for name in from {
+                let producer = match entities.get(name.as_str()) {
+                    None => {
+                        return context(&format!("takes rows from {}, which does not exist", name))
+                    }
+                    Some(&producer) => producer,
+                };
+                if node_producers.contains(&producer) {
+                    return context(&format!("takes rows from {} more than once", name));
+                }
+                node_producers.push(producer);
+                consumers[producer].push(i);
+            }
+            producers.push(node_producers);
+        }
+
+        for (entity, entity_consumers) in consumers.iter().enumerate() {
+            let name = self.entity_name(entity);
+            if entity >= input_count && self.nodes[entity - input_count].output.is_some() {
+                if !entity_consumers.is_empty() {
+                    return pipeline_error(format!("output {} cannot be taken rows from", name));
+                }
+                continue;
+            }
+            if entity_consumers.is_empty() {
+                return pipeline_error(format!(
+                    "rows of {} go nowhere; every path has to end with an output",
+                    name
+                ));
+            }
+            let routed_count = entity_consumers
+                .iter()
+                .filter(|&&c| self.nodes[c].is_routed())
+                .count();
+            if routed_count > 0 && routed_count != entity_consumers.len() {
+                return pipeline_error(format!(
+                    "either all or none of the nodes that take rows from {} have to have when \
+                    or otherwise",
+                    name
+                ));
+            }
+            let otherwise_count = entity_consumers
+                .iter()
+                .filter(|&&c| self.nodes[c].otherwise)
+                .count();
+            if otherwise_count > 1 {
+                return pipeline_error(format!(
+                    "more than one node takes rows from {} with otherwise",
+                    name
+                ));
+            }
+        }
+
+        // nodes in topological order
+        let mut remaining_producers: Vec<usize> = producers.iter().map(|p| p.len()).collect();
+        let mut ready: Vec<EntityId> = (0..input_count).collect();
+        let mut order: Vec<usize> = Vec::with_capacity(self.nodes.len());
+        while let Some(entity) = ready.pop() {
+            for &consumer in &consumers[entity] {
+                remaining_producers[consumer] -= 1;
+                if remaining_producers[consumer] == 0 {
+                    order.push(consumer);
+                    ready.push(input_count + consumer);
+                }
+            }
+        }
+        if order.len() < self.nodes.len() {
+            let cycle: Vec<&str> = (0..self.nodes.len())
+                .filter(|&i| remaining_producers[i] > 0)
+                .map(|i| self.nodes[i].name.as_str())
+                .collect();
+            return pipeline_error(format!("nodes {} make up a cycle", cycle.join(", ")));
+        }
+
+        // a node goes into the chain of the entity it takes rows from, unless it is a merge
+        // or that entity has other nodes taking rows from it as well
+        let mut entity_chains: Vec<ChainId> = (0..input_count).collect();
+        entity_chains.resize(input_count + self.nodes.len(), 0);
+        let mut chain_count = input_count;
+        let starts_chain = |node: usize| {
+            let producer = producers[node][0];
+            self.nodes[node].merge.is_some()
+                || consumers[producer].len() > 1
+                || self.nodes[node].is_routed()
+        };
+        for &node in &order {
+            entity_chains[input_count + node] = match starts_chain(node) {
+                true => {
+                    chain_count += 1;
+                    chain_count - 1
+                }
+                false => entity_chains[producers[node][0]],
+            };
+        }
+
+        let mut chains: Vec<Vec<PlanNode>> = (0..chain_count).map(|_| Vec::new()).collect();
+        for entity in (0..input_count).chain(order.iter().map(|&node| input_count + node)) {
+            let chain = entity_chains[entity];
+            if entity >= input_count {
+                chains[chain].push(PlanNode::Node(entity - input_count));
+            }
+            let mut split_chains: Vec<ChainId> = Vec::new();
+            for &consumer in &consumers[entity] {
+                if !starts_chain(consumer) {
+                    continue;
+                }
+                let consumer_chain = entity_chains[input_count + consumer];
+                match self.nodes[consumer].merge {
+                    Some(_) => chains[chain].push(PlanNode::Merge(consumer_chain, entity)),
+                    None => split_chains.push(consumer_chain),
+                }
+            }
+            if !split_chains.is_empty() {
+                chains[chain].push(PlanNode::Split(split_chains, entity));
+            }
+        }
+
+        let mut plan = Plan {
+            producers,
+            chains,
+            entity_chains,
+            merge_arrivals: HashMap::new(),
+        };
+        for chain in 0..input_count {
+            plan.simulate_headers(self, chain);
+        }
+        Ok(plan)
+    }
+
+    /// inputs in the order of their chain ids and the graph to give to the driver; output
+    /// files are created right away
+    pub fn compile(
+        &self,
+        timezone: &ChopperTz,
+        output_factory: &OutputFactory,
+    ) -> ChopperResult<(Vec<Input>, HeaderGraph)> {
+        let plan = self.plan()?;
+        Ok((
+            self.plan_inputs(),
+            plan.header_graph(self, timezone, output_factory)?,
+        ))
+    }
+
+    /// pipeline is validated before any input is opened or output created
+    pub fn build_driver(
+        &self,
+        input_factory_builder: InputFactoryBuilder,
+        output_factory: &OutputFactory,
+        timezone: &ChopperTz,
+        timestamp_range: TimestampRange,
+    ) -> ChopperResult<Driver> {
+        let plan = self.plan()?;
+        let mut input_factory = input_factory_builder.build()?;
+        let mut sources: Vec<Box<dyn Source>> = Vec::new();
+        let mut headers: Vec<Header> = Vec::new();
+        for input in self.plan_inputs() {
+            let source = input_factory.create_source_from_input(&input)?;
+            headers.push(source.header().clone());
+            sources.push(source);
+        }
+        let graph = plan.header_graph(self, timezone, output_factory)?;
+        Driver::new(sources, graph, timestamp_range, headers)
+    }
+
+    fn plan_inputs(&self) -> Vec<Input> {
+        self.inputs
+            .iter()
+            .map(|input| Input {
+                input: InputType::Path(input.path.clone()),
+                format: match &input.format {
+                    None => InputFormat::Auto,
+                    Some(format) => InputFormat::Extension(format.clone()),
+                },
+            })
+            .collect()
+    }
+}
+
+impl Plan {
+    /// records the order in which headers reach merges, the same way HeaderGraph processes them
+    fn simulate_headers(&mut self, config: &PipelineConfig, chain: ChainId) {
+        let mut next_chains: Vec<ChainId> = Vec::new();
+        for plan_node in &self.chains[chain] {
+            match plan_node {
+                PlanNode::Node(_) => {}
+                PlanNode::Merge(target, entity) => {
+                    let merge_node = self.chain_node(config, *target);
+                    let arrivals = self.merge_arrivals.entry(merge_node).or_default();
+                    arrivals.push(*entity);
+                    if arrivals.len() == self.producers[merge_node].len() {
+                        next_chains.push(*target);
+                    }
+                }
+                PlanNode::Split(targets, _) => next_chains.extend(targets),
+            }
+        }
+        for next_chain in next_chains {
+            self.simulate_headers(config, next_chain);
+        }
+    }
+
+    /// node that a chain starts with, for chains that are not input chains
+    fn chain_node(&self, config: &PipelineConfig, chain: ChainId) -> usize {
+        let input_count = config.inputs.len();
+        (0..config.nodes.len())
+            .find(|&node| self.entity_chains[input_count + node] == chain)
+            .unwrap()
+    }
+
+    fn header_graph(
+        &self,
+        config: &PipelineConfig,
+        timezone: &ChopperTz,
+        output_factory: &OutputFactory,
+    ) -> ChopperResult<HeaderGraph> {
+        let mut chains: Vec<HeaderChain> = Vec::with_capacity(self.chains.len());
+        for plan_nodes in &self.chains {
+            let mut header_nodes: Vec<HeaderNode> = Vec::with_capacity(plan_nodes.len());
+            for plan_node in plan_nodes {
+                let header_node = match plan_node {
+                    PlanNode::Node(node) => {
+                        let name = &config.nodes[*node].name;
+                        self.header_node(config, *node, timezone, output_factory)
+                            .or_else(|e| pipeline_error(format!("node {}: {}", name, e)))?
+                    }
+                    PlanNode::Merge(target, _) => HeaderNode::Merge(*target),
+                    PlanNode::Split(targets, entity) => {
+                        let split = self.split(config, targets, *entity)?;
+                        let header_count_tracker = split.get_new_header_count_tracker();
+                        HeaderNode::SplitHeaderSink(split, header_count_tracker)
+                    }
+                };
+                header_nodes.push(header_node);
+            }
+            chains.push(HeaderChain::new(header_nodes));
+        }
+        Ok(HeaderGraph::new(chains))
+    }
+
+    fn header_node(
+        &self,
+        config: &PipelineConfig,
+        node: usize,
+        timezone: &ChopperTz,
+        output_factory: &OutputFactory,
+    ) -> ChopperResult<HeaderNode> {
+        let node_config = &config.nodes[node];
+        match &node_config.merge {
+            None => Ok(HeaderNode::HeaderSink(
+                node_config.header_sink(timezone, output_factory)?,
+            )),
+            Some(op) => {
+                let merge = self.merge(config, node, op)?;
+                let header_count_tracker = merge.get_new_header_count_tracker();
+                Ok(HeaderNode::MergeHeaderSink(merge, header_count_tracker))
+            }
+        }
+    }
+
+    /// merge inputs are numbered in the order their headers arrive, which need not be the
+    /// order of from
+    fn merge(
+        &self,
+        config: &PipelineConfig,
+        node: usize,
+        op: &MergeOp,
+    ) -> ChopperResult<Box<dyn MergeHeaderSink>> {
+        let producers = &self.producers[node];
+        let arrivals = &self.merge_arrivals[&node];
+        let arrival_names: Vec<String> = arrivals
+            .iter()
+            .map(|&entity| config.entity_name(entity).to_string())
+            .collect();
+        match op.mode {
+            MergeMode::Strict => MergeJoin::new(producers.len()),
+            MergeMode::Union => {
+                let labels: Vec<String> = match &op.labels {
+                    None => arrival_names,
+                    Some(labels) if labels.len() != producers.len() => {
+                        return Err(Error::from(format!(
+                            "expected {} labels, one per input, got {}",
+                            producers.len(),
+                            labels.len()
+                        )))
+                    }
+                    Some(labels) => arrivals
+                        .iter()
+                        .map(|entity| {
+                            let i = producers.iter().position(|p| p == entity).unwrap();
+                            labels[i].clone()
+                        })
+                        .collect(),
+                };
+                let source_column = op
+                    .source_column
+                    .as_ref()
+                    .map(|column| (column.clone(), labels));
+                MergeJoin::new_union(producers.len(), source_column)
+            }
+            MergeMode::Asof => {
+                if arrivals[0] != producers[0] {
+                    return Err(Error::from(format!(
+                        "primary input {} does not reach the merge first; list the input it \
+                        comes from before the inputs that {} come from",
+                        config.entity_name(producers[0]),
+                        arrival_names[0]
+                    )));
+                }
+                let prefixes: Vec<String> = match &op.prefixes {
+                    None => arrival_names[1..]
+                        .iter()
+                        .map(|name| format!("{}_", name))
+                        .collect(),
+                    Some(prefixes) if prefixes.len() != producers.len() - 1 => {
+                        return Err(Error::from(format!(
+                            "expected {} prefixes, one per secondary input, got {}",
+                            producers.len() - 1,
+                            prefixes.len()
+                        )))
+                    }
+                    Some(prefixes) => arrivals[1..]
+                        .iter()
+                        .map(|entity| {
+                            let i = producers.iter().position(|p| p == entity).unwrap();
+                            prefixes[i - 1].clone()
+                        })
+                        .collect(),
+                };
+                let tolerance = match &op.tolerance {
+                    None => None,
+                    Some(tolerance) => Some(tolerance.parse::<Interval>()?.as_nanos()),
+                };
+                AsOfJoin::new(producers.len(), op.key.as_deref(), tolerance, prefixes)
+            }
+        }
+    }
+
+    fn split(
+        &self,
+        config: &PipelineConfig,
+        targets: &[ChainId],
+        entity: EntityId,
+    ) -> ChopperResult<Box<dyn SplitHeaderSink>> {
+        let target_nodes: Vec<&NodeConfig> = targets
+            .iter()
+            .map(|&chain| &config.nodes[self.chain_node(config, chain)])
+            .collect();
+        if !target_nodes[0].is_routed() {
+            return Ok(Split::new(targets.to_vec()));
+        }
+        let mut routes: Vec<(&str, ChainId)> = Vec::new();
+        let mut default_chain_id: Option<ChainId> = None;
+        for (node, &chain) in target_nodes.iter().zip(targets) {
+            match &node.when {
+                Some(when) => routes.push((when, chain)),
+                None => default_chain_id = Some(chain),
+            }
+        }
+        RoutingSplit::new_predicates(routes, default_chain_id).or_else(|e| {
+            pipeline_error(format!(
+                "routing rows of {}: {}",
+                config.entity_name(entity),
+                e
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pipeline::config::PipelineConfig;
+    use crate::pipeline::plan::PlanNode;
+
+    fn plan_error(toml: &str) -> String {
+        let config = PipelineConfig::from_toml_str(toml).unwrap();
+        config.plan().err().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_plan_chains() {
+        let config = PipelineConfig::from_toml_str(
+            r#"
+            input = [{ name = "trades", path = "t.csv" }, { name = "quotes", path = "q.csv" }]
+
+            [[node]]
+            name = "joined"
+            from = ["quotes", "trades"]
+            merge = { mode = "union" }
+
+            [[node]]
+            name = "big"
+            from = "joined"
+            when = "qty > 100"
+            output = "big.csv"
+
+            [[node]]
+            name = "rest"
+            from = "joined"
+            otherwise = true
+            columns = { keep = "sym,px" }
+
+            [[node]]
+            name = "out"
+            from = "rest"
+            output = "rest.csv"
+            "#,
+        )
+        .unwrap();
+        let plan = config.plan().unwrap();
+        // inputs, then merge chain, then one chain per routed node
+        assert_eq!(plan.chains.len(), 5);
+        assert!(matches!(plan.chains[0][..], [PlanNode::Merge(2, 0)]));
+        assert!(matches!(plan.chains[1][..], [PlanNode::Merge(2, 1)]));
+        assert!(matches!(
+            plan.chains[2][..],
+            [PlanNode::Node(0), PlanNode::Split(_, 2)]
+        ));
+        // rest and out share a chain
+        assert_eq!(plan.entity_chains[4], plan.entity_chains[5]);
+        // headers reach the merge in input order
+        assert_eq!(plan.merge_arrivals[&0], vec![0, 1]);
+    }
+
+    #[test]
+    fn test_plan_errors() {
+        let inputs = "input = [{ name = \"a\", path = \"a.csv\" }]\n";
+        let error = plan_error(&format!(
+            "{}[[node]]\nname = \"x\"\nfrom = \"y\"\nfilter = \"b > 1\"\n\
+            [[node]]\nname = \"y\"\nfrom = [\"a\", \"x\"]\nmerge = {{}}\n\
+            [[node]]\nname = \"z\"\nfrom = \"x\"\noutput = \"z.csv\"\n",
+            inputs
+        ));
+        assert!(error.contains("cycle"), "{}", error);
+        let error = plan_error(&format!(
+            "{}[[node]]\nname = \"x\"\nfrom = \"a\"\nfilter = \"b > 1\"\n",
+            inputs
+        ));
+        assert!(error.contains("rows of x go nowhere"), "{}", error);
+        let error = plan_error(&format!(
+            "{}[[node]]\nname = \"x\"\nfrom = \"b\"\noutput = \"x.csv\"\n",
+            inputs
+        ));
+        assert!(error.contains("does not exist"), "{}", error);
+        let error = plan_error(&format!(
+            "{}[[node]]\nname = \"a\"\nfrom = \"a\"\noutput = \"x.csv\"\n",
+            inputs
+        ));
+        assert!(
+            error.contains("more than one input or node named a"),
+            "{}",
+            error
+        );
+    }
+}
@@ -216,6 +216,6 @@ mod tests {
         ];
         let header_sink = AssertingSink::new(expected_header, expected_rows);
         let mut data_sink = to_dyn_header_sink(&input_rows, tfi, Box::new(header_sink)).unwrap();
-        data_sink.flush().unwrap();
+        data_sink.flush(&mut Vec::new()).unwrap();
     }
 }
@@ -0,0 +1,82 @@
+use std::collections::VecDeque;
+use std::io::{Cursor, Read, Seek};
+
+use arrow::ipc::reader::{FileReader, StreamReader};
+use arrow::record_batch::RecordBatchReader;
+
+use crate::chopper::error::ChopperResult;
+use crate::chopper::types::{Header, Row};
+use crate::source::columnar_input_config::ColumnarInputConfig;
+use crate::source::source::Source;
+use crate::util::arrow_util::RecordBatchRowReader;
+
+/// magic at the start of ipc file format, which is the same as feather v2
+pub const FILE_MAGIC: &[u8; 6] = b"ARROW1";
+/// every message in ipc stream format starts with this continuation marker
+pub const STREAM_MAGIC: &[u8; 4] = &[0xff, 0xff, 0xff, 0xff];
+
+pub struct ArrowSource {
+    reader: Box<dyn RecordBatchReader>,
+    row_reader: RecordBatchRowReader,
+    rows: VecDeque<Row>,
+}
+
+impl ArrowSource {
+    /// ipc stream format is read as it comes in
+    pub fn new_stream<R: 'static + Read>(
+        reader: R,
+        config: &ColumnarInputConfig,
+    ) -> ChopperResult<Self> {
+        Self::new(Box::new(StreamReader::try_new(reader, None)?), config)
+    }
+
+    /// ipc file format has its footer at the end, which is read first, and then batches
+    /// are read one at a time
+    pub fn new_file<R: 'static + Read + Seek>(
+        reader: R,
+        config: &ColumnarInputConfig,
+    ) -> ChopperResult<Self> {
+        Self::new(Box::new(FileReader::try_new(reader, None)?), config)
+    }
+
+    /// ipc file format from inputs that can't seek, e.g. stdin, http or decompressed files;
+    /// since the footer is at the end, the whole input is read into memory first
+    pub fn new_file_from_stream<R: Read>(
+        mut reader: R,
+        config: &ColumnarInputConfig,
+    ) -> ChopperResult<Self> {
+        let mut buf: Vec<u8> = Vec::new();
+        reader.read_to_end(&mut buf)?;
+        Self::new_file(Cursor::new(buf), config)
+    }
+
+    fn new(
+        reader: Box<dyn RecordBatchReader>,
+        config: &ColumnarInputConfig,
+    ) -> ChopperResult<Self> {
+        let row_reader = RecordBatchRowReader::new(&reader.schema(), config)?;
+        Ok(ArrowSource {
+            reader,
+            row_reader,
+            rows: VecDeque::new(),
+        })
+    }
+}
+
+impl Source for ArrowSource {
+    fn header(&self) -> &Header {
+        self.row_reader.header()
+    }
+
+    fn next_row(&mut self) -> ChopperResult<Option<Row>> {
+        loop {
+            if let Some(row) = self.rows.pop_front() {
+                return Ok(Some(row));
+            }
+            match self.reader.next() {
+                None => return Ok(None),
+                Some(batch) => self.rows.extend(self.row_reader.read_batch(&batch?)?),
+            }
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use std::io::{BufReader, Read, Seek, SeekFrom};
+
+use crate::chopper::error::ChopperResult;
+use crate::source::arrow_source::{ArrowSource, FILE_MAGIC, STREAM_MAGIC};
+use crate::source::columnar_input_config::ColumnarInputConfig;
+use crate::source::source::Source;
+use crate::source::source_factory::SourceFactory;
+use crate::transport::seekable::ReadSeek;
+use crate::util::reader::ChopperBufPreviewer;
+
+/// handles both arrow ipc file (feather v2) and stream formats; the two are told apart
+/// by magic, since the extensions are used interchangeably in the wild
+#[derive(Clone)]
+pub struct ArrowSourceFactory {
+    columnar_input_config: ColumnarInputConfig,
+}
+
+impl ArrowSourceFactory {
+    pub fn new(columnar_input_config: ColumnarInputConfig) -> Self {
+        ArrowSourceFactory {
+            columnar_input_config,
+        }
+    }
+}
+
+impl SourceFactory for ArrowSourceFactory {
+    fn can_create_from_format(&self, format: &String) -> bool {
+        format.ends_with(".arrow")
+            || format.ends_with(".arrows")
+            || format.ends_with(".feather")
+            || format.ends_with(".ipc")
+    }
+
+    fn can_create_from_previewer(&self, previewer: &ChopperBufPreviewer<Box<dyn Read>>) -> bool {
+        let buf = previewer.get_buf();
+        buf.starts_with(FILE_MAGIC) || buf.starts_with(STREAM_MAGIC)
+    }
+
+    fn create_source(
+        &mut self,
+        previewer: ChopperBufPreviewer<Box<dyn Read>>,
+    ) -> ChopperResult<Box<dyn Source>> {
+        let is_file_format = previewer.get_buf().starts_with(FILE_MAGIC);
+        let reader = previewer.get_reader();
+        let source = match is_file_format {
+            true => ArrowSource::new_file_from_stream(reader, &self.columnar_input_config)?,
+            false => ArrowSource::new_stream(reader, &self.columnar_input_config)?,
+        };
+        Ok(Box::new(source))
+    }
+
+    fn can_create_from_seekable(&self) -> bool {
+        true
+    }
+
+    fn create_source_from_seekable(
+        &mut self,
+        mut reader: BufReader<Box<dyn ReadSeek>>,
+    ) -> ChopperResult<Box<dyn Source>> {
+        let mut magic: Vec<u8> = Vec::new();
+        (&mut reader)
+            .take(FILE_MAGIC.len() as u64)
+            .read_to_end(&mut magic)?;
+        reader.seek(SeekFrom::Start(0))?;
+        let source = match magic.starts_with(FILE_MAGIC) {
+            true => ArrowSource::new_file(reader, &self.columnar_input_config)?,
+            false => ArrowSource::new_stream(reader, &self.columnar_input_config)?,
+        };
+        Ok(Box::new(source))
+    }
+
+    fn box_clone(&self) -> Box<dyn SourceFactory> {
+        Box::new((*self).clone())
+    }
+}
@@ -0,0 +1,35 @@
+use crate::source::csv_timestamp_config::TimestampColConfig;
+
+/// config shared by sources of typed columnar formats, i.e. parquet and arrow
+#[derive(Debug, Clone)]
+pub struct ColumnarInputConfig {
+    pub timestamp_col: TimestampColConfig,
+    pub hide_timestamp_column: bool,
+}
+
+impl ColumnarInputConfig {
+    pub fn new() -> Self {
+        ColumnarInputConfig {
+            timestamp_col: TimestampColConfig::Auto,
+            hide_timestamp_column: false,
+        }
+    }
+
+    /// only Auto, Index and Name are supported, since timestamps in columnar formats
+    /// are never split into separate date and time columns
+    pub fn with_timestamp_col(mut self, timestamp_col: TimestampColConfig) -> Self {
+        self.timestamp_col = timestamp_col;
+        self
+    }
+
+    pub fn hide_timestamp_column(mut self, hide_timestamp_column: bool) -> Self {
+        self.hide_timestamp_column = hide_timestamp_column;
+        self
+    }
+}
+
+impl Default for ColumnarInputConfig {
+    fn default() -> Self {
+        ColumnarInputConfig::new()
+    }
+}
@@ -1,14 +1,34 @@
+use clap::arg_enum;
+
 use crate::chopper::error::ChopperResult;
+use crate::chopper::types::FieldType;
 use crate::cli::util::YesNoAuto;
 use crate::source::csv_timestamp_config::TimestampConfig;
 use crate::util::csv_util;
 
+arg_enum! {
+    /// what to do with a cell that doesn't parse into the type of its column
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum CellParseErrorPolicy {
+        Null,
+        Error,
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct CSVInputConfig {
     pub delimiter: Option<u8>,
     pub has_header: YesNoAuto,
     pub hide_timestamp_column: bool,
     pub timestamp_config: TimestampConfig,
+    pub infer_types: bool,
+    /// number of leading rows to sample for type inference;
+    /// if None, rows available in the preview buffer are used
+    pub infer_types_row_count: Option<usize>,
+    /// declared column types, which take precedence over inferred ones
+    pub schema: Vec<(String, FieldType)>,
+    /// if None, declared columns fail on parse errors and inferred columns get null values
+    pub cell_parse_error_policy: Option<CellParseErrorPolicy>,
 }
 
 impl CSVInputConfig {
@@ -18,6 +38,10 @@ impl CSVInputConfig {
             has_header: YesNoAuto::Auto,
             hide_timestamp_column: false,
             timestamp_config,
+            infer_types: false,
+            infer_types_row_count: None,
+            schema: Vec::new(),
+            cell_parse_error_policy: None,
         }
     }
 
@@ -38,4 +62,24 @@ impl CSVInputConfig {
         self.hide_timestamp_column = hide_timestamp_column;
         self
     }
+
+    pub fn with_type_inference(mut self, infer_types: bool, row_count: Option<usize>) -> Self {
+        self.infer_types = infer_types;
+        self.infer_types_row_count = row_count;
+        self
+    }
+
+    /// see csv_util::parse_into_schema for the format
+    pub fn with_schema(mut self, schema: Option<&str>) -> ChopperResult<Self> {
+        self.schema = match schema {
+            None => Vec::new(),
+            Some(x) => csv_util::parse_into_schema(x)?,
+        };
+        Ok(self)
+    }
+
+    pub fn with_cell_parse_error_policy(mut self, policy: Option<CellParseErrorPolicy>) -> Self {
+        self.cell_parse_error_policy = policy;
+        self
+    }
 }
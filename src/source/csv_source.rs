@@ -1,12 +1,13 @@
+use std::collections::VecDeque;
 use std::io::Read;
 use std::mem;
 
 use csv::{self, Trim};
 
-use crate::chopper::error::ChopperResult;
+use crate::chopper::error::{ChopperResult, Error};
 use crate::chopper::types::{FieldType, FieldValue, Header, Row};
 use crate::cli::util::YesNoAuto;
-use crate::source::csv_input_config::CSVInputConfig;
+use crate::source::csv_input_config::{CSVInputConfig, CellParseErrorPolicy};
 use crate::source::csv_timestamp_util::{self, TimestampCol, TimestampFmt};
 use crate::source::source::Source;
 use crate::util::csv_util;
@@ -18,6 +19,12 @@ const DELIMITERS: &[u8] = b",\t ";
 pub struct CSVSource<R: Read> {
     reader: csv::Reader<ChopperBufReader<R>>,
     header: Header,
+    /// names and types of all the columns in the record, including hidden timestamp columns
+    record_field_names: Vec<String>,
+    record_field_types: Vec<FieldType>,
+    record_cell_parse_error_policies: Vec<CellParseErrorPolicy>,
+    /// records read ahead for type inference that still need to be output
+    buffered_records: VecDeque<csv::StringRecord>,
     hide_timestamp_column: bool,
     timestamp_col: TimestampCol,
     timestamp_fmt: TimestampFmt,
@@ -52,6 +59,11 @@ impl<R: Read> CSVSource<R> {
             YesNoAuto::Auto => csv_util::guess_has_header(line1, line2, delimiter),
         };
 
+        let preview_row_count = match previewer.get_lines() {
+            None => 0,
+            Some(lines) => lines.len().saturating_sub(if has_header { 1 } else { 0 }),
+        };
+
         let reader = previewer.get_reader();
 
         let mut reader = csv::ReaderBuilder::new()
@@ -78,8 +90,43 @@ impl<R: Read> CSVSource<R> {
             Header::generate_default_field_names(field_count)
         };
 
-        let field_types: Vec<FieldType> = vec![FieldType::String; field_count];
-        let header: Header = Header::new(field_names, field_types);
+        // read ahead the sample rows if we need to guess the column types
+        let mut buffered_records: VecDeque<csv::StringRecord> = VecDeque::new();
+        buffered_records.push_back(first_row.clone());
+        let mut field_types: Vec<FieldType> = if csv_input_config.infer_types {
+            let sample_row_count = csv_input_config
+                .infer_types_row_count
+                .unwrap_or(preview_row_count);
+            while buffered_records.len() < sample_row_count {
+                match reader.records().next() {
+                    Some(r) => buffered_records.push_back(r?),
+                    None => break,
+                }
+            }
+            Self::infer_field_types(&buffered_records, field_count)
+        } else {
+            vec![FieldType::String; field_count]
+        };
+        let inferred_policy = csv_input_config
+            .cell_parse_error_policy
+            .unwrap_or(CellParseErrorPolicy::Null);
+        let mut cell_parse_error_policies = vec![inferred_policy; field_count];
+
+        // declared column types take precedence over inferred ones
+        let declared_policy = csv_input_config
+            .cell_parse_error_policy
+            .unwrap_or(CellParseErrorPolicy::Error);
+        for (name, field_type) in &csv_input_config.schema {
+            let i = match field_names.iter().position(|n| n == name) {
+                Some(i) if i < field_count => i,
+                _ => return Err(Error::ColumnMissing(name.to_string())),
+            };
+            field_types[i] = *field_type;
+            cell_parse_error_policies[i] = declared_policy;
+        }
+
+        let header: Header = Header::new(field_names, field_types.clone());
+        let record_field_names = header.field_names().clone();
 
         let timestamp_config = &csv_input_config.timestamp_config;
         let timezone = timestamp_config.timezone();
@@ -111,6 +158,10 @@ impl<R: Read> CSVSource<R> {
         let mut csv_reader = CSVSource {
             reader,
             header,
+            record_field_names,
+            record_field_types: field_types,
+            record_cell_parse_error_policies: cell_parse_error_policies,
+            buffered_records,
             hide_timestamp_column: csv_input_config.hide_timestamp_column,
             timestamp_col,
             timestamp_fmt,
@@ -120,11 +171,79 @@ impl<R: Read> CSVSource<R> {
         };
 
         // update next_row with first row
+        let first_row = csv_reader.buffered_records.pop_front().unwrap();
         csv_reader.update_row(first_row)?;
 
         Ok(csv_reader)
     }
 
+    /// empty cells are ignored, since they are treated as missing values
+    fn infer_field_types(
+        records: &VecDeque<csv::StringRecord>,
+        field_count: usize,
+    ) -> Vec<FieldType> {
+        (0..field_count)
+            .map(|i| {
+                let guesses: Vec<FieldType> = records
+                    .iter()
+                    .filter_map(|r| r.get(i))
+                    .filter(|v| !v.is_empty())
+                    .map(csv_util::guess_type)
+                    .collect();
+                csv_util::guess_common_type(&guesses)
+            })
+            .collect()
+    }
+
+    fn parse_field_value(
+        &self,
+        record: &csv::StringRecord,
+        index: usize,
+    ) -> ChopperResult<FieldValue> {
+        let value = record.get(index).unwrap();
+        let field_type = match self.record_field_types.get(index) {
+            // flexible csv records can have extra values at the end
+            None => return Ok(FieldValue::String(value.to_string())),
+            Some(t) => *t,
+        };
+
+        if field_type == FieldType::String {
+            return Ok(FieldValue::String(value.to_string()));
+        }
+        if value.is_empty() {
+            return Ok(FieldValue::None);
+        }
+
+        let field_value = match field_type {
+            FieldType::Boolean => value.parse::<bool>().ok().map(FieldValue::Boolean),
+            FieldType::Byte => value.parse::<u8>().ok().map(FieldValue::Byte),
+            FieldType::ByteBuf => Some(FieldValue::ByteBuf(value.as_bytes().to_vec())),
+            // same numeric representation as used by csv output
+            FieldType::Char => value.parse::<u16>().ok().map(FieldValue::Char),
+            FieldType::Double => value.parse::<f64>().ok().map(FieldValue::Double),
+            FieldType::Float => value.parse::<f32>().ok().map(FieldValue::Float),
+            FieldType::Int => value.parse::<i32>().ok().map(FieldValue::Int),
+            FieldType::Long => value.parse::<i64>().ok().map(FieldValue::Long),
+            FieldType::Short => value.parse::<i16>().ok().map(FieldValue::Short),
+            FieldType::String | FieldType::MultiDimDoubleArray => None,
+        };
+        match field_value {
+            Some(v) => Ok(v),
+            None => match self.record_cell_parse_error_policies[index] {
+                CellParseErrorPolicy::Null => Ok(FieldValue::None),
+                CellParseErrorPolicy::Error => Err(Error::CellParsing(
+                    value.to_string(),
+                    field_type,
+                    self.record_field_names
+                        .get(index)
+                        .cloned()
+                        .unwrap_or_else(|| format!("col_{}", index)),
+                    record.position().map_or(0, |p| p.line()),
+                )),
+            },
+        }
+    }
+
     fn update_row(&mut self, next_record: csv::StringRecord) -> ChopperResult<()> {
         if self.hide_timestamp_column {
             match self.timestamp_col {
@@ -136,7 +255,7 @@ impl<R: Read> CSVSource<R> {
 
                         self.next_row
                             .field_values
-                            .push(FieldValue::String(next_record.get(i).unwrap().to_string()));
+                            .push(self.parse_field_value(&next_record, i)?);
                     }
                 }
                 TimestampCol::DateTimeIndex(d, t) => {
@@ -147,7 +266,7 @@ impl<R: Read> CSVSource<R> {
 
                         self.next_row
                             .field_values
-                            .push(FieldValue::String(next_record.get(i).unwrap().to_string()));
+                            .push(self.parse_field_value(&next_record, i)?);
                     }
                 }
             };
@@ -155,7 +274,7 @@ impl<R: Read> CSVSource<R> {
             for i in 0..next_record.len() {
                 self.next_row
                     .field_values
-                    .push(FieldValue::String(next_record.get(i).unwrap().to_string()));
+                    .push(self.parse_field_value(&next_record, i)?);
             }
         };
 
@@ -174,8 +293,12 @@ impl<R: Read> CSVSource<R> {
         }
 
         let current_row = mem::replace(&mut self.next_row, Row::empty());
-        match self.reader.records().next() {
-            Some(r) => self.update_row(r?)?,
+        let next_record = match self.buffered_records.pop_front() {
+            Some(r) => Some(r),
+            None => self.reader.records().next().transpose()?,
+        };
+        match next_record {
+            Some(r) => self.update_row(r)?,
             None => self.has_next_row = false,
         }
         Ok(Some(current_row))
@@ -191,3 +314,156 @@ impl<R: Read> Source for CSVSource<R> {
         self.next_row()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use crate::chopper::types::{FieldType, FieldValue};
+    use crate::cli::util::YesNoAuto;
+    use crate::source::csv_input_config::{CSVInputConfig, CellParseErrorPolicy};
+    use crate::source::csv_source::CSVSource;
+    use crate::source::csv_timestamp_config::{
+        TimestampColConfig, TimestampConfig, TimestampFmtConfig,
+    };
+    use crate::source::source::Source;
+    use crate::util::reader::ChopperBufPreviewer;
+    use crate::util::timestamp_units::TimestampUnits;
+    use crate::util::tz::ChopperTz;
+
+    fn new_source(csv: &'static str, csv_input_config: &CSVInputConfig) -> Box<dyn Source> {
+        let reader: Box<dyn Read> = Box::new(csv.as_bytes());
+        let previewer = ChopperBufPreviewer::new(reader).unwrap();
+        Box::new(CSVSource::new(previewer, csv_input_config).unwrap())
+    }
+
+    fn new_config() -> CSVInputConfig {
+        let timestamp_config = TimestampConfig::new(
+            TimestampColConfig::Index(0),
+            TimestampFmtConfig::Epoch(TimestampUnits::Nanos),
+            ChopperTz::new_always_fails(),
+        );
+        CSVInputConfig::new(timestamp_config)
+            .with_delimiter(Some(","))
+            .unwrap()
+            .with_header(YesNoAuto::Yes)
+    }
+
+    #[test]
+    fn test_infer_types() {
+        let csv = "timestamp,px,qty,flag,sym\n1,1.5,7,true,A\n2,2,,false,B\n3,3.25,9,true,C\n";
+
+        let mut source = new_source(csv, &new_config());
+        assert_eq!(source.header().field_types(), &vec![FieldType::String; 5]);
+
+        let mut source_typed = new_source(csv, &new_config().with_type_inference(true, None));
+        assert_eq!(
+            source_typed.header().field_types(),
+            &vec![
+                FieldType::Long,
+                FieldType::Double,
+                FieldType::Long,
+                FieldType::Boolean,
+                FieldType::String
+            ]
+        );
+
+        let row = source_typed.next_row().unwrap().unwrap();
+        assert_eq!(row.timestamp, 1);
+        assert_eq!(
+            row.field_values,
+            vec![
+                FieldValue::Long(1),
+                FieldValue::Double(1.5),
+                FieldValue::Long(7),
+                FieldValue::Boolean(true),
+                FieldValue::String("A".to_string())
+            ]
+        );
+        let row = source_typed.next_row().unwrap().unwrap();
+        assert_eq!(row.field_values[1], FieldValue::Double(2.0));
+        assert_eq!(row.field_values[2], FieldValue::None);
+        assert!(source_typed.next_row().unwrap().is_some());
+        assert!(source_typed.next_row().unwrap().is_none());
+
+        let row = source.next_row().unwrap().unwrap();
+        assert_eq!(row.field_values[1], FieldValue::String("1.5".to_string()));
+    }
+
+    #[test]
+    fn test_infer_types_with_row_count() {
+        let csv = "timestamp,qty\n1,7\n2,x\n";
+
+        let mut source = new_source(csv, &new_config().with_type_inference(true, Some(1)));
+        assert_eq!(
+            source.header().field_types(),
+            &vec![FieldType::Long, FieldType::Long]
+        );
+        assert_eq!(
+            source.next_row().unwrap().unwrap().field_values[1],
+            FieldValue::Long(7)
+        );
+        assert_eq!(
+            source.next_row().unwrap().unwrap().field_values[1],
+            FieldValue::None
+        );
+
+        let config = new_config()
+            .with_type_inference(true, Some(1))
+            .with_cell_parse_error_policy(Some(CellParseErrorPolicy::Error));
+        let reader: Box<dyn Read> = Box::new(csv.as_bytes());
+        let previewer = ChopperBufPreviewer::new(reader).unwrap();
+        let mut source = CSVSource::new(previewer, &config).unwrap();
+        assert!(source.next_row().is_err());
+    }
+
+    #[test]
+    fn test_schema() {
+        let csv = "timestamp,px,qty,sym,flag\n1,1.5,7,A,true\n2,2.5,x,B,false\n";
+
+        let config = new_config()
+            .with_type_inference(true, None)
+            .with_schema(Some("px:f32,qty:i32,sym:[u8],timestamp:i64"))
+            .unwrap();
+        let mut source = new_source(csv, &config);
+        assert_eq!(
+            source.header().field_types(),
+            &vec![
+                FieldType::Long,
+                FieldType::Float,
+                FieldType::Int,
+                FieldType::ByteBuf,
+                FieldType::Boolean
+            ]
+        );
+        assert_eq!(
+            source.next_row().unwrap_err().to_string(),
+            "Failed to parse 'x' as Int in column 'qty' on line 3."
+        );
+
+        let config = new_config()
+            .with_schema(Some("qty:i32"))
+            .unwrap()
+            .with_cell_parse_error_policy(Some(CellParseErrorPolicy::Null));
+        let mut source = new_source(csv, &config);
+        assert_eq!(
+            source.next_row().unwrap().unwrap().field_values,
+            vec![
+                FieldValue::String("1".to_string()),
+                FieldValue::String("1.5".to_string()),
+                FieldValue::Int(7),
+                FieldValue::String("A".to_string()),
+                FieldValue::String("true".to_string())
+            ]
+        );
+        assert_eq!(
+            source.next_row().unwrap().unwrap().field_values[2],
+            FieldValue::None
+        );
+
+        let config = new_config().with_schema(Some("size:i32")).unwrap();
+        let reader: Box<dyn Read> = Box::new(csv.as_bytes());
+        let previewer = ChopperBufPreviewer::new(reader).unwrap();
+        assert!(CSVSource::new(previewer, &config).is_err());
+    }
+}
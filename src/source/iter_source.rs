@@ -0,0 +1,56 @@
+use crate::chopper::error::ChopperResult;
+use crate::chopper::types::{Header, Row};
+use crate::source::source::Source;
+
+/// in-memory source over any iterator of rows; rows are expected to be in timestamp order
+/// and to match the header
+pub struct IterSource<I: Iterator<Item = Row>> {
+    header: Header,
+    rows: I,
+}
+
+pub type VecSource = IterSource<std::vec::IntoIter<Row>>;
+
+impl<I: Iterator<Item = Row>> IterSource<I> {
+    pub fn new<T: IntoIterator<Item = Row, IntoIter = I>>(header: Header, rows: T) -> Self {
+        IterSource {
+            header,
+            rows: rows.into_iter(),
+        }
+    }
+}
+
+impl<I: Iterator<Item = Row>> Source for IterSource<I> {
+    fn header(&self) -> &Header {
+        &self.header
+    }
+
+    fn next_row(&mut self) -> ChopperResult<Option<Row>> {
+        Ok(self.rows.next())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chopper::types::{FieldType, FieldValue, Header, Row};
+    use crate::source::iter_source::{IterSource, VecSource};
+    use crate::source::source::Source;
+
+    #[test]
+    fn test_iter_source() {
+        let header = Header::new(vec!["a".to_string()], vec![FieldType::Long]);
+        let rows = (0..3).map(|i| Row {
+            timestamp: i,
+            field_values: vec![FieldValue::Long(i as i64)],
+        });
+        let source: Box<dyn Source> = Box::new(IterSource::new(header.clone(), rows));
+        let timestamps = source
+            .into_iter()
+            .map(|row| row.unwrap().timestamp)
+            .collect::<Vec<u64>>();
+        assert_eq!(timestamps, vec![0, 1, 2]);
+
+        let mut source = VecSource::new(header, Vec::new());
+        assert!(source.next_row().unwrap().is_none());
+    }
+}
@@ -0,0 +1,40 @@
+use crate::source::csv_input_config::CellParseErrorPolicy;
+use crate::source::csv_timestamp_config::TimestampConfig;
+
+#[derive(Debug, Clone)]
+pub struct JsonInputConfig {
+    pub hide_timestamp_column: bool,
+    pub timestamp_config: TimestampConfig,
+    /// number of leading objects to collect keys and guess value types from;
+    /// if None, objects available in the preview buffer are used
+    pub header_row_count: Option<usize>,
+    /// for values that don't fit the guessed type of their key; if None, they become null,
+    /// same as with guessed csv column types
+    pub cell_parse_error_policy: Option<CellParseErrorPolicy>,
+}
+
+impl JsonInputConfig {
+    pub fn new(timestamp_config: TimestampConfig) -> Self {
+        JsonInputConfig {
+            hide_timestamp_column: false,
+            timestamp_config,
+            header_row_count: None,
+            cell_parse_error_policy: None,
+        }
+    }
+
+    pub fn hide_timestamp_column(mut self, hide_timestamp_column: bool) -> Self {
+        self.hide_timestamp_column = hide_timestamp_column;
+        self
+    }
+
+    pub fn with_header_row_count(mut self, header_row_count: Option<usize>) -> Self {
+        self.header_row_count = header_row_count;
+        self
+    }
+
+    pub fn with_cell_parse_error_policy(mut self, policy: Option<CellParseErrorPolicy>) -> Self {
+        self.cell_parse_error_policy = policy;
+        self
+    }
+}
@@ -0,0 +1,453 @@
+use std::collections::{HashSet, VecDeque};
+use std::io::{BufRead, Read};
+
+use ndarray::{ArrayD, IxDyn};
+use serde_json::{Map, Value};
+
+use crate::chopper::error::{ChopperResult, Error};
+use crate::chopper::types::{FieldType, FieldValue, Header, Row};
+use crate::source::csv_input_config::CellParseErrorPolicy;
+use crate::source::csv_timestamp_util::{self, TimestampCol, TimestampFmt};
+use crate::source::json_input_config::JsonInputConfig;
+use crate::source::source::Source;
+use crate::util::reader::{ChopperBufPreviewer, ChopperBufReader};
+use crate::util::tz::ChopperTz;
+
+type JsonObject = Map<String, Value>;
+
+/// reads one json object per line; header is made out of the keys of the leading objects,
+/// and keys that show up for the first time later on are ignored
+pub struct JsonSource<R: Read> {
+    reader: ChopperBufReader<R>,
+    header: Header,
+    /// names and types of all the keys, including hidden timestamp ones
+    record_field_names: Vec<String>,
+    record_field_types: Vec<FieldType>,
+    /// objects read ahead for building the header that still need to be output
+    buffered_objects: VecDeque<(u64, JsonObject)>,
+    hide_timestamp_column: bool,
+    /// None if there are no objects in the input
+    timestamp: Option<(TimestampCol, TimestampFmt)>,
+    timezone: ChopperTz,
+    cell_parse_error_policy: CellParseErrorPolicy,
+    line: String,
+    line_number: u64,
+}
+
+impl<R: Read> JsonSource<R> {
+    pub fn new(
+        previewer: ChopperBufPreviewer<R>,
+        json_input_config: &JsonInputConfig,
+    ) -> ChopperResult<Self> {
+        let preview_row_count = match previewer.get_lines() {
+            None => 0,
+            Some(lines) => lines.iter().filter(|l| !l.trim().is_empty()).count(),
+        };
+        let sample_row_count = json_input_config
+            .header_row_count
+            .unwrap_or(preview_row_count)
+            .max(1);
+
+        let timestamp_config = &json_input_config.timestamp_config;
+        let mut source = JsonSource {
+            reader: previewer.get_reader(),
+            header: Header::new(Vec::new(), Vec::new()),
+            record_field_names: Vec::new(),
+            record_field_types: Vec::new(),
+            buffered_objects: VecDeque::new(),
+            hide_timestamp_column: json_input_config.hide_timestamp_column,
+            timestamp: None,
+            timezone: timestamp_config.timezone().clone(),
+            cell_parse_error_policy: json_input_config
+                .cell_parse_error_policy
+                .unwrap_or(CellParseErrorPolicy::Null),
+            line: String::new(),
+            line_number: 0,
+        };
+
+        while source.buffered_objects.len() < sample_row_count {
+            match source.read_object()? {
+                Some(object) => source.buffered_objects.push_back(object),
+                None => break,
+            }
+        }
+
+        // keys are kept in order of their first appearance
+        let mut seen: HashSet<&String> = HashSet::new();
+        let mut field_names: Vec<String> = Vec::new();
+        for (_, object) in &source.buffered_objects {
+            for key in object.keys() {
+                if seen.insert(key) {
+                    field_names.push(key.clone());
+                }
+            }
+        }
+        let field_types: Vec<FieldType> = field_names
+            .iter()
+            .map(|name| {
+                infer_field_type(
+                    source
+                        .buffered_objects
+                        .iter()
+                        .filter_map(|(_, object)| object.get(name)),
+                )
+            })
+            .collect();
+        let mut header = Header::new(field_names, field_types);
+
+        if let Some((line_number, first_object)) = source.buffered_objects.front() {
+            let first_record = to_record(header.field_names(), first_object, None, *line_number)?;
+            let (timestamp_col, timestamp_fmt) = csv_timestamp_util::get_timestamp_col_and_fmt(
+                &header,
+                &first_record,
+                timestamp_config.timestamp_col(),
+                timestamp_config.timestamp_fmt(),
+                &source.timezone,
+            )?;
+            source.timestamp = Some((timestamp_col, timestamp_fmt));
+        }
+
+        source.record_field_names = header.field_names().clone();
+        source.record_field_types = header.field_types().clone();
+        if let Some((timestamp_col, _)) = &source.timestamp {
+            if source.hide_timestamp_column {
+                for i in (0..source.record_field_names.len()).rev() {
+                    if is_timestamp_col(timestamp_col, i) {
+                        header.field_names_mut().remove(i);
+                        header.field_types_mut().remove(i);
+                    }
+                }
+            }
+        }
+        source.header = header;
+
+        Ok(source)
+    }
+
+    fn read_object(&mut self) -> ChopperResult<Option<(u64, JsonObject)>> {
+        loop {
+            self.line.clear();
+            if self.reader.read_line(&mut self.line)? == 0 {
+                return Ok(None);
+            }
+            self.line_number += 1;
+
+            let line = self.line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            return match serde_json::from_str::<Value>(line) {
+                Ok(Value::Object(object)) => Ok(Some((self.line_number, object))),
+                Ok(_) => Err(Error::from(format!(
+                    "json -- line {} is not an object",
+                    self.line_number
+                ))),
+                Err(e) => Err(Error::from(format!(
+                    "json -- failed to parse line {}: {}",
+                    self.line_number, e
+                ))),
+            };
+        }
+    }
+
+    fn object_to_row(&self, line_number: u64, object: JsonObject) -> ChopperResult<Row> {
+        let (timestamp_col, timestamp_fmt) = self.timestamp.as_ref().unwrap();
+        let record = to_record(
+            &self.record_field_names,
+            &object,
+            Some(timestamp_col),
+            line_number,
+        )?;
+        let timestamp = csv_timestamp_util::get_timestamp(
+            &record,
+            timestamp_col,
+            timestamp_fmt,
+            &self.timezone,
+        )?;
+
+        let mut field_values: Vec<FieldValue> = Vec::with_capacity(self.header.field_names().len());
+        for (i, (name, field_type)) in self
+            .record_field_names
+            .iter()
+            .zip(&self.record_field_types)
+            .enumerate()
+        {
+            if self.hide_timestamp_column && is_timestamp_col(timestamp_col, i) {
+                continue;
+            }
+            let value = match object.get(name) {
+                None | Some(Value::Null) => FieldValue::None,
+                Some(value) => match to_field_value(value, *field_type) {
+                    Some(v) => v,
+                    None => match self.cell_parse_error_policy {
+                        CellParseErrorPolicy::Null => FieldValue::None,
+                        CellParseErrorPolicy::Error => {
+                            return Err(Error::CellParsing(
+                                value.to_string(),
+                                *field_type,
+                                name.clone(),
+                                line_number,
+                            ))
+                        }
+                    },
+                },
+            };
+            field_values.push(value);
+        }
+
+        Ok(Row {
+            timestamp,
+            field_values,
+        })
+    }
+}
+
+impl<R: Read> Source for JsonSource<R> {
+    fn header(&self) -> &Header {
+        &self.header
+    }
+
+    fn next_row(&mut self) -> ChopperResult<Option<Row>> {
+        let next_object = match self.buffered_objects.pop_front() {
+            Some(o) => Some(o),
+            None => self.read_object()?,
+        };
+        match next_object {
+            None => Ok(None),
+            Some((line_number, object)) => Ok(Some(self.object_to_row(line_number, object)?)),
+        }
+    }
+}
+
+fn is_timestamp_col(timestamp_col: &TimestampCol, i: usize) -> bool {
+    match timestamp_col {
+        TimestampCol::Index(t) => i == *t,
+        TimestampCol::DateTimeIndex(d, t) => i == *d || i == *t,
+    }
+}
+
+/// makes a record that csv timestamp parsing can work with; if timestamp column is given,
+/// only timestamp values are filled in, since the rest is not needed
+fn to_record(
+    field_names: &[String],
+    object: &JsonObject,
+    timestamp_col: Option<&TimestampCol>,
+    line_number: u64,
+) -> ChopperResult<csv::StringRecord> {
+    let mut record = csv::StringRecord::new();
+    for (i, name) in field_names.iter().enumerate() {
+        let is_needed = match timestamp_col {
+            None => true,
+            Some(timestamp_col) => is_timestamp_col(timestamp_col, i),
+        };
+        if !is_needed {
+            record.push_field("");
+            continue;
+        }
+        match object.get(name) {
+            Some(Value::String(s)) => record.push_field(s),
+            Some(Value::Null) | None if timestamp_col.is_some() => {
+                return Err(Error::from(format!(
+                    "json -- timestamp key '{}' is missing on line {}",
+                    name, line_number
+                )))
+            }
+            Some(Value::Null) | None => record.push_field(""),
+            Some(value) => record.push_field(&value.to_string()),
+        }
+    }
+    Ok(record)
+}
+
+/// nulls are ignored; numbers are Long if they all fit, otherwise Double;
+/// anything mixed or nested, other than arrays of numbers, is kept as json text
+fn infer_field_type<'a>(values: impl Iterator<Item = &'a Value>) -> FieldType {
+    let mut common: Option<FieldType> = None;
+    for value in values {
+        let field_type = match value {
+            Value::Null => continue,
+            Value::Bool(_) => FieldType::Boolean,
+            Value::Number(n) if n.is_i64() => FieldType::Long,
+            Value::Number(_) => FieldType::Double,
+            Value::String(_) => FieldType::String,
+            Value::Array(_) if to_array(value).is_some() => FieldType::MultiDimDoubleArray,
+            _ => return FieldType::String,
+        };
+        common = match (common, field_type) {
+            (None, t) => Some(t),
+            (Some(c), t) if c == t => Some(c),
+            (Some(FieldType::Long), FieldType::Double)
+            | (Some(FieldType::Double), FieldType::Long) => Some(FieldType::Double),
+            _ => return FieldType::String,
+        };
+    }
+    common.unwrap_or(FieldType::String)
+}
+
+fn to_field_value(value: &Value, field_type: FieldType) -> Option<FieldValue> {
+    match field_type {
+        FieldType::Boolean => value.as_bool().map(FieldValue::Boolean),
+        FieldType::Long => value.as_i64().map(FieldValue::Long),
+        FieldType::Double => value.as_f64().map(FieldValue::Double),
+        FieldType::String => match value {
+            Value::String(s) => Some(FieldValue::String(s.clone())),
+            _ => Some(FieldValue::String(value.to_string())),
+        },
+        FieldType::MultiDimDoubleArray => to_array(value).map(FieldValue::MultiDimDoubleArray),
+        // these are never inferred
+        _ => None,
+    }
+}
+
+/// nested arrays have to be rectangular; nulls become NaNs
+fn to_array(value: &Value) -> Option<ArrayD<f64>> {
+    let mut shape: Vec<usize> = Vec::new();
+    let mut current = value;
+    while let Value::Array(items) = current {
+        shape.push(items.len());
+        match items.first() {
+            Some(first) => current = first,
+            None => break,
+        }
+    }
+    if shape.is_empty() {
+        return None;
+    }
+
+    let mut data: Vec<f64> = Vec::with_capacity(shape.iter().product());
+    if !flatten_array(value, &shape, &mut data) {
+        return None;
+    }
+    ArrayD::from_shape_vec(IxDyn(&shape), data).ok()
+}
+
+fn flatten_array(value: &Value, shape: &[usize], data: &mut Vec<f64>) -> bool {
+    match (shape.split_first(), value) {
+        (None, Value::Number(n)) => {
+            data.push(n.as_f64().unwrap_or(f64::NAN));
+            true
+        }
+        (None, Value::Null) => {
+            data.push(f64::NAN);
+            true
+        }
+        (Some((len, rest)), Value::Array(items)) if items.len() == *len => {
+            items.iter().all(|item| flatten_array(item, rest, data))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use crate::chopper::types::{FieldType, FieldValue, Header, Row};
+    use crate::source::csv_input_config::CellParseErrorPolicy;
+    use crate::source::csv_timestamp_config::{
+        TimestampColConfig, TimestampConfig, TimestampFmtConfig,
+    };
+    use crate::source::json_input_config::JsonInputConfig;
+    use crate::source::json_source::JsonSource;
+    use crate::source::source::Source;
+    use crate::util::reader::ChopperBufPreviewer;
+    use crate::util::tz::ChopperTz;
+
+    #[test]
+    fn test_json_source() {
+        let input = "{\"time\":1600000000000,\"sym\":\"a\",\"px\":1,\"ok\":true}\n\
+                     \n\
+                     {\"time\":1600000001000,\"px\":1.5,\"book\":[[1,2],[3,null]],\"extra\":{\"x\":1}}\n";
+        let reader: Box<dyn Read> = Box::new(input.as_bytes());
+        let previewer = ChopperBufPreviewer::new(reader).unwrap();
+        let timestamp_config = TimestampConfig::new(
+            TimestampColConfig::Auto,
+            TimestampFmtConfig::Auto,
+            ChopperTz::new_always_fails(),
+        );
+        let config = JsonInputConfig::new(timestamp_config).hide_timestamp_column(true);
+        let mut source = JsonSource::new(previewer, &config).unwrap();
+
+        let expected_header = Header::new(
+            vec![
+                "sym".to_string(),
+                "px".to_string(),
+                "ok".to_string(),
+                "book".to_string(),
+                "extra".to_string(),
+            ],
+            vec![
+                FieldType::String,
+                FieldType::Double,
+                FieldType::Boolean,
+                FieldType::MultiDimDoubleArray,
+                FieldType::String,
+            ],
+        );
+        assert_eq!(source.header(), &expected_header);
+
+        let row = source.next_row().unwrap().unwrap();
+        assert_eq!(
+            row,
+            Row {
+                timestamp: 1_600_000_000_000_000_000,
+                field_values: vec![
+                    FieldValue::String("a".to_string()),
+                    FieldValue::Double(1.0),
+                    FieldValue::Boolean(true),
+                    FieldValue::None,
+                    FieldValue::None,
+                ],
+            }
+        );
+
+        let row = source.next_row().unwrap().unwrap();
+        assert_eq!(row.timestamp, 1_600_000_001_000_000_000);
+        assert_eq!(row.field_values[1], FieldValue::Double(1.5));
+        match &row.field_values[3] {
+            FieldValue::MultiDimDoubleArray(array) => {
+                assert_eq!(array.shape(), &[2, 2]);
+                assert_eq!(array[[1, 0]], 3.0);
+                assert!(array[[1, 1]].is_nan());
+            }
+            v => panic!("unexpected value {}", v),
+        }
+        assert_eq!(
+            row.field_values[4],
+            FieldValue::String("{\"x\":1}".to_string())
+        );
+        assert!(source.next_row().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_cell_parse_error_policy() {
+        let input = "{\"time\":1600000000000,\"px\":1}\n{\"time\":1600000001000,\"px\":\"x\"}\n";
+        let new_source = |policy: Option<CellParseErrorPolicy>| {
+            let reader: Box<dyn Read> = Box::new(input.as_bytes());
+            let previewer = ChopperBufPreviewer::new(reader).unwrap();
+            let timestamp_config = TimestampConfig::new(
+                TimestampColConfig::Auto,
+                TimestampFmtConfig::Auto,
+                ChopperTz::new_always_fails(),
+            );
+            let config = JsonInputConfig::new(timestamp_config)
+                .with_header_row_count(Some(1))
+                .with_cell_parse_error_policy(policy);
+            JsonSource::new(previewer, &config).unwrap()
+        };
+
+        let mut source = new_source(None);
+        assert_eq!(
+            source.next_row().unwrap().unwrap().field_values[1],
+            FieldValue::Long(1)
+        );
+        assert_eq!(
+            source.next_row().unwrap().unwrap().field_values[1],
+            FieldValue::None
+        );
+
+        let mut source = new_source(Some(CellParseErrorPolicy::Error));
+        assert!(source.next_row().unwrap().is_some());
+        assert!(source.next_row().is_err());
+    }
+}
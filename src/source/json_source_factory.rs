@@ -0,0 +1,50 @@
+use std::io::Read;
+
+use crate::chopper::error::ChopperResult;
+use crate::source::json_input_config::JsonInputConfig;
+use crate::source::json_source::JsonSource;
+use crate::source::source::Source;
+use crate::source::source_factory::SourceFactory;
+use crate::util::reader::ChopperBufPreviewer;
+
+#[derive(Clone)]
+pub struct JsonSourceFactory {
+    json_input_config: JsonInputConfig,
+}
+
+impl JsonSourceFactory {
+    pub fn new(json_input_config: JsonInputConfig) -> Self {
+        JsonSourceFactory { json_input_config }
+    }
+}
+
+impl SourceFactory for JsonSourceFactory {
+    fn can_create_from_format(&self, format: &String) -> bool {
+        format.ends_with(".jsonl") || format.ends_with(".ndjson")
+    }
+
+    fn can_create_from_previewer(&self, previewer: &ChopperBufPreviewer<Box<dyn Read>>) -> bool {
+        // this has to go before csv factory, which would happily take json lines as well
+        match previewer.get_lines() {
+            None => false,
+            Some(lines) => match lines.iter().find(|l| !l.trim().is_empty()) {
+                None => false,
+                Some(line) => line.trim_start().starts_with('{'),
+            },
+        }
+    }
+
+    fn create_source(
+        &mut self,
+        previewer: ChopperBufPreviewer<Box<dyn Read>>,
+    ) -> ChopperResult<Box<dyn Source>> {
+        Ok(Box::new(JsonSource::new(
+            previewer,
+            &self.json_input_config,
+        )?))
+    }
+
+    fn box_clone(&self) -> Box<dyn SourceFactory> {
+        Box::new((*self).clone())
+    }
+}
@@ -1,3 +1,6 @@
+pub mod arrow_source;
+pub mod arrow_source_factory;
+pub mod columnar_input_config;
 pub mod csv_input_config;
 pub mod csv_source;
 pub mod csv_source_factory;
@@ -5,6 +8,14 @@ pub mod csv_timestamp_config;
 pub mod csv_timestamp_util;
 pub mod dc_source;
 pub mod dc_source_factory;
+pub mod iter_source;
+pub mod json_input_config;
+pub mod json_source;
+pub mod json_source_factory;
+pub mod monotonic_source;
 pub mod multi_file_source;
+pub mod parquet_source;
+pub mod parquet_source_factory;
+pub mod row_adapters;
 pub mod source;
 pub mod source_factory;
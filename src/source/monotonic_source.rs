@@ -0,0 +1,262 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::str::FromStr;
+
+use crate::chopper::error::{ChopperResult, Error};
+use crate::chopper::types::{Header, Nanos, Row};
+use crate::source::source::Source;
+use crate::util::interval::Interval;
+
+/// what to do with a row whose timestamp is earlier than that of the previous row
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutOfOrderPolicy {
+    /// fail with source name, row number and both timestamps
+    Error,
+    /// skip the row
+    Drop,
+    /// set row timestamp to that of the previous row
+    Clamp,
+    /// buffer rows and sort them, as long as no row is later than given number of nanos;
+    /// rows that are later than that are an error
+    Reorder(Nanos),
+}
+
+impl FromStr for OutOfOrderPolicy {
+    type Err = Error;
+
+    /// lateness for reorder is an interval, e.g. 500ms
+    fn from_str(policy: &str) -> ChopperResult<OutOfOrderPolicy> {
+        match policy {
+            "error" => Ok(OutOfOrderPolicy::Error),
+            "drop" => Ok(OutOfOrderPolicy::Drop),
+            "clamp" => Ok(OutOfOrderPolicy::Clamp),
+            _ => match policy.strip_prefix("reorder:") {
+                Some(lateness) => Ok(OutOfOrderPolicy::Reorder(
+                    lateness.parse::<Interval>()?.as_nanos(),
+                )),
+                None => Err(Error::from(format!(
+                    "out of order -- {} is not a valid policy",
+                    policy
+                ))),
+            },
+        }
+    }
+}
+
+/// rows in the reorder buffer; ties keep arrival order
+struct BufferedRow {
+    row: Row,
+    row_number: u64,
+}
+
+impl PartialEq for BufferedRow {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for BufferedRow {}
+
+impl PartialOrd for BufferedRow {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BufferedRow {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.row
+            .timestamp
+            .cmp(&other.row.timestamp)
+            .then_with(|| self.row_number.cmp(&other.row_number))
+    }
+}
+
+/// makes sure timestamps of the wrapped source never go backwards
+pub struct MonotonicSource {
+    source: Box<dyn Source>,
+    name: String,
+    policy: OutOfOrderPolicy,
+    row_count: u64,
+    last_timestamp: Option<Nanos>,
+    max_timestamp: Option<Nanos>,
+    buffer: BinaryHeap<Reverse<BufferedRow>>,
+}
+
+impl MonotonicSource {
+    /// name is only used for error messages, e.g. file path
+    pub fn new(source: Box<dyn Source>, name: String, policy: OutOfOrderPolicy) -> Self {
+        MonotonicSource {
+            source,
+            name,
+            policy,
+            row_count: 0,
+            last_timestamp: None,
+            max_timestamp: None,
+            buffer: BinaryHeap::new(),
+        }
+    }
+
+    fn out_of_order_error(&self, row_number: u64, timestamp: Nanos) -> Error {
+        Error::TimestampOutOfOrder(
+            self.name.clone(),
+            row_number,
+            self.last_timestamp.unwrap(),
+            timestamp,
+        )
+    }
+
+    fn next_row_in_order(&mut self) -> ChopperResult<Option<Row>> {
+        loop {
+            let mut row = match self.source.next_row()? {
+                None => return Ok(None),
+                Some(row) => row,
+            };
+            self.row_count += 1;
+            let last_timestamp = match self.last_timestamp {
+                Some(last_timestamp) if row.timestamp < last_timestamp => last_timestamp,
+                _ => {
+                    self.last_timestamp = Some(row.timestamp);
+                    return Ok(Some(row));
+                }
+            };
+            match self.policy {
+                OutOfOrderPolicy::Drop => continue,
+                OutOfOrderPolicy::Clamp => {
+                    row.timestamp = last_timestamp;
+                    return Ok(Some(row));
+                }
+                _ => return Err(self.out_of_order_error(self.row_count, row.timestamp)),
+            }
+        }
+    }
+
+    fn next_row_reordered(&mut self, lateness: Nanos) -> ChopperResult<Option<Row>> {
+        loop {
+            // rows that are at least lateness behind the latest timestamp can't be preceded by
+            // any row that is still to come
+            if let (Some(Reverse(next)), Some(max_timestamp)) =
+                (self.buffer.peek(), self.max_timestamp)
+            {
+                if next.row.timestamp <= max_timestamp.saturating_sub(lateness) {
+                    return Ok(Some(self.pop_buffered_row()));
+                }
+            }
+
+            let row = match self.source.next_row()? {
+                None => {
+                    return match self.buffer.is_empty() {
+                        true => Ok(None),
+                        false => Ok(Some(self.pop_buffered_row())),
+                    };
+                }
+                Some(row) => row,
+            };
+            self.row_count += 1;
+            if let Some(last_timestamp) = self.last_timestamp {
+                if row.timestamp < last_timestamp {
+                    return Err(self.out_of_order_error(self.row_count, row.timestamp));
+                }
+            }
+            self.max_timestamp = Some(match self.max_timestamp {
+                Some(max_timestamp) => max_timestamp.max(row.timestamp),
+                None => row.timestamp,
+            });
+            self.buffer.push(Reverse(BufferedRow {
+                row,
+                row_number: self.row_count,
+            }));
+        }
+    }
+
+    fn pop_buffered_row(&mut self) -> Row {
+        let Reverse(buffered_row) = self.buffer.pop().unwrap();
+        self.last_timestamp = Some(buffered_row.row.timestamp);
+        buffered_row.row
+    }
+}
+
+impl Source for MonotonicSource {
+    fn header(&self) -> &Header {
+        self.source.header()
+    }
+
+    fn next_row(&mut self) -> ChopperResult<Option<Row>> {
+        match self.policy {
+            OutOfOrderPolicy::Reorder(lateness) => self.next_row_reordered(lateness),
+            _ => self.next_row_in_order(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chopper::error::{ChopperResult, Error};
+    use crate::chopper::types::{FieldType, FieldValue, Header, Nanos, Row};
+    use crate::source::iter_source::VecSource;
+    use crate::source::monotonic_source::{MonotonicSource, OutOfOrderPolicy};
+    use crate::source::source::Source;
+
+    fn read(timestamps: &[Nanos], policy: OutOfOrderPolicy) -> ChopperResult<Vec<(Nanos, i64)>> {
+        let rows: Vec<Row> = timestamps
+            .iter()
+            .enumerate()
+            .map(|(i, &timestamp)| Row {
+                timestamp,
+                field_values: vec![FieldValue::Long(i as i64)],
+            })
+            .collect();
+        let header = Header::new(vec!["i".to_string()], vec![FieldType::Long]);
+        let source = Box::new(VecSource::new(header, rows));
+        let mut source = MonotonicSource::new(source, "test".to_string(), policy);
+
+        let mut output = Vec::new();
+        while let Some(row) = source.next_row()? {
+            match row.field_values[0] {
+                FieldValue::Long(i) => output.push((row.timestamp, i)),
+                _ => unreachable!(),
+            }
+        }
+        Ok(output)
+    }
+
+    #[test]
+    fn test_monotonic_source() {
+        let timestamps = [10, 12, 11, 15, 13, 20];
+
+        match read(&timestamps, OutOfOrderPolicy::Error) {
+            Err(Error::TimestampOutOfOrder(name, 3, 12, 11)) => assert_eq!(name, "test"),
+            _ => panic!("expected out of order error"),
+        }
+        assert_eq!(
+            read(&timestamps, OutOfOrderPolicy::Drop).unwrap(),
+            vec![(10, 0), (12, 1), (15, 3), (20, 5)]
+        );
+        assert_eq!(
+            read(&timestamps, OutOfOrderPolicy::Clamp).unwrap(),
+            vec![(10, 0), (12, 1), (12, 2), (15, 3), (15, 4), (20, 5)]
+        );
+        assert_eq!(
+            read(&timestamps, OutOfOrderPolicy::Reorder(2)).unwrap(),
+            vec![(10, 0), (11, 2), (12, 1), (13, 4), (15, 3), (20, 5)]
+        );
+        match read(&[10, 20, 30, 15], OutOfOrderPolicy::Reorder(2)) {
+            Err(Error::TimestampOutOfOrder(_, 4, 20, 15)) => {}
+            _ => panic!("expected out of order error"),
+        }
+    }
+
+    #[test]
+    fn test_out_of_order_policy_from_str() {
+        assert_eq!(
+            "reorder:5ms".parse::<OutOfOrderPolicy>().unwrap(),
+            OutOfOrderPolicy::Reorder(5_000_000)
+        );
+        assert_eq!(
+            "reorder:2m".parse::<OutOfOrderPolicy>().unwrap(),
+            OutOfOrderPolicy::Reorder(120_000_000_000)
+        );
+        assert!("reorder:7".parse::<OutOfOrderPolicy>().is_err());
+        assert!("reorder:5min".parse::<OutOfOrderPolicy>().is_err());
+    }
+}
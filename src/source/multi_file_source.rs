@@ -7,13 +7,14 @@ use crate::input::input::InputFormat;
 use crate::input::serial_multi_file_provider::SerialMultiFilePathProvider;
 use crate::input::single_file::SingleFileInputFactory;
 use crate::source::source::Source;
+use crate::util::column_map::ColumnMap;
 
 pub struct SerialMultiFileSource {
     input_factory: SingleFileInputFactory,
     path_provider: Box<dyn SerialMultiFilePathProvider>,
     input_format: InputFormat,
     common_header: Header,
-    swap_map: Vec<usize>, // empty map means pass-through
+    column_map: Option<ColumnMap>, // no map means pass-through
     current_source: Option<Box<dyn Source>>,
 }
 
@@ -24,24 +25,24 @@ impl SerialMultiFileSource {
         input_format: InputFormat,
         external_common_header: Option<Header>,
     ) -> ChopperResult<SerialMultiFileSource> {
-        let (common_header, current_source, swap_map) = match path_provider.get_next_path() {
-            None => (Header::new(Vec::new(), Vec::new()), None, Vec::new()),
+        let (common_header, current_source, column_map) = match path_provider.get_next_path() {
+            None => (Header::new(Vec::new(), Vec::new()), None, None),
             Some(first_path) => {
                 let first_source =
                     Self::create_source_from_path(&mut input_factory, &first_path, &input_format)?;
 
-                let (common_header, swap_map) = match external_common_header {
-                    None => (first_source.header().clone(), Vec::new()),
+                let (common_header, column_map) = match external_common_header {
+                    None => (first_source.header().clone(), None),
                     Some(header) => {
-                        let swap_map = Self::check_headers_match_and_return_swap_map(
+                        let column_map = Self::check_headers_match_and_return_column_map(
                             &header,
                             first_source.header(),
                         )?;
-                        (header, swap_map)
+                        (header, column_map)
                     }
                 };
 
-                (common_header, Some(first_source), swap_map)
+                (common_header, Some(first_source), column_map)
             }
         };
 
@@ -51,31 +52,31 @@ impl SerialMultiFileSource {
             input_format,
             common_header,
             current_source,
-            swap_map,
+            column_map,
         })
     }
 
     fn update_to_next_source(&mut self) -> ChopperResult<()> {
         let next_path = self.path_provider.get_next_path();
 
-        let (next_source, next_swap_map) = match next_path {
-            None => (None, Vec::new()),
+        let (next_source, next_column_map) = match next_path {
+            None => (None, None),
             Some(path) => {
                 let next_source = Self::create_source_from_path(
                     &mut self.input_factory,
                     &path,
                     &self.input_format,
                 )?;
-                let next_swap_map = Self::check_headers_match_and_return_swap_map(
+                let next_column_map = Self::check_headers_match_and_return_column_map(
                     &self.common_header,
                     next_source.header(),
                 )?;
-                (Some(next_source), next_swap_map)
+                (Some(next_source), next_column_map)
             }
         };
 
         self.current_source = next_source;
-        self.swap_map = next_swap_map;
+        self.column_map = next_column_map;
 
         Ok(())
     }
@@ -97,10 +98,11 @@ impl SerialMultiFileSource {
         }
     }
 
-    fn check_headers_match_and_return_swap_map(
+    /// returns no map if new header is the same as the reference one
+    fn check_headers_match_and_return_column_map(
         ref_header: &Header,
         new_header: &Header,
-    ) -> ChopperResult<Vec<usize>> {
+    ) -> ChopperResult<Option<ColumnMap>> {
         if new_header.field_names().len() < ref_header.field_names().len() {
             return Err(Error::from(format!(
                 "next file in a multi-file input has less columns than expected; \
@@ -110,16 +112,10 @@ impl SerialMultiFileSource {
             )));
         }
 
-        let mut new_header = new_header.clone();
-        let mut is_identity_map = true;
-
-        let mut swap_map = Vec::with_capacity(ref_header.field_names().len());
-        for (ref_idx, ref_field_name) in ref_header.field_names().iter().enumerate() {
-            let new_idx_offset = new_header.field_names()[ref_idx..]
-                .iter()
-                .position(|name| name == ref_field_name);
-
-            match new_idx_offset {
+        let column_map = ColumnMap::new(ref_header, new_header);
+        for (ref_idx, new_idx) in column_map.indexes().iter().enumerate() {
+            let ref_field_name = &ref_header.field_names()[ref_idx];
+            match new_idx {
                 None => {
                     return Err(Error::from(format!(
                         "next file in a multi-file input is missing at least one expected column; \
@@ -127,11 +123,9 @@ impl SerialMultiFileSource {
                         ref_field_name,
                     )));
                 }
-                Some(new_idx_offset) => {
-                    let new_idx = ref_idx + new_idx_offset;
-
+                Some(new_idx) => {
                     let ref_field_type = &ref_header.field_types()[ref_idx];
-                    let new_field_type = &new_header.field_types()[new_idx];
+                    let new_field_type = &new_header.field_types()[*new_idx];
 
                     if ref_field_type != new_field_type {
                         return Err(Error::from(format!(
@@ -142,22 +136,14 @@ impl SerialMultiFileSource {
                             new_field_type
                         )));
                     }
-
-                    swap_map.push(new_idx);
-                    if ref_idx != new_idx {
-                        new_header.field_names_mut().swap(ref_idx, new_idx);
-                        new_header.field_types_mut().swap(ref_idx, new_idx);
-                        is_identity_map = false;
-                    }
                 }
             };
         }
 
-        if is_identity_map {
-            swap_map.clear();
+        match column_map.is_identity() {
+            true => Ok(None),
+            false => Ok(Some(column_map)),
         }
-
-        Ok(swap_map)
     }
 }
 
@@ -178,11 +164,8 @@ impl Source for SerialMultiFileSource {
                             continue;
                         }
                         Some(mut next_row) => {
-                            if !self.swap_map.is_empty() {
-                                for (ref_idx, &new_idx) in self.swap_map.iter().enumerate() {
-                                    next_row.field_values.swap(ref_idx, new_idx);
-                                }
-                                next_row.field_values.truncate(self.swap_map.len());
+                            if let Some(column_map) = &self.column_map {
+                                column_map.map_row(&mut next_row);
                             }
                             return Ok(Some(next_row));
                         }
@@ -199,7 +182,7 @@ mod tests {
     use crate::source::multi_file_source::SerialMultiFileSource;
 
     #[test]
-    fn test_check_headers_match_and_return_swap_map() {
+    fn test_check_headers_match_and_return_column_map() {
         let ref_field_names = vec![
             "f1".to_string(),
             "f2".to_string(),
@@ -232,12 +215,12 @@ mod tests {
         ];
         let new_header = Header::new(new_field_names, new_field_types);
         assert!(
-            SerialMultiFileSource::check_headers_match_and_return_swap_map(
+            SerialMultiFileSource::check_headers_match_and_return_column_map(
                 &ref_header,
                 &new_header
             )
             .unwrap()
-            .is_empty()
+            .is_none()
         );
 
         let new_field_names = vec![
@@ -256,7 +239,7 @@ mod tests {
         ];
         let new_header = Header::new(new_field_names, new_field_types);
         assert!(
-            SerialMultiFileSource::check_headers_match_and_return_swap_map(
+            SerialMultiFileSource::check_headers_match_and_return_column_map(
                 &ref_header,
                 &new_header
             )
@@ -279,7 +262,7 @@ mod tests {
         ];
         let new_header = Header::new(new_field_names, new_field_types);
         assert!(
-            SerialMultiFileSource::check_headers_match_and_return_swap_map(
+            SerialMultiFileSource::check_headers_match_and_return_column_map(
                 &ref_header,
                 &new_header
             )
@@ -302,12 +285,14 @@ mod tests {
         ];
         let new_header = Header::new(new_field_names, new_field_types);
         assert_eq!(
-            SerialMultiFileSource::check_headers_match_and_return_swap_map(
+            SerialMultiFileSource::check_headers_match_and_return_column_map(
                 &ref_header,
                 &new_header
             )
-            .unwrap(),
-            vec![0, 2, 2, 3, 4]
+            .unwrap()
+            .unwrap()
+            .indexes(),
+            &vec![Some(0), Some(2), Some(1), Some(3), Some(4)]
         );
 
         let new_field_names = vec![
@@ -332,12 +317,14 @@ mod tests {
         ];
         let new_header = Header::new(new_field_names, new_field_types);
         assert_eq!(
-            SerialMultiFileSource::check_headers_match_and_return_swap_map(
+            SerialMultiFileSource::check_headers_match_and_return_column_map(
                 &ref_header,
                 &new_header
             )
-            .unwrap(),
-            vec![0, 4, 4, 5, 6]
+            .unwrap()
+            .unwrap()
+            .indexes(),
+            &vec![Some(0), Some(4), Some(1), Some(5), Some(6)]
         );
     }
 }
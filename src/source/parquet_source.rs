@@ -0,0 +1,131 @@
+use std::collections::VecDeque;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use parquet::arrow::arrow_reader::{ParquetRecordBatchReader, ParquetRecordBatchReaderBuilder};
+use parquet::file::reader::{ChunkReader, Length};
+
+use crate::chopper::error::ChopperResult;
+use crate::chopper::types::{Header, Row};
+use crate::source::columnar_input_config::ColumnarInputConfig;
+use crate::source::source::Source;
+use crate::transport::seekable::ReadSeek;
+use crate::util::arrow_util::RecordBatchRowReader;
+
+pub const MAGIC: &[u8; 4] = b"PAR1";
+
+pub struct ParquetSource {
+    reader: ParquetRecordBatchReader,
+    row_reader: RecordBatchRowReader,
+    rows: VecDeque<Row>,
+}
+
+impl ParquetSource {
+    /// metadata is read from the end of the input, e.g. a file, and then row groups are
+    /// read and decoded one batch at a time
+    pub fn new<R: 'static + ChunkReader>(
+        reader: R,
+        config: &ColumnarInputConfig,
+    ) -> ChopperResult<Self> {
+        let builder = ParquetRecordBatchReaderBuilder::try_new(reader)?;
+        let row_reader = RecordBatchRowReader::new(builder.schema(), config)?;
+        Ok(ParquetSource {
+            reader: builder.build()?,
+            row_reader,
+            rows: VecDeque::new(),
+        })
+    }
+}
+
+impl ParquetSource {
+    /// for inputs that can't seek, e.g. stdin, http or decompressed files; parquet metadata
+    /// lives at the end of the file, so the whole input is read into memory first
+    pub fn from_stream<R: Read>(
+        mut reader: R,
+        config: &ColumnarInputConfig,
+    ) -> ChopperResult<Self> {
+        let mut buf: Vec<u8> = Vec::new();
+        reader.read_to_end(&mut buf)?;
+        Self::new(Bytes::from(buf), config)
+    }
+
+    /// for seekable inputs, e.g. files, which are read the same way as by new
+    pub fn from_seekable(
+        mut reader: BufReader<Box<dyn ReadSeek>>,
+        config: &ColumnarInputConfig,
+    ) -> ChopperResult<Self> {
+        let len = reader.seek(SeekFrom::End(0))?;
+        let reader = SeekableChunkReader {
+            reader: Arc::new(Mutex::new(reader)),
+            len,
+        };
+        Self::new(reader, config)
+    }
+}
+
+/// gives parquet reader access to any part of a seekable input; same as with files, all the
+/// readers it returns share the input
+struct SeekableChunkReader {
+    reader: Arc<Mutex<BufReader<Box<dyn ReadSeek>>>>,
+    len: u64,
+}
+
+impl Length for SeekableChunkReader {
+    fn len(&self) -> u64 {
+        self.len
+    }
+}
+
+impl ChunkReader for SeekableChunkReader {
+    type T = SeekableChunk;
+
+    fn get_read(&self, start: u64) -> parquet::errors::Result<SeekableChunk> {
+        Ok(SeekableChunk {
+            reader: self.reader.clone(),
+            position: start,
+        })
+    }
+
+    fn get_bytes(&self, start: u64, length: usize) -> parquet::errors::Result<Bytes> {
+        let mut reader = self.reader.lock().unwrap();
+        reader.seek(SeekFrom::Start(start))?;
+        let mut buf = vec![0; length];
+        reader.read_exact(&mut buf)?;
+        Ok(Bytes::from(buf))
+    }
+}
+
+struct SeekableChunk {
+    reader: Arc<Mutex<BufReader<Box<dyn ReadSeek>>>>,
+    position: u64,
+}
+
+impl Read for SeekableChunk {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut reader = self.reader.lock().unwrap();
+        let current = reader.stream_position()?;
+        reader.seek_relative(self.position as i64 - current as i64)?;
+        let bytes_read = reader.read(buf)?;
+        self.position += bytes_read as u64;
+        Ok(bytes_read)
+    }
+}
+
+impl Source for ParquetSource {
+    fn header(&self) -> &Header {
+        self.row_reader.header()
+    }
+
+    fn next_row(&mut self) -> ChopperResult<Option<Row>> {
+        loop {
+            if let Some(row) = self.rows.pop_front() {
+                return Ok(Some(row));
+            }
+            match self.reader.next() {
+                None => return Ok(None),
+                Some(batch) => self.rows.extend(self.row_reader.read_batch(&batch?)?),
+            }
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use std::io::{BufReader, Read};
+
+use crate::chopper::error::ChopperResult;
+use crate::source::columnar_input_config::ColumnarInputConfig;
+use crate::source::parquet_source::{ParquetSource, MAGIC};
+use crate::source::source::Source;
+use crate::source::source_factory::SourceFactory;
+use crate::transport::seekable::ReadSeek;
+use crate::util::reader::ChopperBufPreviewer;
+
+#[derive(Clone)]
+pub struct ParquetSourceFactory {
+    columnar_input_config: ColumnarInputConfig,
+}
+
+impl ParquetSourceFactory {
+    pub fn new(columnar_input_config: ColumnarInputConfig) -> Self {
+        ParquetSourceFactory {
+            columnar_input_config,
+        }
+    }
+}
+
+impl SourceFactory for ParquetSourceFactory {
+    fn can_create_from_format(&self, format: &String) -> bool {
+        format.ends_with(".parquet")
+    }
+
+    fn can_create_from_previewer(&self, previewer: &ChopperBufPreviewer<Box<dyn Read>>) -> bool {
+        previewer.get_buf().starts_with(MAGIC)
+    }
+
+    fn create_source(
+        &mut self,
+        previewer: ChopperBufPreviewer<Box<dyn Read>>,
+    ) -> ChopperResult<Box<dyn Source>> {
+        let reader = previewer.get_reader();
+        Ok(Box::new(ParquetSource::from_stream(
+            reader,
+            &self.columnar_input_config,
+        )?))
+    }
+
+    fn can_create_from_seekable(&self) -> bool {
+        true
+    }
+
+    fn create_source_from_seekable(
+        &mut self,
+        reader: BufReader<Box<dyn ReadSeek>>,
+    ) -> ChopperResult<Box<dyn Source>> {
+        let source = ParquetSource::from_seekable(reader, &self.columnar_input_config)?;
+        Ok(Box::new(source))
+    }
+
+    fn box_clone(&self) -> Box<dyn SourceFactory> {
+        Box::new((*self).clone())
+    }
+}
@@ -0,0 +1,138 @@
+use crate::chopper::error::ChopperResult;
+use crate::chopper::types::{Header, Nanos, Row};
+use crate::source::source::Source;
+
+pub struct MapRows<S: Source, F: FnMut(Row) -> Row> {
+    source: S,
+    f: F,
+}
+
+impl<S: Source, F: FnMut(Row) -> Row> MapRows<S, F> {
+    pub fn new(source: S, f: F) -> Self {
+        MapRows { source, f }
+    }
+}
+
+impl<S: Source, F: FnMut(Row) -> Row> Source for MapRows<S, F> {
+    fn header(&self) -> &Header {
+        self.source.header()
+    }
+
+    fn next_row(&mut self) -> ChopperResult<Option<Row>> {
+        Ok(self.source.next_row()?.map(&mut self.f))
+    }
+}
+
+pub struct FilterRows<S: Source, F: FnMut(&Row) -> bool> {
+    source: S,
+    f: F,
+}
+
+impl<S: Source, F: FnMut(&Row) -> bool> FilterRows<S, F> {
+    pub fn new(source: S, f: F) -> Self {
+        FilterRows { source, f }
+    }
+}
+
+impl<S: Source, F: FnMut(&Row) -> bool> Source for FilterRows<S, F> {
+    fn header(&self) -> &Header {
+        self.source.header()
+    }
+
+    fn next_row(&mut self) -> ChopperResult<Option<Row>> {
+        while let Some(row) = self.source.next_row()? {
+            if (self.f)(&row) {
+                return Ok(Some(row));
+            }
+        }
+        Ok(None)
+    }
+}
+
+pub struct TakeUntil<S: Source> {
+    source: S,
+    end: Nanos,
+    done: bool,
+}
+
+impl<S: Source> TakeUntil<S> {
+    pub fn new(source: S, end: Nanos) -> Self {
+        TakeUntil {
+            source,
+            end,
+            done: false,
+        }
+    }
+}
+
+impl<S: Source> Source for TakeUntil<S> {
+    fn header(&self) -> &Header {
+        self.source.header()
+    }
+
+    fn next_row(&mut self) -> ChopperResult<Option<Row>> {
+        if self.done {
+            return Ok(None);
+        }
+        match self.source.next_row()? {
+            Some(row) if row.timestamp < self.end => Ok(Some(row)),
+            _ => {
+                self.done = true;
+                Ok(None)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chopper::types::{FieldType, FieldValue, Header, Row};
+    use crate::source::iter_source::VecSource;
+    use crate::source::source::Source;
+
+    fn source() -> VecSource {
+        let header = Header::new(vec!["a".to_string()], vec![FieldType::Int]);
+        let rows = (0..5)
+            .map(|i| Row {
+                timestamp: i as u64 * 10,
+                field_values: vec![FieldValue::Int(i)],
+            })
+            .collect::<Vec<Row>>();
+        VecSource::new(header, rows)
+    }
+
+    fn values<S: Source>(source: S) -> Vec<FieldValue> {
+        source
+            .into_rows()
+            .map(|row| row.unwrap().field_values[0].clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_row_adapters() {
+        let doubled = source().map_rows(|mut row| {
+            if let FieldValue::Int(x) = row.field_values[0] {
+                row.field_values[0] = FieldValue::Int(x * 2);
+            }
+            row
+        });
+        assert_eq!(doubled.header().field_names(), &vec!["a"]);
+        assert_eq!(
+            values(doubled),
+            vec![0, 2, 4, 6, 8]
+                .into_iter()
+                .map(FieldValue::Int)
+                .collect::<Vec<FieldValue>>()
+        );
+
+        let odd = source().filter_rows(|row| row.field_values[0] != FieldValue::Int(0));
+        let odd = odd.filter_rows(|row| match row.field_values[0] {
+            FieldValue::Int(x) => x % 2 == 1,
+            _ => false,
+        });
+        assert_eq!(values(odd), vec![FieldValue::Int(1), FieldValue::Int(3)]);
+
+        let early = source().take_until(20);
+        assert_eq!(values(early), vec![FieldValue::Int(0), FieldValue::Int(1)]);
+    }
+}
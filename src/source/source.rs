@@ -1,11 +1,47 @@
 use std::fmt;
 
 use crate::chopper::error::ChopperResult;
-use crate::chopper::types::{Header, Row};
+use crate::chopper::types::{Header, Nanos, Row};
+use crate::source::row_adapters::{FilterRows, MapRows, TakeUntil};
 
 pub trait Source {
     fn header(&self) -> &Header;
     fn next_row(&mut self) -> ChopperResult<Option<Row>>;
+
+    /// rows changed by f; changed rows still have to match the header
+    fn map_rows<F: FnMut(Row) -> Row>(self, f: F) -> MapRows<Self, F>
+    where
+        Self: Sized,
+    {
+        MapRows::new(self, f)
+    }
+
+    /// only rows for which f is true
+    fn filter_rows<F: FnMut(&Row) -> bool>(self, f: F) -> FilterRows<Self, F>
+    where
+        Self: Sized,
+    {
+        FilterRows::new(self, f)
+    }
+
+    /// rows with timestamps before end; source is not read past the first row at or after end
+    fn take_until(self, end: Nanos) -> TakeUntil<Self>
+    where
+        Self: Sized,
+    {
+        TakeUntil::new(self, end)
+    }
+
+    /// rows as an iterator, which ends after the first error
+    fn into_rows(self) -> SourceRows<Self>
+    where
+        Self: Sized,
+    {
+        SourceRows {
+            source: self,
+            done: false,
+        }
+    }
 }
 
 impl<S: Source + ?Sized> Source for Box<S> {
@@ -20,6 +56,47 @@ impl<S: Source + ?Sized> Source for Box<S> {
     }
 }
 
+pub struct SourceRows<S: Source> {
+    source: S,
+    done: bool,
+}
+
+impl<S: Source> SourceRows<S> {
+    pub fn header(&self) -> &Header {
+        self.source.header()
+    }
+}
+
+impl<S: Source> Iterator for SourceRows<S> {
+    type Item = ChopperResult<Row>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.source.next_row() {
+            Ok(Some(row)) => Some(Ok(row)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl IntoIterator for Box<dyn Source> {
+    type Item = ChopperResult<Row>;
+    type IntoIter = SourceRows<Box<dyn Source>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.into_rows()
+    }
+}
+
 //TODO better debug format?
 impl fmt::Debug for dyn Source {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
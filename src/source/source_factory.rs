@@ -1,7 +1,8 @@
-use std::io::Read;
+use std::io::{BufReader, Read};
 
-use crate::chopper::error::ChopperResult;
+use crate::chopper::error::{ChopperResult, Error};
 use crate::source::source::Source;
+use crate::transport::seekable::ReadSeek;
 use crate::util::reader::ChopperBufPreviewer;
 
 pub trait SourceFactory {
@@ -12,6 +13,23 @@ pub trait SourceFactory {
         previewer: ChopperBufPreviewer<Box<dyn Read>>,
     ) -> ChopperResult<Box<dyn Source>>;
 
+    /// formats that need random access, e.g. with metadata at the end of the file, can read
+    /// seekable inputs, e.g. plain files, directly instead of buffering the whole input
+    fn can_create_from_seekable(&self) -> bool {
+        false
+    }
+
+    /// only called if can_create_from_seekable, for inputs this factory can create sources
+    /// from; reader is at the start of the input
+    fn create_source_from_seekable(
+        &mut self,
+        _reader: BufReader<Box<dyn ReadSeek>>,
+    ) -> ChopperResult<Box<dyn Source>> {
+        Err(Error::from(
+            "SourceFactory -- seekable input is not supported",
+        ))
+    }
+
     fn box_clone(&self) -> Box<dyn SourceFactory>;
 }
 
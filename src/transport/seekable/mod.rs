@@ -4,6 +4,6 @@ pub mod file;
 pub mod seekable_factory;
 pub mod seekable_transport;
 
-pub trait ReadSeek: std::io::Read + std::io::Seek {}
+pub trait ReadSeek: std::io::Read + std::io::Seek + Send {}
 
 impl ReadSeek for File {}
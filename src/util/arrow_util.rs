@@ -0,0 +1,608 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+use arrow::array::{
+    Array, ArrayRef, AsArray, BinaryBuilder, BooleanBuilder, FixedSizeListBuilder, Float32Builder,
+    Float64Builder, Int16Builder, Int32Builder, Int64Builder, ListBuilder, StringBuilder,
+    TimestampNanosecondArray, UInt16Builder, UInt8Builder,
+};
+use arrow::compute::{cast_with_options, CastOptions};
+use arrow::datatypes::{
+    DataType, Field, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Schema, SchemaRef,
+    TimeUnit, UInt16Type, UInt8Type,
+};
+use arrow::record_batch::RecordBatch;
+use ndarray::{ArrayD, IxDyn};
+use serde::Deserialize;
+
+use crate::chopper::error::{ChopperResult, Error};
+use crate::chopper::types::{FieldType, FieldValue, Header, Nanos, Row};
+use crate::source::columnar_input_config::ColumnarInputConfig;
+use crate::source::csv_timestamp_config::TimestampColConfig;
+use crate::util::timestamp_units::{TimestampUnits, TIMESTAMP_UNITS};
+
+pub const TIME_COL_NAME: &str = "timestamp";
+pub const TIME_COL_TIMEZONE: &str = "UTC";
+
+/// canonical arrow extension type for tensors of the same shape, stored as fixed size lists
+pub const TENSOR_EXTENSION_NAME: &str = "arrow.fixed_shape_tensor";
+const EXTENSION_NAME_KEY: &str = "ARROW:extension:name";
+const EXTENSION_METADATA_KEY: &str = "ARROW:extension:metadata";
+
+const CAST_OPTIONS: CastOptions = CastOptions {
+    // we want an error instead of a silent null on overflow
+    safe: false,
+    format_options: arrow::util::display::FormatOptions::new(),
+};
+
+/// converts record batches into rows; column types are mapped to the closest field type
+/// that can hold all the values, so for example u32 becomes Long and f16 becomes Float
+pub struct RecordBatchRowReader {
+    header: Header,
+    timestamp_col: usize,
+    timestamp_col_name: String,
+    timestamp_units: TimestampUnits,
+    /// arrow column index and tensor shape (if any) for each of the header fields
+    columns: Vec<(usize, Option<Vec<usize>>)>,
+}
+
+impl RecordBatchRowReader {
+    pub fn new(schema: &Schema, config: &ColumnarInputConfig) -> ChopperResult<Self> {
+        let (timestamp_col, timestamp_units) = find_timestamp_col(schema, &config.timestamp_col)?;
+
+        let mut field_names: Vec<String> = Vec::new();
+        let mut field_types: Vec<FieldType> = Vec::new();
+        let mut columns: Vec<(usize, Option<Vec<usize>>)> = Vec::new();
+        for (i, field) in schema.fields().iter().enumerate() {
+            if i == timestamp_col && config.hide_timestamp_column {
+                continue;
+            }
+            field_names.push(field.name().to_string());
+            field_types.push(field_type_from_arrow(field)?);
+            columns.push((i, tensor_shape(field)));
+        }
+
+        Ok(RecordBatchRowReader {
+            header: Header::new(field_names, field_types),
+            timestamp_col,
+            timestamp_col_name: schema.field(timestamp_col).name().to_string(),
+            timestamp_units,
+            columns,
+        })
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    pub fn read_batch(&self, batch: &RecordBatch) -> ChopperResult<Vec<Row>> {
+        let timestamps = timestamp_column_to_nanos(
+            batch.column(self.timestamp_col),
+            &self.timestamp_col_name,
+            self.timestamp_units,
+        )?;
+        let mut rows: Vec<Row> = timestamps
+            .into_iter()
+            .map(|timestamp| Row {
+                timestamp,
+                field_values: Vec::with_capacity(self.columns.len()),
+            })
+            .collect();
+
+        for ((arrow_col, shape), field_type) in self.columns.iter().zip(self.header.field_types()) {
+            let values = column_to_field_values(batch.column(*arrow_col), *field_type, shape)?;
+            for (row, value) in rows.iter_mut().zip(values) {
+                row.field_values.push(value);
+            }
+        }
+        Ok(rows)
+    }
+}
+
+fn find_timestamp_col(
+    schema: &Schema,
+    timestamp_col_config: &TimestampColConfig,
+) -> ChopperResult<(usize, TimestampUnits)> {
+    let fields = schema.fields();
+    let index = match timestamp_col_config {
+        TimestampColConfig::Auto => {
+            // prefer columns that are typed as timestamps, then fall back to obvious names
+            let typed = fields.iter().position(|f| is_timestamp_type(f.data_type()));
+            let named = fields.iter().position(|f| {
+                let name = f.name().to_lowercase();
+                f.data_type().is_integer()
+                    && (name == "time"
+                        || name == TIME_COL_NAME
+                        || (name.starts_with("time") && units_from_name(&name).is_some()))
+            });
+            match typed.or(named) {
+                Some(i) => i,
+                None => {
+                    return Err(Error::from(
+                        "columnar input -- failed to find timestamp column; please specify it",
+                    ))
+                }
+            }
+        }
+        TimestampColConfig::Index(i) => {
+            if *i >= fields.len() {
+                return Err(Error::from(format!(
+                    "columnar input -- timestamp column index {} is out of range",
+                    i
+                )));
+            }
+            *i
+        }
+        TimestampColConfig::Name(name) => match schema.index_of(name) {
+            Ok(i) => i,
+            Err(_) => return Err(Error::ColumnMissing(name.to_string())),
+        },
+        TimestampColConfig::DateTimeIndex(_, _) | TimestampColConfig::DateTimeName(_, _) => {
+            return Err(Error::from(
+                "columnar input -- separate date and time columns are not supported",
+            ))
+        }
+    };
+
+    let field = &fields[index];
+    match field.data_type() {
+        // units are taken from the type, so these are effectively ignored
+        t if is_timestamp_type(t) => Ok((index, TimestampUnits::Nanos)),
+        t if t.is_integer() => Ok((
+            index,
+            units_from_name(&field.name().to_lowercase()).unwrap_or(TimestampUnits::Nanos),
+        )),
+        t => Err(Error::from(format!(
+            "columnar input -- timestamp column '{}' has unsupported type {}",
+            field.name(),
+            t
+        ))),
+    }
+}
+
+fn is_timestamp_type(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::Timestamp(_, _) | DataType::Date32 | DataType::Date64
+    )
+}
+
+fn units_from_name(name: &str) -> Option<TimestampUnits> {
+    TIMESTAMP_UNITS
+        .iter()
+        .find(|(_, suffix)| name.ends_with(suffix))
+        .map(|(units, _)| *units)
+}
+
+fn timestamp_column_to_nanos(
+    array: &ArrayRef,
+    name: &str,
+    units: TimestampUnits,
+) -> ChopperResult<Vec<Nanos>> {
+    let multiplier: i64 = match array.data_type() {
+        t if is_timestamp_type(t) => 1,
+        _ => match units {
+            TimestampUnits::Seconds => 1_000_000_000,
+            TimestampUnits::Millis => 1_000_000,
+            TimestampUnits::Micros => 1_000,
+            TimestampUnits::Nanos => 1,
+        },
+    };
+    let array = cast_to_nanos(array)?;
+    let array = cast_with_options(&array, &DataType::Int64, &CAST_OPTIONS)?;
+    let array = array.as_primitive::<Int64Type>();
+
+    let mut timestamps: Vec<Nanos> = Vec::with_capacity(array.len());
+    for i in 0..array.len() {
+        let timestamp = match array.is_null(i) {
+            true => None,
+            false => array
+                .value(i)
+                .checked_mul(multiplier)
+                .and_then(|t| u64::try_from(t).ok()),
+        };
+        match timestamp {
+            Some(timestamp) => timestamps.push(timestamp),
+            None => {
+                return Err(Error::from(format!(
+                    "columnar input -- timestamp column '{}' has a null or negative value",
+                    name
+                )))
+            }
+        }
+    }
+    Ok(timestamps)
+}
+
+/// timestamp and date types are converted to nanos since epoch; everything else is unchanged
+fn cast_to_nanos(array: &ArrayRef) -> ChopperResult<ArrayRef> {
+    match array.data_type() {
+        t if is_timestamp_type(t) => Ok(cast_with_options(
+            array,
+            &DataType::Timestamp(TimeUnit::Nanosecond, None),
+            &CAST_OPTIONS,
+        )?),
+        _ => Ok(array.clone()),
+    }
+}
+
+pub fn field_type_from_arrow(field: &Field) -> ChopperResult<FieldType> {
+    let field_type = match field.data_type() {
+        DataType::Boolean => FieldType::Boolean,
+        DataType::UInt8 => FieldType::Byte,
+        DataType::Int8 | DataType::Int16 => FieldType::Short,
+        DataType::UInt16 | DataType::Int32 => FieldType::Int,
+        DataType::UInt32 | DataType::Int64 | DataType::UInt64 => FieldType::Long,
+        t if is_timestamp_type(t) => FieldType::Long,
+        DataType::Float16 | DataType::Float32 => FieldType::Float,
+        DataType::Float64 => FieldType::Double,
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => FieldType::String,
+        DataType::Dictionary(_, value_type)
+            if matches!(
+                value_type.as_ref(),
+                DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View
+            ) =>
+        {
+            FieldType::String
+        }
+        DataType::Binary
+        | DataType::LargeBinary
+        | DataType::BinaryView
+        | DataType::FixedSizeBinary(_) => FieldType::ByteBuf,
+        DataType::List(item) | DataType::LargeList(item) | DataType::FixedSizeList(item, _)
+            if item.data_type().is_numeric() =>
+        {
+            FieldType::MultiDimDoubleArray
+        }
+        t => {
+            return Err(Error::from(format!(
+                "columnar input -- column '{}' has unsupported type {}",
+                field.name(),
+                t
+            )))
+        }
+    };
+    Ok(field_type)
+}
+
+/// arrow type used to store values of the given field type; multi-dim arrays are stored
+/// as fixed shape tensors when the shape is known and as plain lists otherwise
+pub fn arrow_type_from_field_type(field_type: FieldType, shape: Option<&[usize]>) -> DataType {
+    match field_type {
+        FieldType::Boolean => DataType::Boolean,
+        FieldType::Byte => DataType::UInt8,
+        FieldType::ByteBuf => DataType::Binary,
+        FieldType::Char => DataType::UInt16,
+        FieldType::Double => DataType::Float64,
+        FieldType::Float => DataType::Float32,
+        FieldType::Int => DataType::Int32,
+        FieldType::Long => DataType::Int64,
+        FieldType::Short => DataType::Int16,
+        FieldType::String => DataType::Utf8,
+        FieldType::MultiDimDoubleArray => {
+            let item = Arc::new(Field::new_list_field(DataType::Float64, true));
+            match shape {
+                Some(shape) => {
+                    DataType::FixedSizeList(item, shape.iter().product::<usize>() as i32)
+                }
+                None => DataType::List(item),
+            }
+        }
+    }
+}
+
+/// extension metadata of fixed shape tensors, e.g. {"shape":[2,3]}; only the shape is of
+/// interest
+#[derive(Deserialize)]
+struct TensorMetadata {
+    shape: Vec<usize>,
+}
+
+fn tensor_shape(field: &Field) -> Option<Vec<usize>> {
+    let metadata = field.metadata();
+    if metadata.get(EXTENSION_NAME_KEY).map(|s| s.as_str()) != Some(TENSOR_EXTENSION_NAME) {
+        return None;
+    }
+    let json = metadata.get(EXTENSION_METADATA_KEY)?;
+    serde_json::from_str::<TensorMetadata>(json)
+        .ok()
+        .map(|metadata| metadata.shape)
+}
+
+fn tensor_field(name: &str, shape: &[usize]) -> Field {
+    let mut metadata = HashMap::new();
+    metadata.insert(
+        EXTENSION_NAME_KEY.to_string(),
+        TENSOR_EXTENSION_NAME.to_string(),
+    );
+    metadata.insert(
+        EXTENSION_METADATA_KEY.to_string(),
+        serde_json::json!({ "shape": shape }).to_string(),
+    );
+    Field::new(
+        name,
+        arrow_type_from_field_type(FieldType::MultiDimDoubleArray, Some(shape)),
+        true,
+    )
+    .with_metadata(metadata)
+}
+
+fn column_to_field_values(
+    array: &ArrayRef,
+    field_type: FieldType,
+    shape: &Option<Vec<usize>>,
+) -> ChopperResult<Vec<FieldValue>> {
+    let array = cast_to_nanos(array)?;
+    let target = match (field_type, array.data_type()) {
+        (FieldType::MultiDimDoubleArray, DataType::FixedSizeList(_, size)) => {
+            let item = Arc::new(Field::new_list_field(DataType::Float64, true));
+            DataType::FixedSizeList(item, *size)
+        }
+        _ => arrow_type_from_field_type(field_type, None),
+    };
+    let array = cast_with_options(&array, &target, &CAST_OPTIONS)?;
+
+    let mut values: Vec<FieldValue> = Vec::with_capacity(array.len());
+    for i in 0..array.len() {
+        if array.is_null(i) {
+            values.push(FieldValue::None);
+            continue;
+        }
+        let value = match field_type {
+            FieldType::Boolean => FieldValue::Boolean(array.as_boolean().value(i)),
+            FieldType::Byte => FieldValue::Byte(array.as_primitive::<UInt8Type>().value(i)),
+            FieldType::ByteBuf => FieldValue::ByteBuf(array.as_binary::<i32>().value(i).to_vec()),
+            FieldType::Char => FieldValue::Char(array.as_primitive::<UInt16Type>().value(i)),
+            FieldType::Double => FieldValue::Double(array.as_primitive::<Float64Type>().value(i)),
+            FieldType::Float => FieldValue::Float(array.as_primitive::<Float32Type>().value(i)),
+            FieldType::Int => FieldValue::Int(array.as_primitive::<Int32Type>().value(i)),
+            FieldType::Long => FieldValue::Long(array.as_primitive::<Int64Type>().value(i)),
+            FieldType::Short => FieldValue::Short(array.as_primitive::<Int16Type>().value(i)),
+            FieldType::String => FieldValue::String(array.as_string::<i32>().value(i).to_string()),
+            FieldType::MultiDimDoubleArray => {
+                let list = match array.data_type() {
+                    DataType::FixedSizeList(_, _) => array.as_fixed_size_list().value(i),
+                    _ => array.as_list::<i32>().value(i),
+                };
+                let list = list.as_primitive::<Float64Type>();
+                let data: Vec<f64> = (0..list.len())
+                    .map(|j| match list.is_null(j) {
+                        true => f64::NAN,
+                        false => list.value(j),
+                    })
+                    .collect();
+                let shape = match shape {
+                    Some(shape) => shape.clone(),
+                    None => vec![data.len()],
+                };
+                FieldValue::MultiDimDoubleArray(ArrayD::from_shape_vec(IxDyn(&shape), data)?)
+            }
+        };
+        values.push(value);
+    }
+    Ok(values)
+}
+
+/// schema with the time column first, followed by header fields; shapes of multi-dim
+/// array columns are taken from the first non-null value in the given rows
+pub fn schema_from_header(header: &Header, rows: &[Row]) -> SchemaRef {
+    // don't clash with a column that's already there, which would happen for example
+    // when a file previously written by chopper is read back with timestamp column visible
+    let mut time_col_name = TIME_COL_NAME.to_string();
+    while header.field_names().contains(&time_col_name) {
+        time_col_name.insert(0, '_');
+    }
+
+    let mut fields: Vec<Field> = Vec::with_capacity(header.field_names().len() + 1);
+    fields.push(Field::new(
+        time_col_name,
+        DataType::Timestamp(TimeUnit::Nanosecond, Some(TIME_COL_TIMEZONE.into())),
+        false,
+    ));
+    for (i, (name, field_type)) in header
+        .field_names()
+        .iter()
+        .zip(header.field_types())
+        .enumerate()
+    {
+        let shape = match field_type {
+            FieldType::MultiDimDoubleArray => {
+                rows.iter().find_map(|row| match &row.field_values[i] {
+                    FieldValue::MultiDimDoubleArray(array) => Some(array.shape().to_vec()),
+                    _ => None,
+                })
+            }
+            _ => None,
+        };
+        let field = match shape {
+            Some(shape) => tensor_field(name, &shape),
+            None => Field::new(name, arrow_type_from_field_type(*field_type, None), true),
+        };
+        fields.push(field);
+    }
+    Arc::new(Schema::new(fields))
+}
+
+/// rows must match the schema, which is expected to be created by schema_from_header
+pub fn rows_to_record_batch(schema: &SchemaRef, rows: &[Row]) -> ChopperResult<RecordBatch> {
+    let mut columns: Vec<ArrayRef> = Vec::with_capacity(schema.fields().len());
+
+    let timestamps = rows
+        .iter()
+        .map(|row| {
+            i64::try_from(row.timestamp).map_err(|_| {
+                Error::from(format!(
+                    "columnar output -- timestamp {} is too large",
+                    row.timestamp
+                ))
+            })
+        })
+        .collect::<ChopperResult<Vec<i64>>>()?;
+    columns.push(Arc::new(
+        TimestampNanosecondArray::from(timestamps).with_timezone(TIME_COL_TIMEZONE),
+    ));
+
+    for (i, field) in schema.fields().iter().skip(1).enumerate() {
+        columns.push(build_column(field, i, rows)?);
+    }
+    Ok(RecordBatch::try_new(schema.clone(), columns)?)
+}
+
+fn build_column(field: &Field, index: usize, rows: &[Row]) -> ChopperResult<ArrayRef> {
+    macro_rules! build {
+        ($builder:expr, $variant:ident, $value:ident => $append:expr) => {{
+            let mut builder = $builder;
+            for row in rows {
+                match &row.field_values[index] {
+                    FieldValue::$variant($value) => builder.append_value($append),
+                    FieldValue::None => builder.append_null(),
+                    value => return Err(type_mismatch(field, value)),
+                }
+            }
+            Arc::new(builder.finish()) as ArrayRef
+        }};
+    }
+
+    let array = match field.data_type() {
+        DataType::Boolean => build!(BooleanBuilder::new(), Boolean, v => *v),
+        DataType::UInt8 => build!(UInt8Builder::new(), Byte, v => *v),
+        DataType::Binary => build!(BinaryBuilder::new(), ByteBuf, v => v),
+        DataType::UInt16 => build!(UInt16Builder::new(), Char, v => *v),
+        DataType::Float64 => build!(Float64Builder::new(), Double, v => *v),
+        DataType::Float32 => build!(Float32Builder::new(), Float, v => *v),
+        DataType::Int32 => build!(Int32Builder::new(), Int, v => *v),
+        DataType::Int64 => build!(Int64Builder::new(), Long, v => *v),
+        DataType::Int16 => build!(Int16Builder::new(), Short, v => *v),
+        DataType::Utf8 => build!(StringBuilder::new(), String, v => v),
+        DataType::FixedSizeList(_, size) => {
+            let shape = tensor_shape(field).unwrap_or_else(|| vec![*size as usize]);
+            let mut builder = FixedSizeListBuilder::new(Float64Builder::new(), *size);
+            for row in rows {
+                match &row.field_values[index] {
+                    FieldValue::MultiDimDoubleArray(array) => {
+                        if array.shape() != shape.as_slice() {
+                            return Err(Error::from(format!(
+                                "columnar output -- column '{}' has arrays of shape {:?} and {:?}",
+                                field.name(),
+                                shape,
+                                array.shape()
+                            )));
+                        }
+                        builder.values().extend(array.iter().map(|v| Some(*v)));
+                        builder.append(true);
+                    }
+                    FieldValue::None => {
+                        builder.values().append_nulls(*size as usize);
+                        builder.append(false);
+                    }
+                    value => return Err(type_mismatch(field, value)),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::List(_) => {
+            let mut builder = ListBuilder::new(Float64Builder::new());
+            for row in rows {
+                match &row.field_values[index] {
+                    FieldValue::MultiDimDoubleArray(array) => {
+                        builder.values().extend(array.iter().map(|v| Some(*v)));
+                        builder.append(true);
+                    }
+                    FieldValue::None => builder.append(false),
+                    value => return Err(type_mismatch(field, value)),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        t => unreachable!("unexpected output column type {}", t),
+    };
+    Ok(array)
+}
+
+fn type_mismatch(field: &Field, value: &FieldValue) -> Error {
+    Error::from(format!(
+        "columnar output -- value {} doesn't match type {} of column '{}'",
+        value,
+        field.data_type(),
+        field.name()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::ArrayD;
+
+    use crate::chopper::types::{FieldType, FieldValue, Header, Row};
+    use crate::source::columnar_input_config::ColumnarInputConfig;
+    use crate::util::arrow_util::{
+        rows_to_record_batch, schema_from_header, RecordBatchRowReader, TIME_COL_NAME,
+    };
+
+    #[test]
+    fn test_round_trip() {
+        let header = Header::new(
+            vec![
+                "a".to_string(),
+                "b".to_string(),
+                "c".to_string(),
+                "d".to_string(),
+                "e".to_string(),
+                "f".to_string(),
+            ],
+            vec![
+                FieldType::Boolean,
+                FieldType::Char,
+                FieldType::Long,
+                FieldType::String,
+                FieldType::ByteBuf,
+                FieldType::MultiDimDoubleArray,
+            ],
+        );
+        let array = ArrayD::from_shape_vec(vec![2, 3], vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]).unwrap();
+        let rows = vec![
+            Row {
+                timestamp: 1,
+                field_values: vec![
+                    FieldValue::Boolean(true),
+                    FieldValue::Char(7),
+                    FieldValue::Long(-3),
+                    FieldValue::String("x".to_string()),
+                    FieldValue::ByteBuf(vec![0, 1]),
+                    FieldValue::MultiDimDoubleArray(array),
+                ],
+            },
+            Row {
+                timestamp: 2,
+                field_values: vec![FieldValue::None; 6],
+            },
+        ];
+
+        let schema = schema_from_header(&header, &rows);
+        assert_eq!(schema.field(0).name(), TIME_COL_NAME);
+        let batch = rows_to_record_batch(&schema, &rows).unwrap();
+
+        let config = ColumnarInputConfig::new().hide_timestamp_column(true);
+        let reader = RecordBatchRowReader::new(&schema, &config).unwrap();
+        // chars don't have a dedicated arrow type, so they come back as wider ints
+        let mut expected_types = header.field_types().clone();
+        expected_types[1] = FieldType::Int;
+        assert_eq!(reader.header().field_names(), header.field_names());
+        assert_eq!(reader.header().field_types(), &expected_types);
+
+        let mut expected = rows.clone();
+        expected[0].field_values[1] = FieldValue::Int(7);
+        assert_eq!(reader.read_batch(&batch).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_timestamp_out_of_range() {
+        let header = Header::new(vec!["a".to_string()], vec![FieldType::Int]);
+        let rows = vec![Row {
+            timestamp: u64::MAX,
+            field_values: vec![FieldValue::Int(1)],
+        }];
+        let schema = schema_from_header(&header, &rows);
+        assert!(rows_to_record_batch(&schema, &rows).is_err());
+    }
+}
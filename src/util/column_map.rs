@@ -0,0 +1,223 @@
+use std::mem;
+
+use crate::chopper::error::{ChopperResult, Error};
+use crate::chopper::types::{FieldType, FieldValue, Header, Row};
+
+/// type that values of both given types can be converted to without losing more than
+/// floating point precision, if any
+pub fn widen_type(a: FieldType, b: FieldType) -> Option<FieldType> {
+    fn integer_rank(field_type: FieldType) -> Option<u8> {
+        match field_type {
+            FieldType::Byte => Some(0),
+            FieldType::Short => Some(1),
+            FieldType::Int => Some(2),
+            FieldType::Long => Some(3),
+            _ => None,
+        }
+    }
+    if a == b {
+        return Some(a);
+    }
+    match (integer_rank(a), integer_rank(b)) {
+        (Some(rank_a), Some(rank_b)) => Some(if rank_a > rank_b { a } else { b }),
+        _ => {
+            let is_number = |t: FieldType| {
+                integer_rank(t).is_some() || t == FieldType::Float || t == FieldType::Double
+            };
+            match is_number(a) && is_number(b) {
+                true => Some(FieldType::Double),
+                false => None,
+            }
+        }
+    }
+}
+
+/// converts value to a type that widen_type returned for its own type
+fn widen_value(value: FieldValue, field_type: FieldType) -> FieldValue {
+    match (value, field_type) {
+        (FieldValue::Byte(v), FieldType::Short) => FieldValue::Short(v as i16),
+        (FieldValue::Byte(v), FieldType::Int) => FieldValue::Int(v as i32),
+        (FieldValue::Byte(v), FieldType::Long) => FieldValue::Long(v as i64),
+        (FieldValue::Byte(v), FieldType::Double) => FieldValue::Double(v as f64),
+        (FieldValue::Short(v), FieldType::Int) => FieldValue::Int(v as i32),
+        (FieldValue::Short(v), FieldType::Long) => FieldValue::Long(v as i64),
+        (FieldValue::Short(v), FieldType::Double) => FieldValue::Double(v as f64),
+        (FieldValue::Int(v), FieldType::Long) => FieldValue::Long(v as i64),
+        (FieldValue::Int(v), FieldType::Double) => FieldValue::Double(v as f64),
+        (FieldValue::Long(v), FieldType::Double) => FieldValue::Double(v as f64),
+        (FieldValue::Float(v), FieldType::Double) => FieldValue::Double(v as f64),
+        (value, _) => value,
+    }
+}
+
+/// rearranges row values of one header to match another header, with columns matched by name
+pub struct ColumnMap {
+    /// for each target column, index of the matching column, or none if there isn't one
+    indexes: Vec<Option<usize>>,
+    /// for each target column, type that values have to be widened to, if any
+    conversions: Vec<Option<FieldType>>,
+    identity: bool,
+}
+
+impl ColumnMap {
+    /// each target column is matched with the first column of the same name that is not yet
+    /// matched with an earlier target column; types are not checked
+    pub fn new(target: &Header, header: &Header) -> ColumnMap {
+        let mut matched = vec![false; header.field_names().len()];
+        let mut indexes: Vec<Option<usize>> = Vec::with_capacity(target.field_names().len());
+        for target_name in target.field_names() {
+            let index = header
+                .field_names()
+                .iter()
+                .enumerate()
+                .position(|(i, name)| !matched[i] && name == target_name);
+            if let Some(index) = index {
+                matched[index] = true;
+            }
+            indexes.push(index);
+        }
+        let identity = indexes.len() == header.field_names().len()
+            && indexes
+                .iter()
+                .enumerate()
+                .all(|(i, index)| *index == Some(i));
+        ColumnMap {
+            conversions: vec![None; indexes.len()],
+            indexes,
+            identity,
+        }
+    }
+
+    /// values of matched columns whose types differ are converted to the target type, as long
+    /// as the target type is wider; otherwise, it is an error
+    pub fn with_widening(mut self, target: &Header, header: &Header) -> ChopperResult<Self> {
+        for (i, index) in self.indexes.iter().enumerate() {
+            if let Some(index) = index {
+                let target_type = target.field_types()[i];
+                let field_type = header.field_types()[*index];
+                if target_type == field_type {
+                    continue;
+                }
+                if widen_type(target_type, field_type) != Some(target_type) {
+                    return Err(Error::from(format!(
+                        "column {:?} of type {:?} cannot be converted to type {:?}",
+                        target.field_names()[i],
+                        field_type,
+                        target_type
+                    )));
+                }
+                self.conversions[i] = Some(target_type);
+                self.identity = false;
+            }
+        }
+        Ok(self)
+    }
+
+    pub fn indexes(&self) -> &Vec<Option<usize>> {
+        &self.indexes
+    }
+
+    /// true when rows don't have to be changed at all
+    pub fn is_identity(&self) -> bool {
+        self.identity
+    }
+
+    /// unmatched target columns get null values and columns that aren't matched are dropped
+    pub fn map_row(&self, row: &mut Row) {
+        if self.identity {
+            return;
+        }
+        let mut field_values = mem::take(&mut row.field_values);
+        row.field_values = self
+            .indexes
+            .iter()
+            .zip(&self.conversions)
+            .map(|(index, conversion)| match index {
+                None => FieldValue::None,
+                Some(index) => {
+                    let value = mem::replace(&mut field_values[*index], FieldValue::None);
+                    match conversion {
+                        None => value,
+                        Some(field_type) => widen_value(value, *field_type),
+                    }
+                }
+            })
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chopper::types::{FieldType, FieldValue, Header, Row};
+    use crate::util::column_map::{widen_type, ColumnMap};
+
+    fn header(names: &[&str], types: &[FieldType]) -> Header {
+        Header::new(
+            names.iter().map(|s| s.to_string()).collect(),
+            types.to_vec(),
+        )
+    }
+
+    #[test]
+    fn test_column_map() {
+        let target = header(
+            &["a", "b", "b", "c"],
+            &[
+                FieldType::Long,
+                FieldType::String,
+                FieldType::String,
+                FieldType::Double,
+            ],
+        );
+        let other = header(
+            &["b", "x", "c", "b"],
+            &[
+                FieldType::String,
+                FieldType::Boolean,
+                FieldType::Int,
+                FieldType::String,
+            ],
+        );
+        let map = ColumnMap::new(&target, &other)
+            .with_widening(&target, &other)
+            .unwrap();
+        assert_eq!(map.indexes(), &vec![None, Some(0), Some(3), Some(2)]);
+        let mut row = Row {
+            timestamp: 0,
+            field_values: vec![
+                FieldValue::String("b0".to_string()),
+                FieldValue::Boolean(true),
+                FieldValue::Int(7),
+                FieldValue::String("b1".to_string()),
+            ],
+        };
+        map.map_row(&mut row);
+        assert_eq!(
+            row.field_values,
+            vec![
+                FieldValue::None,
+                FieldValue::String("b0".to_string()),
+                FieldValue::String("b1".to_string()),
+                FieldValue::Double(7.0),
+            ]
+        );
+
+        assert!(ColumnMap::new(&target, &target).is_identity());
+        assert!(ColumnMap::new(&other, &target)
+            .with_widening(&other, &target)
+            .is_err());
+    }
+
+    #[test]
+    fn test_widen_type() {
+        assert_eq!(
+            widen_type(FieldType::Short, FieldType::Long),
+            Some(FieldType::Long)
+        );
+        assert_eq!(
+            widen_type(FieldType::Float, FieldType::Int),
+            Some(FieldType::Double)
+        );
+        assert_eq!(widen_type(FieldType::String, FieldType::Int), None);
+    }
+}
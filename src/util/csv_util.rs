@@ -2,6 +2,7 @@ use std::collections::HashMap;
 
 use crate::chopper::error::{ChopperResult, Error};
 use crate::chopper::types::FieldType;
+use crate::util::dc_factory::DCFactory;
 
 pub fn parse_into_delimiter(str: &str) -> ChopperResult<u8> {
     /* Code in this function was adapted from public domain xsv project. */
@@ -27,6 +28,54 @@ pub fn parse_into_delimiter(str: &str) -> ChopperResult<u8> {
     }
 }
 
+/// parses column type declarations like "price:f64,qty:i64,sym:utf8str" using dc type names;
+/// declarations can be separated by commas or newlines, blank lines and lines starting
+/// with '#' are ignored, so the same format can be used for schema files
+pub fn parse_into_schema(str: &str) -> ChopperResult<Vec<(String, FieldType)>> {
+    let type_map = DCFactory::create_default_field_name_to_type_map();
+    let mut schema: Vec<(String, FieldType)> = Vec::new();
+    for line in str.lines().map(|l| l.trim()) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        for declaration in line.split(',').map(|d| d.trim()) {
+            if declaration.is_empty() {
+                continue;
+            }
+            let mut split = declaration.rsplitn(2, ':');
+            let type_name = split.next().unwrap().trim();
+            let column_name = match split.next() {
+                None => {
+                    return Err(Error::from(format!(
+                        "Error: column type declaration '{}' is not in 'name:type' format.",
+                        declaration
+                    )))
+                }
+                Some(name) => name.trim(),
+            };
+            let field_type = match type_map.get(type_name) {
+                None => {
+                    let mut type_names: Vec<&String> = type_map.keys().collect();
+                    type_names.sort();
+                    return Err(Error::from(format!(
+                        "Error: unknown type '{}' for column '{}'; known types: {:?}.",
+                        type_name, column_name, type_names
+                    )));
+                }
+                Some(FieldType::MultiDimDoubleArray) => {
+                    return Err(Error::from(format!(
+                        "Error: type '{}' for column '{}' is not supported for csv input.",
+                        type_name, column_name
+                    )))
+                }
+                Some(field_type) => *field_type,
+            };
+            schema.push((column_name.to_string(), field_type));
+        }
+    }
+    Ok(schema)
+}
+
 pub fn guess_delimiter(row: &str, possible_delimiters: &[u8]) -> u8 {
     assert_ne!(possible_delimiters.len(), 0);
 
@@ -85,9 +134,12 @@ pub fn guess_common_type(field_types: &[FieldType]) -> FieldType {
         }
 
         let field_type = type_upconvert(field_type);
-        guess = match field_type {
-            FieldType::Double => FieldType::Double,
-            FieldType::Long => guess, // at this point guess can be only long or double, and we want double to take precedence over long
+        guess = match (field_type, guess) {
+            // booleans don't mix with numbers
+            (FieldType::Boolean, FieldType::Boolean) => FieldType::Boolean,
+            (FieldType::Boolean, _) | (_, FieldType::Boolean) => return FieldType::String,
+            (FieldType::Double, _) => FieldType::Double,
+            (FieldType::Long, _) => guess, // at this point guess can be only long or double, and we want double to take precedence over long
             _ => return FieldType::String,
         }
     }
@@ -97,7 +149,7 @@ pub fn guess_common_type(field_types: &[FieldType]) -> FieldType {
 
 fn type_upconvert(field_type: FieldType) -> FieldType {
     match field_type {
-        FieldType::Boolean => FieldType::String,
+        FieldType::Boolean => FieldType::Boolean,
         FieldType::ByteBuf => FieldType::String,
         FieldType::String => FieldType::String,
         FieldType::Byte | FieldType::Char | FieldType::Short | FieldType::Int | FieldType::Long => {
@@ -108,7 +160,7 @@ fn type_upconvert(field_type: FieldType) -> FieldType {
     }
 }
 
-/// current limitations: only long, double, and string; rust format only
+/// current limitations: only long, double, boolean, and string; rust format only
 pub fn guess_type(str: &str) -> FieldType {
     if str.parse::<i64>().is_ok() {
         return FieldType::Long;
@@ -116,13 +168,18 @@ pub fn guess_type(str: &str) -> FieldType {
     if str.parse::<f64>().is_ok() {
         return FieldType::Double;
     }
+    if str.parse::<bool>().is_ok() {
+        return FieldType::Boolean;
+    }
     FieldType::String
 }
 
 #[cfg(test)]
 mod tests {
     use crate::chopper::types::FieldType;
-    use crate::util::csv_util::{guess_has_header, guess_type};
+    use crate::util::csv_util::{
+        guess_common_type, guess_has_header, guess_type, parse_into_schema,
+    };
 
     #[test]
     fn test_guess_has_header() {
@@ -168,5 +225,53 @@ mod tests {
         assert_eq!(guess_type("zzz"), FieldType::String);
         assert_eq!(guess_type("123"), FieldType::Long);
         assert_eq!(guess_type("123.4"), FieldType::Double);
+        assert_eq!(guess_type("true"), FieldType::Boolean);
+    }
+
+    #[test]
+    fn test_guess_common_type() {
+        assert_eq!(guess_common_type(&[]), FieldType::String);
+        assert_eq!(
+            guess_common_type(&[FieldType::Long, FieldType::Long]),
+            FieldType::Long
+        );
+        assert_eq!(
+            guess_common_type(&[FieldType::Long, FieldType::Double]),
+            FieldType::Double
+        );
+        assert_eq!(
+            guess_common_type(&[FieldType::Boolean, FieldType::Boolean]),
+            FieldType::Boolean
+        );
+        assert_eq!(
+            guess_common_type(&[FieldType::Boolean, FieldType::Long]),
+            FieldType::String
+        );
+        assert_eq!(
+            guess_common_type(&[FieldType::Double, FieldType::String]),
+            FieldType::String
+        );
+    }
+
+    #[test]
+    fn test_parse_into_schema() {
+        assert_eq!(
+            parse_into_schema("price:f64, qty:i64,sym:utf8str").unwrap(),
+            vec![
+                ("price".to_string(), FieldType::Double),
+                ("qty".to_string(), FieldType::Long),
+                ("sym".to_string(), FieldType::String),
+            ]
+        );
+        assert_eq!(
+            parse_into_schema("# comment\na:b:bool\n\nc:u16char,\n").unwrap(),
+            vec![
+                ("a:b".to_string(), FieldType::Boolean),
+                ("c".to_string(), FieldType::Char),
+            ]
+        );
+        assert!(parse_into_schema("price").is_err());
+        assert!(parse_into_schema("price:decimal").is_err());
+        assert!(parse_into_schema("price:[f64]xD").is_err());
     }
 }
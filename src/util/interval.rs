@@ -0,0 +1,177 @@
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+use chrono::{Datelike, Duration, NaiveDate};
+
+use crate::chopper::error::{ChopperResult, Error};
+use crate::chopper::types::Nanos;
+use crate::util::tz::ChopperTz;
+
+const NANOS_PER_DAY: Nanos = 86_400_000_000_000;
+
+/// width of a time bucket
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interval {
+    /// buckets are aligned to unix epoch
+    Nanos(Nanos),
+    /// buckets start at midnight of a calendar day in the given timezone and are aligned to
+    /// day 1 of common era, so that e.g. 7d buckets always start on a monday
+    Days(u32),
+}
+
+impl FromStr for Interval {
+    type Err = Error;
+
+    /// positive number followed by one of ns, us, ms, s, m, h, d
+    fn from_str(interval: &str) -> ChopperResult<Interval> {
+        let digits_end = interval
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(interval.len());
+        let (value, units) = interval.split_at(digits_end);
+        let invalid = || Error::from(format!("Interval -- {} is not a valid interval", interval));
+        let value = value.parse::<Nanos>().map_err(|_| invalid())?;
+        if value == 0 {
+            return Err(invalid());
+        }
+        let nanos_per_unit: Nanos = match units {
+            "ns" => 1,
+            "us" => 1_000,
+            "ms" => 1_000_000,
+            "s" => 1_000_000_000,
+            "m" => 60_000_000_000,
+            "h" => 3_600_000_000_000,
+            "d" => NANOS_PER_DAY,
+            _ => return Err(invalid()),
+        };
+        // days are limited to what fits into nanos as well, so that as_nanos cannot overflow
+        let nanos = value
+            .checked_mul(nanos_per_unit)
+            .ok_or_else(|| Error::from(format!("Interval -- {} is too large", interval)))?;
+        match units {
+            "d" => Ok(Interval::Days(u32::try_from(value)?)),
+            _ => Ok(Interval::Nanos(nanos)),
+        }
+    }
+}
+
+impl Interval {
+    /// length in nanos, with days taken as 24 hours
+    pub fn as_nanos(&self) -> Nanos {
+        match self {
+            Interval::Nanos(nanos) => *nanos,
+            Interval::Days(days) => *days as Nanos * NANOS_PER_DAY,
+        }
+    }
+
+    pub(crate) fn bucket_start(
+        &self,
+        timestamp: Nanos,
+        timezone: &ChopperTz,
+    ) -> ChopperResult<Nanos> {
+        match self {
+            Interval::Nanos(nanos) => Ok(timestamp - timestamp % nanos),
+            Interval::Days(days) => {
+                let date = timezone.timestamp(timestamp)?.date_naive();
+                // day 1 of common era is a monday
+                let offset = (date.num_days_from_ce() - 1).rem_euclid(*days as i32);
+                local_midnight(date - Duration::days(offset as i64), timezone)
+            }
+        }
+    }
+
+    pub(crate) fn next_bucket_start(
+        &self,
+        bucket_start: Nanos,
+        timezone: &ChopperTz,
+    ) -> ChopperResult<Nanos> {
+        match self {
+            Interval::Nanos(nanos) => bucket_start
+                .checked_add(*nanos)
+                .ok_or_else(|| Error::from(format!("Interval -- {}ns is too large", nanos))),
+            Interval::Days(days) => {
+                let date = timezone.timestamp(bucket_start)?.date_naive();
+                local_midnight(date + Duration::days(*days as i64), timezone)
+            }
+        }
+    }
+}
+
+fn local_midnight(date: NaiveDate, timezone: &ChopperTz) -> ChopperResult<Nanos> {
+    let midnight = timezone.start_of_day(date)?;
+    u64::try_from(midnight.timestamp())
+        .ok()
+        .and_then(|seconds| seconds.checked_mul(1_000_000_000))
+        .ok_or_else(|| Error::from(format!("Interval -- {} is out of range", date)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use chrono::{Datelike, Weekday};
+    use chrono_tz::America::{New_York, Santiago};
+
+    use crate::chopper::types::Nanos;
+    use crate::util::interval::Interval;
+    use crate::util::tz::ChopperTz;
+
+    const SECOND: Nanos = 1_000_000_000;
+
+    #[test]
+    fn test_interval_from_str() {
+        assert_eq!(
+            Interval::from_str("5m").unwrap(),
+            Interval::Nanos(300 * SECOND)
+        );
+        assert_eq!(Interval::from_str("2d").unwrap(), Interval::Days(2));
+        assert!(Interval::from_str("0s").is_err());
+        assert!(Interval::from_str("1y").is_err());
+        assert!(Interval::from_str("300000d").is_err());
+        assert!(Interval::from_str("6000000h").is_err());
+    }
+
+    #[test]
+    fn test_week_buckets_start_on_monday() {
+        let timezone = ChopperTz::from(New_York);
+        let interval = Interval::Days(7);
+        // noon utc from friday 2020-03-06 to thursday 2020-03-12, around sunday 2020-03-08
+        for day in 2..9 {
+            let timestamp = (1_583_323_200 + day * 86_400) * SECOND;
+            let start = interval.bucket_start(timestamp, &timezone).unwrap();
+            let start = timezone.timestamp(start).unwrap();
+            assert_eq!(start.weekday(), Weekday::Mon);
+            assert!(start.timestamp() as Nanos * SECOND <= timestamp);
+        }
+    }
+
+    #[test]
+    fn test_next_bucket_start_overflow() {
+        let timezone = ChopperTz::from(New_York);
+        let interval = Interval::from_str("5000000h").unwrap();
+        let start = interval.bucket_start(u64::MAX - 1, &timezone).unwrap();
+        assert!(interval.next_bucket_start(start, &timezone).is_err());
+    }
+
+    #[test]
+    fn test_day_buckets_around_midnight_dst() {
+        let timezone = ChopperTz::from(Santiago);
+        let interval = Interval::Days(1);
+        // clocks go from 00:00 to 01:00 on 2020-09-06, so that day starts at 01:00 local
+        let day_start = 1_599_364_800 * SECOND;
+        let noon = day_start + 11 * 3600 * SECOND;
+        assert_eq!(interval.bucket_start(noon, &timezone).unwrap(), day_start);
+        let previous_noon = noon - 24 * 3600 * SECOND;
+        let start = interval.bucket_start(previous_noon, &timezone).unwrap();
+        assert_eq!(
+            interval.next_bucket_start(start, &timezone).unwrap(),
+            day_start
+        );
+    }
+
+    #[test]
+    fn test_bucket_before_1970() {
+        // new york midnight before the epoch cannot be a timestamp
+        let timezone = ChopperTz::from(New_York);
+        assert!(Interval::Days(1).bucket_start(0, &timezone).is_err());
+    }
+}
@@ -1,7 +1,10 @@
+pub mod arrow_util;
+pub mod column_map;
 pub mod csv_util;
 pub mod dc_factory;
 pub mod dc_util;
 pub mod file;
+pub mod interval;
 pub mod path;
 pub mod reader;
 pub mod remove_multiple;
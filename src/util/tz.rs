@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use std::env;
 
-use chrono::{DateTime, LocalResult, NaiveDateTime, TimeZone};
+use chrono::{DateTime, Duration, LocalResult, NaiveDate, NaiveDateTime, TimeZone};
 use chrono_tz::Tz;
 
 use crate::chopper::error::ChopperResult;
@@ -62,6 +62,24 @@ impl ChopperTz {
         }
     }
 
+    /// earliest time on given local date; that is midnight, except in zones where daylight
+    /// saving time starts at midnight, where it is the end of the gap
+    pub fn start_of_day(&self, date: NaiveDate) -> ChopperResult<DateTime<Tz>> {
+        let midnight = date.and_hms_opt(0, 0, 0).unwrap();
+        let timezone = match self.timezone {
+            None => return Err(TimeZoneMissingForParsing(midnight)),
+            Some(timezone) => timezone,
+        };
+        let mut local = midnight;
+        while local.date() == date {
+            if let Some(t) = timezone.from_local_datetime(&local).earliest() {
+                return Ok(t);
+            }
+            local += Duration::minutes(1);
+        }
+        Err(TimeConversion(midnight, timezone))
+    }
+
     pub fn timestamp(&self, nanoseconds: u64) -> ChopperResult<DateTime<Tz>> {
         match self.timezone {
             None => Err(TimeZoneMissingForOutput(nanoseconds)),
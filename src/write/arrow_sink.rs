@@ -0,0 +1,211 @@
+use std::io::Write;
+
+use arrow::datatypes::SchemaRef;
+use arrow::ipc::writer::{FileWriter, StreamWriter};
+use arrow::record_batch::RecordBatch;
+
+use crate::chopper::error::ChopperResult;
+use crate::chopper::sink::{DataSink, DynHeaderSink};
+use crate::chopper::types::{Header, Row};
+use crate::util::arrow_util;
+
+const ROWS_PER_BATCH: usize = 8192;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArrowFormat {
+    /// random access file format, also known as feather v2
+    File,
+    Stream,
+}
+
+enum IpcWriter<W: Write> {
+    File(FileWriter<W>),
+    Stream(StreamWriter<W>),
+}
+
+impl<W: Write> IpcWriter<W> {
+    fn write(&mut self, batch: &RecordBatch) -> ChopperResult<()> {
+        match self {
+            IpcWriter::File(w) => w.write(batch)?,
+            IpcWriter::Stream(w) => w.write(batch)?,
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> ChopperResult<()> {
+        match self {
+            IpcWriter::File(w) => w.flush()?,
+            IpcWriter::Stream(w) => w.flush()?,
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> ChopperResult<()> {
+        match self {
+            IpcWriter::File(w) => w.finish()?,
+            IpcWriter::Stream(w) => w.finish()?,
+        }
+        Ok(())
+    }
+}
+
+/// timestamps are written into a leading column of utc nanos timestamp type;
+/// same as with parquet, flush writes the file footer or end of stream marker, and dropping
+/// the sink without flush does so too, but loses errors
+pub struct ArrowSink<W: 'static + Write> {
+    format: ArrowFormat,
+    header: Header,
+    rows: Vec<Row>,
+    /// held until the first batch, since tensor shapes are only known from the data
+    output: Option<W>,
+    writer: Option<(IpcWriter<W>, SchemaRef)>,
+}
+
+impl<W: 'static + Write> ArrowSink<W> {
+    pub fn new(output: W, format: ArrowFormat) -> Self {
+        ArrowSink {
+            format,
+            header: Header::new(Vec::new(), Vec::new()),
+            rows: Vec::new(),
+            output: Some(output),
+            writer: None,
+        }
+    }
+
+    fn write_batch(&mut self) -> ChopperResult<()> {
+        if self.writer.is_none() {
+            let schema = arrow_util::schema_from_header(&self.header, &self.rows);
+            let output = self.output.take().unwrap();
+            let writer = match self.format {
+                ArrowFormat::File => IpcWriter::File(FileWriter::try_new(output, &schema)?),
+                ArrowFormat::Stream => IpcWriter::Stream(StreamWriter::try_new(output, &schema)?),
+            };
+            self.writer = Some((writer, schema));
+        }
+        if self.rows.is_empty() {
+            return Ok(());
+        }
+
+        let (writer, schema) = self.writer.as_mut().unwrap();
+        let batch = arrow_util::rows_to_record_batch(schema, &self.rows)?;
+        writer.write(&batch)?;
+        self.rows.clear();
+        Ok(())
+    }
+
+    fn close(&mut self) -> ChopperResult<()> {
+        if self.output.is_none() && self.writer.is_none() {
+            // already closed
+            return Ok(());
+        }
+        self.write_batch()?;
+        if let Some((mut writer, _)) = self.writer.take() {
+            writer.finish()?;
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: 'static + Write> DynHeaderSink for ArrowSink<W> {
+    fn process_header(
+        mut self: Box<Self>,
+        header: &mut Header,
+    ) -> ChopperResult<Box<dyn DataSink>> {
+        self.header = header.clone();
+        Ok(self)
+    }
+}
+
+impl<W: 'static + Write> DataSink for ArrowSink<W> {
+    fn write_row(&mut self, io_rows: &mut Vec<Row>) -> ChopperResult<()> {
+        self.rows.append(io_rows);
+        if self.rows.len() >= ROWS_PER_BATCH {
+            self.write_batch()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self, _io_rows: &mut Vec<Row>) -> ChopperResult<()> {
+        self.close()
+    }
+}
+
+impl<W: 'static + Write> Drop for ArrowSink<W> {
+    fn drop(&mut self) {
+        // only if flush was never called; same as with BufWriter, there is no way to
+        // report errors from here
+        let _ = self.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs::File;
+    use std::io::BufWriter;
+
+    use ndarray::ArrayD;
+
+    use crate::chopper::sink::DynHeaderSink;
+    use crate::chopper::types::{FieldType, FieldValue, Header, Row};
+    use crate::source::arrow_source::ArrowSource;
+    use crate::source::columnar_input_config::ColumnarInputConfig;
+    use crate::source::source::Source;
+    use crate::write::arrow_sink::{ArrowFormat, ArrowSink};
+
+    fn round_trip(format: ArrowFormat, file_name: &str) {
+        let mut header = Header::new(
+            vec!["px".to_string(), "book".to_string()],
+            vec![FieldType::Float, FieldType::MultiDimDoubleArray],
+        );
+        let rows: Vec<Row> = (0..10u64)
+            .map(|i| Row {
+                timestamp: 1_600_000_000_000_000_000 + i,
+                field_values: vec![
+                    FieldValue::Float(i as f32),
+                    match i % 4 {
+                        0 => FieldValue::None,
+                        _ => FieldValue::MultiDimDoubleArray(
+                            ArrayD::from_shape_vec(vec![2, 2], vec![i as f64; 4]).unwrap(),
+                        ),
+                    },
+                ],
+            })
+            .collect();
+
+        let path = env::temp_dir().join(file_name);
+        {
+            let output = BufWriter::new(File::create(&path).unwrap());
+            let sink = Box::new(ArrowSink::new(output, format));
+            let mut sink = sink.process_header(&mut header).unwrap();
+            for row in &rows {
+                sink.write_row(&mut vec![row.clone()]).unwrap();
+            }
+            sink.flush(&mut Vec::new()).unwrap();
+        }
+
+        let config = ColumnarInputConfig::new().hide_timestamp_column(true);
+        let input = File::open(&path).unwrap();
+        let mut source = match format {
+            ArrowFormat::File => ArrowSource::new_file(input, &config).unwrap(),
+            ArrowFormat::Stream => ArrowSource::new_stream(input, &config).unwrap(),
+        };
+        assert_eq!(source.header(), &header);
+        let mut actual: Vec<Row> = Vec::new();
+        while let Some(row) = source.next_row().unwrap() {
+            actual.push(row);
+        }
+        assert_eq!(actual, rows);
+    }
+
+    #[test]
+    fn test_round_trip_file() {
+        round_trip(ArrowFormat::File, "chopper_arrow_sink_round_trip.arrow");
+    }
+
+    #[test]
+    fn test_round_trip_stream() {
+        round_trip(ArrowFormat::Stream, "chopper_arrow_sink_round_trip.arrows");
+    }
+}
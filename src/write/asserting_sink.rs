@@ -34,7 +34,7 @@ impl DataSink for AssertingSink {
         Ok(())
     }
 
-    fn flush(&mut self) -> ChopperResult<()> {
+    fn flush(&mut self, _io_rows: &mut Vec<Row>) -> ChopperResult<()> {
         assert_eq!(self.rows.len(), self.current_row);
         Ok(())
     }
@@ -0,0 +1,74 @@
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+use crate::chopper::error::ChopperResult;
+use crate::chopper::sink::{DataSink, DynHeaderSink};
+use crate::chopper::types::{Header, Row};
+use crate::compress::compress::CompressedWriter;
+
+/// compressed writer shared by the sink writing to it and the CompressedSink finishing it
+#[derive(Clone)]
+pub struct SharedCompressedWriter(Arc<Mutex<CompressedWriter<Box<dyn Write + Send>>>>);
+
+impl SharedCompressedWriter {
+    pub fn new(writer: CompressedWriter<Box<dyn Write + Send>>) -> Self {
+        SharedCompressedWriter(Arc::new(Mutex::new(writer)))
+    }
+
+    fn finish(&self) -> io::Result<()> {
+        self.0.lock().unwrap().finish()
+    }
+}
+
+impl Write for SharedCompressedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.lock().unwrap().flush()
+    }
+}
+
+/// wraps a sink writing compressed output, so that the compressed stream is finished when
+/// the sink is flushed, and errors writing its trailer are returned
+pub struct CompressedSinkConfig {
+    sink: Box<dyn DynHeaderSink>,
+    writer: SharedCompressedWriter,
+}
+
+impl CompressedSinkConfig {
+    pub fn new(sink: Box<dyn DynHeaderSink>, writer: SharedCompressedWriter) -> Self {
+        CompressedSinkConfig { sink, writer }
+    }
+}
+
+pub struct CompressedSink {
+    sink: Box<dyn DataSink>,
+    writer: SharedCompressedWriter,
+}
+
+impl DynHeaderSink for CompressedSinkConfig {
+    fn process_header(self: Box<Self>, header: &mut Header) -> ChopperResult<Box<dyn DataSink>> {
+        Ok(Box::new(CompressedSink {
+            sink: self.sink.process_header(header)?,
+            writer: self.writer,
+        }))
+    }
+
+    fn name(&self) -> String {
+        self.sink.name()
+    }
+}
+
+impl DataSink for CompressedSink {
+    fn write_row(&mut self, io_rows: &mut Vec<Row>) -> ChopperResult<()> {
+        self.sink.write_row(io_rows)
+    }
+
+    fn flush(&mut self, io_rows: &mut Vec<Row>) -> ChopperResult<()> {
+        self.sink.flush(io_rows)?;
+        self.writer.finish()?;
+        Ok(())
+    }
+}
@@ -1,7 +1,15 @@
+use std::io::Write;
+
+use clap::arg_enum;
+
+use crate::chopper::error::ChopperResult;
+use crate::chopper::types::Nanos;
 use crate::util::timestamp_units::TimestampUnits;
 use crate::util::tz::ChopperTz;
 
 pub static OUTPUT_DELIMITER_DEFAULT: &str = ",";
+pub static OUTPUT_QUOTE_DEFAULT: u8 = b'"';
+pub static OUTPUT_LINE_TERMINATOR_DEFAULT: &str = "\n";
 
 #[derive(Clone, Copy)]
 pub enum TimestampStyle {
@@ -9,9 +17,69 @@ pub enum TimestampStyle {
     HumanReadable,
 }
 
+impl TimestampStyle {
+    pub fn default_time_col_name(&self, units: TimestampUnits) -> String {
+        match self {
+            TimestampStyle::Epoch => {
+                let base_name = "timestamp".to_string();
+                base_name + units.to_suffix_str()
+            }
+            TimestampStyle::HumanReadable => "time".to_string(),
+        }
+    }
+
+    /// returns true if written timestamp is a number
+    pub fn write_timestamp<W: Write>(
+        &self,
+        writer: &mut W,
+        timestamp: Nanos,
+        units: TimestampUnits,
+        timezone: &ChopperTz,
+    ) -> ChopperResult<bool> {
+        match self {
+            TimestampStyle::Epoch => {
+                let time = match units {
+                    TimestampUnits::Seconds => timestamp / 1_000_000_000,
+                    TimestampUnits::Millis => timestamp / 1_000_000,
+                    TimestampUnits::Micros => timestamp / 1_000,
+                    TimestampUnits::Nanos => timestamp,
+                };
+                write!(writer, "{}", time)?;
+                Ok(true)
+            }
+            TimestampStyle::HumanReadable => {
+                let format = match units {
+                    TimestampUnits::Seconds => "%Y-%m-%dT%H:%M:%S%:z",
+                    TimestampUnits::Millis => "%Y-%m-%dT%H:%M:%S%.3f%:z",
+                    TimestampUnits::Micros => "%Y-%m-%dT%H:%M:%S%.6f%:z",
+                    TimestampUnits::Nanos => "%Y-%m-%dT%H:%M:%S%.9f%:z",
+                };
+                let time = timezone.timestamp(timestamp)?;
+                write!(writer, "{}", time.format(format))?;
+                Ok(false)
+            }
+        }
+    }
+}
+
+arg_enum! {
+    /// when to put values in quotes; values that need quoting, i.e. ones containing delimiter,
+    /// quote or line break characters, are always quoted, and minimal quotes only those,
+    /// while non-numeric quotes everything except numbers
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum QuoteStyle {
+        Minimal,
+        All,
+        NonNumeric,
+    }
+}
+
 #[derive(Clone)]
 pub struct CSVOutputConfig {
     delimiter: String,
+    quote: u8,
+    quote_style: QuoteStyle,
+    line_terminator: String,
     print_time_col: bool,
     time_col_name: String,
     time_col_style: TimestampStyle,
@@ -29,19 +97,15 @@ impl CSVOutputConfig {
         timezone: ChopperTz,
     ) -> Self {
         let time_col_name = match time_col_name {
-            None => match time_col_style {
-                TimestampStyle::Epoch => {
-                    let base_name = "timestamp".to_string();
-                    let units = time_col_units.to_suffix_str();
-                    base_name + units
-                }
-                TimestampStyle::HumanReadable => "time".to_string(),
-            },
+            None => time_col_style.default_time_col_name(time_col_units),
             Some(name) => name,
         };
 
         CSVOutputConfig {
             delimiter: delimiter.to_string(),
+            quote: OUTPUT_QUOTE_DEFAULT,
+            quote_style: QuoteStyle::Minimal,
+            line_terminator: OUTPUT_LINE_TERMINATOR_DEFAULT.to_string(),
             print_time_col,
             time_col_name,
             time_col_style,
@@ -61,10 +125,37 @@ impl CSVOutputConfig {
         )
     }
 
+    pub fn with_quote(mut self, quote: u8) -> Self {
+        self.quote = quote;
+        self
+    }
+
+    pub fn with_quote_style(mut self, quote_style: QuoteStyle) -> Self {
+        self.quote_style = quote_style;
+        self
+    }
+
+    pub fn with_line_terminator(mut self, line_terminator: &str) -> Self {
+        self.line_terminator = line_terminator.to_string();
+        self
+    }
+
     pub fn delimiter(&self) -> &String {
         &self.delimiter
     }
 
+    pub fn quote(&self) -> u8 {
+        self.quote
+    }
+
+    pub fn quote_style(&self) -> QuoteStyle {
+        self.quote_style
+    }
+
+    pub fn line_terminator(&self) -> &String {
+        &self.line_terminator
+    }
+
     pub fn print_time_col(&self) -> bool {
         self.print_time_col
     }
@@ -3,12 +3,14 @@ use std::io::Write;
 use crate::chopper::error::ChopperResult;
 use crate::chopper::sink::{DataSink, DynHeaderSink, TypedHeaderSink};
 use crate::chopper::types::{FieldValue, Header, Row};
-use crate::util::timestamp_units::TimestampUnits;
-use crate::write::csv_output_config::{CSVOutputConfig, TimestampStyle};
+use crate::write::csv_output_config::{CSVOutputConfig, QuoteStyle};
 
 pub struct CSVSink<W: 'static + Write> {
     writer: W,
     csv_output_config: CSVOutputConfig,
+    /// reused for formatting values before they are quoted/escaped
+    field_buf: Vec<u8>,
+    write_header: bool,
 }
 
 impl<W: 'static + Write> CSVSink<W> {
@@ -16,29 +18,86 @@ impl<W: 'static + Write> CSVSink<W> {
         Ok(CSVSink {
             writer,
             csv_output_config,
+            field_buf: Vec::new(),
+            write_header: true,
         })
     }
 
+    /// for appending to a file that already has a header
+    pub fn without_header(mut self) -> Self {
+        self.write_header = false;
+        self
+    }
+
     fn write_csv_header(&mut self, header: &mut Header) -> ChopperResult<()> {
-        let writer = &mut self.writer;
-        let field_name = header.field_names().clone();
-        let mut first = true;
+        if !self.write_header {
+            return Ok(());
+        }
+        let config = &self.csv_output_config;
+        let mut first_col = true;
+
+        if config.print_time_col() {
+            let name = config.time_col_name().as_bytes();
+            Self::write_field(&mut self.writer, config, name, false, &mut first_col)?;
+        }
+        for name in header.field_names() {
+            let name = name.as_bytes();
+            Self::write_field(&mut self.writer, config, name, false, &mut first_col)?;
+        }
+        self.writer.write_all(config.line_terminator().as_bytes())?;
+        Ok(())
+    }
 
-        if self.csv_output_config.print_time_col() {
-            write!(writer, "{},", self.csv_output_config.time_col_name())?;
+    /// writes delimiter if needed, followed by the value quoted and escaped as per rfc 4180
+    fn write_field(
+        writer: &mut W,
+        config: &CSVOutputConfig,
+        value: &[u8],
+        is_numeric: bool,
+        first_col: &mut bool,
+    ) -> ChopperResult<()> {
+        if *first_col {
+            *first_col = false;
+        } else {
+            writer.write_all(config.delimiter().as_bytes())?;
         }
-        for name in field_name {
-            if first {
-                write!(writer, "{}", name)?;
-                first = false;
+
+        let quote = config.quote();
+        let needs_quotes = match config.quote_style() {
+            QuoteStyle::All => true,
+            QuoteStyle::NonNumeric if !is_numeric => true,
+            _ => Self::has_special_chars(value, config.delimiter().as_bytes(), quote),
+        };
+        if !needs_quotes {
+            writer.write_all(value)?;
+            return Ok(());
+        }
+
+        writer.write_all(&[quote])?;
+        let mut first_part = true;
+        for part in value.split(|&c| c == quote) {
+            if first_part {
+                first_part = false;
             } else {
-                write!(writer, ",{}", name)?;
+                // quote inside of a value is escaped by doubling it
+                writer.write_all(&[quote, quote])?;
             }
+            writer.write_all(part)?;
         }
-        write!(writer, "\n")?;
+        writer.write_all(&[quote])?;
         Ok(())
     }
 
+    fn has_special_chars(value: &[u8], delimiter: &[u8], quote: u8) -> bool {
+        if value
+            .iter()
+            .any(|&c| c == quote || c == b'\n' || c == b'\r')
+        {
+            return true;
+        }
+        !delimiter.is_empty() && value.windows(delimiter.len()).any(|w| w == delimiter)
+    }
+
     pub fn inner(self) -> W {
         self.writer
     }
@@ -64,60 +123,64 @@ impl<W: 'static + Write> DynHeaderSink for CSVSink<W> {
 impl<W: 'static + Write> DataSink for CSVSink<W> {
     fn write_row(&mut self, io_rows: &mut Vec<Row>) -> ChopperResult<()> {
         let row = io_rows.get(0).unwrap();
+        let config = &self.csv_output_config;
+        let buf = &mut self.field_buf;
 
         let mut first_col = true;
-        if self.csv_output_config.print_time_col() {
-            let time = match self.csv_output_config.time_col_style() {
-                TimestampStyle::Epoch => {
-                    let time = match self.csv_output_config.time_col_units() {
-                        TimestampUnits::Seconds => row.timestamp / 1_000_000_000,
-                        TimestampUnits::Millis => row.timestamp / 1_000_000,
-                        TimestampUnits::Micros => row.timestamp / 1_000,
-                        TimestampUnits::Nanos => row.timestamp,
-                    };
-                    time.to_string()
-                }
-                TimestampStyle::HumanReadable => {
-                    let format = match self.csv_output_config.time_col_units() {
-                        TimestampUnits::Seconds => "%Y-%m-%dT%H:%M:%S%:z",
-                        TimestampUnits::Millis => "%Y-%m-%dT%H:%M:%S%.3f%:z",
-                        TimestampUnits::Micros => "%Y-%m-%dT%H:%M:%S%.6f%:z",
-                        TimestampUnits::Nanos => "%Y-%m-%dT%H:%M:%S%.9f%:z",
-                    };
-                    let time = self.csv_output_config.timezone().timestamp(row.timestamp)?;
-                    time.format(format).to_string()
-                }
-            };
-
-            write!(self.writer, "{}", time)?;
-            first_col = false;
+        if config.print_time_col() {
+            buf.clear();
+            let is_numeric = config.time_col_style().write_timestamp(
+                buf,
+                row.timestamp,
+                config.time_col_units(),
+                config.timezone(),
+            )?;
+            Self::write_field(&mut self.writer, config, buf, is_numeric, &mut first_col)?;
         }
-        let field_values = &row.field_values;
-        let delimiter = self.csv_output_config.delimiter();
-        for value in field_values {
-            if first_col {
-                first_col = false;
-            } else {
-                write!(self.writer, "{}", delimiter)?;
-            }
 
-            match value {
+        for value in &row.field_values {
+            buf.clear();
+            let is_numeric = match value {
                 FieldValue::Boolean(x) => {
-                    write!(self.writer, "{}", if *x { "true" } else { "false" })?
+                    buf.extend_from_slice(if *x { b"true" } else { b"false" });
+                    false
+                }
+                FieldValue::Byte(x) => {
+                    write!(buf, "{}", x)?;
+                    true
+                }
+                FieldValue::ByteBuf(x) => {
+                    write!(buf, "ByteBuf[len={}]", x.len())?;
+                    false
+                }
+                FieldValue::Char(x) => {
+                    write!(buf, "{}", x)?;
+                    true
                 }
-                FieldValue::Byte(x) => write!(self.writer, "{}", x)?,
-                FieldValue::ByteBuf(x) => write!(self.writer, "ByteBuf[len={}]", x.len())?,
-                FieldValue::Char(x) => write!(self.writer, "{}", x)?,
                 FieldValue::Double(x) => {
-                    dtoa::write(&mut self.writer, *x)?;
+                    dtoa::write(&mut *buf, *x)?;
+                    true
                 }
                 FieldValue::Float(x) => {
-                    dtoa::write(&mut self.writer, *x)?;
+                    dtoa::write(&mut *buf, *x)?;
+                    true
+                }
+                FieldValue::Int(x) => {
+                    write!(buf, "{}", x)?;
+                    true
+                }
+                FieldValue::Long(x) => {
+                    write!(buf, "{}", x)?;
+                    true
+                }
+                FieldValue::Short(x) => {
+                    write!(buf, "{}", x)?;
+                    true
+                }
+                FieldValue::String(x) => {
+                    buf.extend_from_slice(x.as_bytes());
+                    false
                 }
-                FieldValue::Int(x) => write!(self.writer, "{}", x)?,
-                FieldValue::Long(x) => write!(self.writer, "{}", x)?,
-                FieldValue::Short(x) => write!(self.writer, "{}", x)?,
-                FieldValue::String(x) => write!(self.writer, "{}", x)?,
                 FieldValue::MultiDimDoubleArray(x) => {
                     let dim_str = x
                         .shape()
@@ -125,17 +188,144 @@ impl<W: 'static + Write> DataSink for CSVSink<W> {
                         .map(|d| d.to_string())
                         .collect::<Vec<String>>()
                         .join("x");
-                    write!(self.writer, "MultiDimDoubleArray[{}]", dim_str)?
+                    write!(buf, "MultiDimDoubleArray[{}]", dim_str)?;
+                    false
+                }
+                FieldValue::None => {
+                    // nothing is written for missing values, not even the quotes
+                    if first_col {
+                        first_col = false;
+                    } else {
+                        self.writer.write_all(config.delimiter().as_bytes())?;
+                    }
+                    continue;
                 }
-                FieldValue::None => (),
             };
+            Self::write_field(&mut self.writer, config, buf, is_numeric, &mut first_col)?;
         }
-        write!(self.writer, "\n")?;
+        self.writer.write_all(config.line_terminator().as_bytes())?;
         Ok(())
     }
 
-    fn flush(&mut self) -> ChopperResult<()> {
+    fn flush(&mut self, _io_rows: &mut Vec<Row>) -> ChopperResult<()> {
         self.writer.flush()?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read};
+
+    use crate::chopper::sink::{DataSink, TypedHeaderSink};
+    use crate::chopper::types::{FieldType, FieldValue, Header, Row};
+    use crate::cli::util::YesNoAuto;
+    use crate::source::csv_input_config::CSVInputConfig;
+    use crate::source::csv_source::CSVSource;
+    use crate::source::csv_timestamp_config::{
+        TimestampColConfig, TimestampConfig, TimestampFmtConfig,
+    };
+    use crate::source::source::Source;
+    use crate::util::reader::ChopperBufPreviewer;
+    use crate::util::timestamp_units::TimestampUnits;
+    use crate::util::tz::ChopperTz;
+    use crate::write::csv_output_config::{CSVOutputConfig, QuoteStyle, TimestampStyle};
+    use crate::write::csv_sink::CSVSink;
+
+    fn write(config: CSVOutputConfig, header: &Header, rows: &[Row]) -> String {
+        let sink = CSVSink::new(Vec::new(), config).unwrap();
+        let mut sink = TypedHeaderSink::process_header(sink, &mut header.clone()).unwrap();
+        for row in rows {
+            sink.write_row(&mut vec![row.clone()]).unwrap();
+        }
+        sink.flush(&mut Vec::new()).unwrap();
+        String::from_utf8(sink.inner()).unwrap()
+    }
+
+    fn test_data() -> (Header, Vec<Row>) {
+        let header = Header::new(
+            vec!["a;b".to_string(), "n".to_string(), "c".to_string()],
+            vec![FieldType::String, FieldType::Long, FieldType::String],
+        );
+        let rows = vec![
+            Row {
+                timestamp: 1,
+                field_values: vec![
+                    FieldValue::String("say \"hi\"".to_string()),
+                    FieldValue::Long(5),
+                    FieldValue::String("x;y".to_string()),
+                ],
+            },
+            Row {
+                timestamp: 2,
+                field_values: vec![
+                    FieldValue::String("two\nlines".to_string()),
+                    FieldValue::None,
+                    FieldValue::String("plain".to_string()),
+                ],
+            },
+        ];
+        (header, rows)
+    }
+
+    #[test]
+    fn test_quoting() {
+        let (header, rows) = test_data();
+
+        let config = CSVOutputConfig::new_default();
+        assert_eq!(
+            write(config.clone(), &header, &rows),
+            "timestampNanos,a;b,n,c\n1,\"say \"\"hi\"\"\",5,x;y\n2,\"two\nlines\",,plain\n"
+        );
+
+        let config = CSVOutputConfig::new_default()
+            .with_quote_style(QuoteStyle::NonNumeric)
+            .with_quote(b'\'')
+            .with_line_terminator("\r\n");
+        assert_eq!(
+            write(config, &header, &rows),
+            "'timestampNanos','a;b','n','c'\r\n1,'say \"hi\"',5,'x;y'\r\n2,'two\nlines',,'plain'\r\n"
+        );
+
+        let config = CSVOutputConfig::new(
+            ";",
+            false,
+            None,
+            TimestampStyle::Epoch,
+            TimestampUnits::Nanos,
+            ChopperTz::new_always_fails(),
+        )
+        .with_quote_style(QuoteStyle::All);
+        assert_eq!(
+            write(config, &header, &rows),
+            "\"a;b\";\"n\";\"c\"\n\"say \"\"hi\"\"\";\"5\";\"x;y\"\n\"two\nlines\";;\"plain\"\n"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let (header, rows) = test_data();
+        let output = write(CSVOutputConfig::new_default(), &header, &rows);
+
+        let reader: Box<dyn Read> = Box::new(Cursor::new(output.into_bytes()));
+        let previewer = ChopperBufPreviewer::new(reader).unwrap();
+        let timestamp_config = TimestampConfig::new(
+            TimestampColConfig::Index(0),
+            TimestampFmtConfig::Epoch(TimestampUnits::Nanos),
+            ChopperTz::new_always_fails(),
+        );
+        let csv_input_config = CSVInputConfig::new(timestamp_config)
+            .with_delimiter(Some(","))
+            .unwrap()
+            .with_header(YesNoAuto::Yes)
+            .hide_timestamp_column(true)
+            .with_schema(Some("n:i64"))
+            .unwrap();
+        let mut source = CSVSource::new(previewer, &csv_input_config).unwrap();
+
+        assert_eq!(source.header(), &header);
+        assert_eq!(source.next_row().unwrap().unwrap(), rows[0]);
+        assert_eq!(source.next_row().unwrap().unwrap(), rows[1]);
+        assert!(source.next_row().unwrap().is_none());
+    }
+}
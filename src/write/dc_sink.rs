@@ -163,7 +163,7 @@ impl<W: 'static + Write> DataSink for DCSink<W> {
         Ok(())
     }
 
-    fn flush(&mut self) -> ChopperResult<()> {
+    fn flush(&mut self, _io_rows: &mut Vec<Row>) -> ChopperResult<()> {
         self.writer.flush()?;
         Ok(())
     }
@@ -1,20 +1,71 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+
 use crate::chopper::error::{ChopperResult, Error};
 use crate::chopper::sink::DynHeaderSink;
+use crate::compress::compress::{compress, is_compressed_using_format, CompressionFormat};
 use crate::util::dc_factory::DCFactory;
-use crate::util::path::buf_writer_from_file_path;
+use crate::util::tz::ChopperTz;
+use crate::write::arrow_sink::{ArrowFormat, ArrowSink};
+use crate::write::compressed_sink::{CompressedSinkConfig, SharedCompressedWriter};
 use crate::write::csv_output_config::CSVOutputConfig;
 use crate::write::csv_sink;
+use crate::write::json_output_config::JsonOutputConfig;
+use crate::write::json_sink::JsonSink;
+use crate::write::parquet_sink::ParquetSink;
+use crate::write::partitioned_sink::PartitionedSink;
+
+#[derive(Clone, Copy, PartialEq)]
+enum SinkKind {
+    Csv,
+    Dc,
+    Json,
+    Parquet,
+    Arrow(ArrowFormat),
+}
 
+impl SinkKind {
+    fn from_path(p: &str) -> ChopperResult<Self> {
+        if p.ends_with("csv") {
+            Ok(SinkKind::Csv)
+        } else if p.ends_with("dc") {
+            Ok(SinkKind::Dc)
+        } else if p.ends_with(".jsonl") || p.ends_with(".ndjson") {
+            Ok(SinkKind::Json)
+        } else if p.ends_with(".parquet") {
+            Ok(SinkKind::Parquet)
+        } else if p.ends_with(".arrow") || p.ends_with(".feather") || p.ends_with(".ipc") {
+            Ok(SinkKind::Arrow(ArrowFormat::File))
+        } else if p.ends_with(".arrows") {
+            Ok(SinkKind::Arrow(ArrowFormat::Stream))
+        } else {
+            Err(Error::from(format!("file type -- {} is not supported", p)))
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct OutputFactory {
     csv_output_config: CSVOutputConfig,
+    json_output_config: JsonOutputConfig,
     dc_factory: Option<DCFactory>,
+    output_compression: Option<CompressionFormat>,
+    /// timezone of dates in path templates and max number of open files, if output is
+    /// partitioned
+    partitioning: Option<(ChopperTz, usize)>,
+    /// sinks write nothing and no files are created
+    dry_run: bool,
 }
 
 impl OutputFactory {
     pub fn new() -> OutputFactory {
         OutputFactory {
             csv_output_config: CSVOutputConfig::new_default(),
+            json_output_config: JsonOutputConfig::new_default(),
             dc_factory: None,
+            output_compression: None,
+            partitioning: None,
+            dry_run: false,
         }
     }
 
@@ -28,37 +79,183 @@ impl OutputFactory {
         self
     }
 
+    pub fn with_json_output_config(mut self, json_output_config: JsonOutputConfig) -> Self {
+        self.json_output_config = json_output_config;
+        self
+    }
+
+    pub fn with_output_compression(
+        mut self,
+        output_compression: Option<CompressionFormat>,
+    ) -> Self {
+        self.output_compression = output_compression;
+        self
+    }
+
+    /// output is then a path template, see PartitionedSink
+    pub fn with_partitioning(mut self, timezone: ChopperTz, max_open_files: usize) -> Self {
+        self.partitioning = Some((timezone, max_open_files));
+        self
+    }
+
+    /// sinks are created as usual, e.g. to see what a graph looks like, but discard
+    /// everything written to them
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
     pub fn new_header_sink(&self, output: Option<&str>) -> ChopperResult<Box<dyn DynHeaderSink>> {
-        let writer: Box<dyn DynHeaderSink> = match output {
-            Some(p) => {
-                let p = p.to_string();
-                if p.ends_with("csv") {
-                    let writer = buf_writer_from_file_path(&Some(p))?;
-                    Box::new(csv_sink::CSVSink::new(
-                        writer,
-                        self.csv_output_config.clone(),
-                    )?)
-                } else if p.ends_with("dc") {
-                    match &self.dc_factory {
-                        None => return Err(Error::DCFactoryMissing),
-                        Some(dc_factory) => {
-                            let writer = buf_writer_from_file_path(&Some(p))?;
-                            Box::new(dc_factory.new_sink(writer)?)
-                        }
-                    }
-                } else {
-                    return Err(Error::from(format!("file type -- {} is not supported", p)));
+        if let Some((timezone, max_open_files)) = &self.partitioning {
+            let template = match output {
+                None => {
+                    return Err(Error::from(
+                        "PartitionedSink -- output path template is required",
+                    ))
                 }
+                Some(template) => template,
+            };
+            let mut output_factory = self.clone();
+            output_factory.partitioning = None;
+            return PartitionedSink::new(
+                template,
+                timezone.clone(),
+                *max_open_files,
+                output_factory,
+            );
+        }
+        // compression suffix takes precedence over the explicit setting
+        let (compression, format) = match output {
+            None => (self.output_compression, None),
+            Some(p) => match is_compressed_using_format(p) {
+                Some((compression, format)) => (Some(compression), Some(format)),
+                None => (self.output_compression, Some(p.to_string())),
+            },
+        };
+        // sink kind is worked out before the file is created, so that an unsupported
+        // output does not truncate an existing file
+        let kind = match format {
+            None => SinkKind::Csv,
+            Some(p) => SinkKind::from_path(&p)?,
+        };
+        if kind == SinkKind::Dc && self.dc_factory.is_none() {
+            return Err(Error::DCFactoryMissing);
+        }
+        let writer = self.new_writer(output)?;
+        let (writer, compressed_writer) = match compression {
+            None => (writer, None),
+            Some(compression) => {
+                let compressed_writer = SharedCompressedWriter::new(compress(compression, writer)?);
+                let writer: Box<dyn Write + Send> = Box::new(compressed_writer.clone());
+                (writer, Some(compressed_writer))
             }
-            None => {
-                // if none use csv sink
-                let writer = buf_writer_from_file_path(&None)?;
-                Box::new(csv_sink::CSVSink::new(
-                    writer,
-                    self.csv_output_config.clone(),
-                )?)
+        };
+        let writer = BufWriter::new(writer);
+
+        let writer: Box<dyn DynHeaderSink> = match kind {
+            SinkKind::Csv => Box::new(csv_sink::CSVSink::new(
+                writer,
+                self.csv_output_config.clone(),
+            )?),
+            SinkKind::Dc => Box::new(self.dc_factory.as_ref().unwrap().new_sink(writer)?),
+            SinkKind::Json => Box::new(JsonSink::new(writer, self.json_output_config.clone())),
+            SinkKind::Parquet => Box::new(ParquetSink::new(writer)),
+            SinkKind::Arrow(format) => Box::new(ArrowSink::new(writer, format)),
+        };
+        Ok(match compressed_writer {
+            None => writer,
+            Some(compressed_writer) => {
+                Box::new(CompressedSinkConfig::new(writer, compressed_writer))
             }
+        })
+    }
+
+    /// true for uncompressed csv and json lines, which can be added to by simply appending
+    /// rows to the end of the file
+    pub fn is_appendable(&self, output: &str) -> bool {
+        self.output_compression.is_none()
+            && is_compressed_using_format(output).is_none()
+            && (output.ends_with("csv")
+                || output.ends_with(".jsonl")
+                || output.ends_with(".ndjson"))
+    }
+
+    /// same as new_header_sink, except that the file is appended to instead of being
+    /// truncated, and csv header is not written again
+    pub fn new_appending_header_sink(&self, output: &str) -> ChopperResult<Box<dyn DynHeaderSink>> {
+        if !self.is_appendable(output) {
+            return Err(Error::from(format!(
+                "file type -- {} cannot be appended to",
+                output
+            )));
+        }
+        let writer: Box<dyn Write + Send> = match self.dry_run {
+            true => Box::new(io::sink()),
+            false => Box::new(OpenOptions::new().append(true).create(true).open(output)?),
+        };
+        let writer = BufWriter::new(writer);
+        let writer: Box<dyn DynHeaderSink> = match output.ends_with("csv") {
+            true => Box::new(
+                csv_sink::CSVSink::new(writer, self.csv_output_config.clone())?.without_header(),
+            ),
+            false => Box::new(JsonSink::new(writer, self.json_output_config.clone())),
         };
         Ok(writer)
     }
+
+    /// parquet writer needs a Send writer, hence files are opened directly instead of
+    /// going through buf_writer_from_file_path
+    fn new_writer(&self, output: Option<&str>) -> ChopperResult<Box<dyn Write + Send>> {
+        Ok(match output {
+            _ if self.dry_run => Box::new(io::sink()),
+            None => Box::new(io::stdout()),
+            Some(p) => Box::new(File::create(p)?),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Read;
+
+    use flate2::read::GzDecoder;
+
+    use crate::chopper::types::{FieldType, FieldValue, Header, Row};
+
+    use crate::write::factory::OutputFactory;
+
+    #[test]
+    fn test_unsupported_output_is_not_created() {
+        let path = std::env::temp_dir().join("chopper_test_unsupported_output.txt");
+        fs::write(&path, "notes").unwrap();
+        let path = path.to_str().unwrap();
+        assert!(OutputFactory::new().new_header_sink(Some(path)).is_err());
+        assert_eq!(fs::read_to_string(path).unwrap(), "notes");
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_compressed_output_is_finished_on_flush() {
+        let path = std::env::temp_dir().join("chopper_test_finished_output.csv.gz");
+        let path = path.to_str().unwrap();
+        let mut header = Header::new(vec!["a".to_string()], vec![FieldType::Int]);
+        let header_sink = OutputFactory::new().new_header_sink(Some(path)).unwrap();
+        let mut sink = header_sink.process_header(&mut header).unwrap();
+        sink.write_row(&mut vec![Row {
+            timestamp: 1,
+            field_values: vec![FieldValue::Int(1)],
+        }])
+        .unwrap();
+        sink.flush(&mut Vec::new()).unwrap();
+
+        // sink is still alive, so the file can only be complete if flush finished it
+        let mut output = String::new();
+        GzDecoder::new(fs::File::open(path).unwrap())
+            .read_to_string(&mut output)
+            .unwrap();
+        assert_eq!(output, "timestampNanos,a\n1,1\n");
+        drop(sink);
+        fs::remove_file(path).unwrap();
+    }
 }
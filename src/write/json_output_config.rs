@@ -0,0 +1,66 @@
+use crate::util::timestamp_units::TimestampUnits;
+use crate::util::tz::ChopperTz;
+use crate::write::csv_output_config::TimestampStyle;
+
+/// time key settings mean the same as for csv output
+#[derive(Clone)]
+pub struct JsonOutputConfig {
+    print_time_col: bool,
+    time_col_name: String,
+    time_col_style: TimestampStyle,
+    time_col_units: TimestampUnits,
+    timezone: ChopperTz,
+}
+
+impl JsonOutputConfig {
+    pub fn new(
+        print_time_col: bool,
+        time_col_name: Option<String>,
+        time_col_style: TimestampStyle,
+        time_col_units: TimestampUnits,
+        timezone: ChopperTz,
+    ) -> Self {
+        let time_col_name = match time_col_name {
+            None => time_col_style.default_time_col_name(time_col_units),
+            Some(name) => name,
+        };
+
+        JsonOutputConfig {
+            print_time_col,
+            time_col_name,
+            time_col_style,
+            time_col_units,
+            timezone,
+        }
+    }
+
+    pub fn new_default() -> Self {
+        Self::new(
+            true,
+            None,
+            TimestampStyle::Epoch,
+            TimestampUnits::Nanos,
+            ChopperTz::new_always_fails(),
+        )
+    }
+
+    pub fn print_time_col(&self) -> bool {
+        self.print_time_col
+    }
+
+    pub fn time_col_name(&self) -> &String {
+        &self.time_col_name
+    }
+
+    pub fn time_col_style(&self) -> TimestampStyle {
+        self.time_col_style
+    }
+
+    pub fn time_col_units(&self) -> TimestampUnits {
+        self.time_col_units
+    }
+
+    pub fn timezone(&self) -> &ChopperTz {
+        &self.timezone
+    }
+}
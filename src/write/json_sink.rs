@@ -0,0 +1,208 @@
+use std::io::Write;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ndarray::{ArrayViewD, Axis};
+
+use crate::chopper::error::ChopperResult;
+use crate::chopper::sink::{DataSink, DynHeaderSink, TypedHeaderSink};
+use crate::chopper::types::{FieldValue, Header, Row};
+use crate::write::json_output_config::JsonOutputConfig;
+
+/// writes each row as a json object on its own line; ByteBuf values are base64 strings,
+/// MultiDimDoubleArray values are nested arrays, and None as well as NaN/inf are nulls
+pub struct JsonSink<W: 'static + Write> {
+    writer: W,
+    json_output_config: JsonOutputConfig,
+    /// keys are escaped once and reused for every row
+    keys: Vec<Vec<u8>>,
+}
+
+impl<W: 'static + Write> JsonSink<W> {
+    pub fn new(writer: W, json_output_config: JsonOutputConfig) -> Self {
+        JsonSink {
+            writer,
+            json_output_config,
+            keys: Vec::new(),
+        }
+    }
+
+    fn process_keys(&mut self, header: &Header) -> ChopperResult<()> {
+        let mut keys: Vec<Vec<u8>> = Vec::with_capacity(header.field_names().len());
+        for name in header.field_names() {
+            keys.push(serde_json::to_vec(name)?);
+        }
+        self.keys = keys;
+        Ok(())
+    }
+
+    pub fn inner(self) -> W {
+        self.writer
+    }
+
+    fn write_value(writer: &mut W, value: &FieldValue) -> ChopperResult<()> {
+        match value {
+            FieldValue::Boolean(x) => writer.write_all(if *x { b"true" } else { b"false" })?,
+            FieldValue::Byte(x) => write!(writer, "{}", x)?,
+            FieldValue::ByteBuf(x) => write!(writer, "\"{}\"", BASE64.encode(x))?,
+            FieldValue::Char(x) => write!(writer, "{}", x)?,
+            FieldValue::Double(x) => Self::write_float(writer, *x)?,
+            FieldValue::Float(x) => Self::write_float(writer, *x as f64)?,
+            FieldValue::Int(x) => write!(writer, "{}", x)?,
+            FieldValue::Long(x) => write!(writer, "{}", x)?,
+            FieldValue::Short(x) => write!(writer, "{}", x)?,
+            FieldValue::String(x) => serde_json::to_writer(&mut *writer, x)?,
+            FieldValue::MultiDimDoubleArray(x) => Self::write_array(writer, x.view())?,
+            FieldValue::None => writer.write_all(b"null")?,
+        }
+        Ok(())
+    }
+
+    fn write_float(writer: &mut W, value: f64) -> ChopperResult<()> {
+        if value.is_finite() {
+            dtoa::write(writer, value)?;
+        } else {
+            writer.write_all(b"null")?;
+        }
+        Ok(())
+    }
+
+    fn write_array(writer: &mut W, array: ArrayViewD<f64>) -> ChopperResult<()> {
+        if array.ndim() == 0 {
+            return Self::write_float(writer, array.iter().next().copied().unwrap_or(f64::NAN));
+        }
+        writer.write_all(b"[")?;
+        for (i, sub_array) in array.axis_iter(Axis(0)).enumerate() {
+            if i > 0 {
+                writer.write_all(b",")?;
+            }
+            Self::write_array(writer, sub_array)?;
+        }
+        writer.write_all(b"]")?;
+        Ok(())
+    }
+}
+
+impl<W: 'static + Write> TypedHeaderSink<Self> for JsonSink<W> {
+    fn process_header(mut self, header: &mut Header) -> ChopperResult<Self> {
+        self.process_keys(header)?;
+        Ok(self)
+    }
+}
+
+impl<W: 'static + Write> DynHeaderSink for JsonSink<W> {
+    fn process_header(
+        mut self: Box<Self>,
+        header: &mut Header,
+    ) -> ChopperResult<Box<dyn DataSink>> {
+        self.process_keys(header)?;
+        Ok(self)
+    }
+}
+
+impl<W: 'static + Write> DataSink for JsonSink<W> {
+    fn write_row(&mut self, io_rows: &mut Vec<Row>) -> ChopperResult<()> {
+        let row = io_rows.first().unwrap();
+        let config = &self.json_output_config;
+        let writer = &mut self.writer;
+
+        writer.write_all(b"{")?;
+        let mut first_key = true;
+        if config.print_time_col() {
+            serde_json::to_writer(&mut *writer, config.time_col_name())?;
+            writer.write_all(b":")?;
+            // human-readable timestamps need to be quoted, so they are formatted separately
+            let mut buf: Vec<u8> = Vec::new();
+            let is_numeric = config.time_col_style().write_timestamp(
+                &mut buf,
+                row.timestamp,
+                config.time_col_units(),
+                config.timezone(),
+            )?;
+            if is_numeric {
+                writer.write_all(&buf)?;
+            } else {
+                serde_json::to_writer(&mut *writer, std::str::from_utf8(&buf).unwrap())?;
+            }
+            first_key = false;
+        }
+        for (key, value) in self.keys.iter().zip(&row.field_values) {
+            if !first_key {
+                writer.write_all(b",")?;
+            }
+            first_key = false;
+            writer.write_all(key)?;
+            writer.write_all(b":")?;
+            Self::write_value(writer, value)?;
+        }
+        writer.write_all(b"}\n")?;
+        Ok(())
+    }
+
+    fn flush(&mut self, _io_rows: &mut Vec<Row>) -> ChopperResult<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::ArrayD;
+
+    use crate::chopper::sink::{DataSink, TypedHeaderSink};
+    use crate::chopper::types::{FieldType, FieldValue, Header, Row};
+    use crate::util::timestamp_units::TimestampUnits;
+    use crate::util::tz::ChopperTz;
+    use crate::write::csv_output_config::TimestampStyle;
+    use crate::write::json_output_config::JsonOutputConfig;
+    use crate::write::json_sink::JsonSink;
+
+    #[test]
+    fn test_json_sink() {
+        let mut header = Header::new(
+            vec![
+                "s".to_string(),
+                "b".to_string(),
+                "a".to_string(),
+                "d".to_string(),
+                "n".to_string(),
+            ],
+            vec![
+                FieldType::String,
+                FieldType::ByteBuf,
+                FieldType::MultiDimDoubleArray,
+                FieldType::Double,
+                FieldType::Int,
+            ],
+        );
+        let row = Row {
+            timestamp: 1_500_000_000,
+            field_values: vec![
+                FieldValue::String("q\"\n".to_string()),
+                FieldValue::ByteBuf(b"hi!".to_vec()),
+                FieldValue::MultiDimDoubleArray(
+                    ArrayD::from_shape_vec(vec![2, 2], vec![1.0, 2.5, f64::NAN, 4.0]).unwrap(),
+                ),
+                FieldValue::Double(f64::INFINITY),
+                FieldValue::None,
+            ],
+        };
+
+        let config = JsonOutputConfig::new(
+            true,
+            Some("ts".to_string()),
+            TimestampStyle::Epoch,
+            TimestampUnits::Seconds,
+            ChopperTz::new_always_fails(),
+        );
+        let sink = JsonSink::new(Vec::new(), config);
+        let mut sink = TypedHeaderSink::process_header(sink, &mut header).unwrap();
+        sink.write_row(&mut vec![row]).unwrap();
+
+        let output = String::from_utf8(sink.inner()).unwrap();
+        assert_eq!(
+            output,
+            "{\"ts\":1,\"s\":\"q\\\"\\n\",\"b\":\"aGkh\",\"a\":[[1.0,2.5],[null,4.0]],\"d\":null,\"n\":null}\n"
+        );
+    }
+}
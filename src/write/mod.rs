@@ -1,7 +1,13 @@
+pub mod arrow_sink;
+pub mod compressed_sink;
 pub mod csv_output_config;
 pub mod csv_sink;
 pub mod dc_sink;
 pub mod factory;
+pub mod json_output_config;
+pub mod json_sink;
+pub mod parquet_sink;
+pub mod partitioned_sink;
 pub mod vec_sink;
 
 #[cfg(test)]
@@ -0,0 +1,185 @@
+use std::io::Write;
+
+use arrow::datatypes::SchemaRef;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+
+use crate::chopper::error::ChopperResult;
+use crate::chopper::sink::{DataSink, DynHeaderSink};
+use crate::chopper::types::{Header, Row};
+use crate::util::arrow_util;
+
+const ROWS_PER_BATCH: usize = 8192;
+
+/// timestamps are written into a leading column of utc nanos timestamp type;
+/// flush happens once after the last row, so it writes the file footer; dropping the sink
+/// without flush writes the footer as well, but errors are lost then
+pub struct ParquetSink<W: 'static + Write + Send> {
+    header: Header,
+    rows: Vec<Row>,
+    /// held until the first batch, since tensor shapes are only known from the data
+    output: Option<W>,
+    writer: Option<(ArrowWriter<W>, SchemaRef)>,
+}
+
+impl<W: 'static + Write + Send> ParquetSink<W> {
+    pub fn new(output: W) -> Self {
+        ParquetSink {
+            header: Header::new(Vec::new(), Vec::new()),
+            rows: Vec::new(),
+            output: Some(output),
+            writer: None,
+        }
+    }
+
+    fn write_batch(&mut self) -> ChopperResult<()> {
+        if self.writer.is_none() {
+            let schema = arrow_util::schema_from_header(&self.header, &self.rows);
+            let props = WriterProperties::builder()
+                .set_compression(Compression::SNAPPY)
+                .build();
+            let output = self.output.take().unwrap();
+            let writer = ArrowWriter::try_new(output, schema.clone(), Some(props))?;
+            self.writer = Some((writer, schema));
+        }
+        if self.rows.is_empty() {
+            return Ok(());
+        }
+
+        let (writer, schema) = self.writer.as_mut().unwrap();
+        let batch = arrow_util::rows_to_record_batch(schema, &self.rows)?;
+        writer.write(&batch)?;
+        self.rows.clear();
+        Ok(())
+    }
+
+    fn close(&mut self) -> ChopperResult<()> {
+        if self.output.is_none() && self.writer.is_none() {
+            // already closed
+            return Ok(());
+        }
+        self.write_batch()?;
+        if let Some((mut writer, _)) = self.writer.take() {
+            writer.finish()?;
+            writer.inner_mut().flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: 'static + Write + Send> DynHeaderSink for ParquetSink<W> {
+    fn process_header(
+        mut self: Box<Self>,
+        header: &mut Header,
+    ) -> ChopperResult<Box<dyn DataSink>> {
+        self.header = header.clone();
+        Ok(self)
+    }
+}
+
+impl<W: 'static + Write + Send> DataSink for ParquetSink<W> {
+    fn write_row(&mut self, io_rows: &mut Vec<Row>) -> ChopperResult<()> {
+        self.rows.append(io_rows);
+        if self.rows.len() >= ROWS_PER_BATCH {
+            self.write_batch()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self, _io_rows: &mut Vec<Row>) -> ChopperResult<()> {
+        self.close()
+    }
+}
+
+impl<W: 'static + Write + Send> Drop for ParquetSink<W> {
+    fn drop(&mut self) {
+        // only if flush was never called; same as with BufWriter, there is no way to
+        // report errors from here
+        let _ = self.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs::File;
+    use std::io::BufWriter;
+
+    use crate::chopper::sink::DynHeaderSink;
+    use crate::chopper::types::{FieldType, FieldValue, Header, Row};
+    use crate::input::input_factory::InputFactoryBuilder;
+    use crate::source::columnar_input_config::ColumnarInputConfig;
+    use crate::source::parquet_source::ParquetSource;
+    use crate::source::source::Source;
+    use crate::write::parquet_sink::ParquetSink;
+
+    #[test]
+    fn test_round_trip() {
+        let mut header = Header::new(
+            vec!["px".to_string(), "sym".to_string()],
+            vec![FieldType::Double, FieldType::String],
+        );
+        let rows: Vec<Row> = (0..20_000u64)
+            .map(|i| Row {
+                timestamp: 1_600_000_000_000_000_000 + i,
+                field_values: vec![
+                    FieldValue::Double(i as f64 / 4.0),
+                    match i % 3 {
+                        0 => FieldValue::None,
+                        _ => FieldValue::String(format!("s{}", i % 7)),
+                    },
+                ],
+            })
+            .collect();
+
+        let path = env::temp_dir().join("chopper_parquet_sink_round_trip.parquet");
+        {
+            let output = BufWriter::new(File::create(&path).unwrap());
+            let sink = Box::new(ParquetSink::new(output));
+            let mut sink = sink.process_header(&mut header).unwrap();
+            for row in &rows {
+                sink.write_row(&mut vec![row.clone()]).unwrap();
+            }
+            sink.flush(&mut Vec::new()).unwrap();
+        }
+
+        let config = ColumnarInputConfig::new().hide_timestamp_column(true);
+        let mut source = ParquetSource::new(File::open(&path).unwrap(), &config).unwrap();
+        assert_eq!(source.header(), &header);
+        let mut actual: Vec<Row> = Vec::new();
+        while let Some(row) = source.next_row().unwrap() {
+            actual.push(row);
+        }
+        assert_eq!(actual, rows);
+
+        // file paths are read directly, without buffering the whole file
+        let mut input_factory = InputFactoryBuilder::new().build().unwrap();
+        let mut source = input_factory
+            .create_source_from_path(path.to_str().unwrap())
+            .unwrap();
+        let mut timestamps: Vec<u64> = Vec::new();
+        while let Some(row) = source.next_row().unwrap() {
+            timestamps.push(row.timestamp);
+        }
+        let expected: Vec<u64> = rows.iter().map(|row| row.timestamp).collect();
+        assert_eq!(timestamps, expected);
+
+        // same for files that are only recognized by their contents
+        let unnamed_path = path.with_extension("dat");
+        std::fs::copy(&path, &unnamed_path).unwrap();
+        let mut source = input_factory
+            .create_source_from_path(unnamed_path.to_str().unwrap())
+            .unwrap();
+        let mut row_count = 0;
+        while source.next_row().unwrap().is_some() {
+            row_count += 1;
+        }
+        assert_eq!(row_count, rows.len());
+        std::fs::remove_file(&unnamed_path).unwrap();
+
+        let input = File::open(&path).unwrap();
+        let source = ParquetSource::from_stream(input, &config).unwrap();
+        assert_eq!(source.header(), &header);
+    }
+}
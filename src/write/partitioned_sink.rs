@@ -0,0 +1,366 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use chrono::format::{Item, StrftimeItems};
+
+use crate::chopper::error::{ChopperResult, Error};
+use crate::chopper::sink::{DataSink, DynHeaderSink};
+use crate::chopper::types::{FieldValue, Header, Row};
+use crate::util::tz::ChopperTz;
+use crate::write::factory::OutputFactory;
+
+static DEFAULT_DATE_FORMAT: &str = "%Y%m%d";
+
+/// part of an output path template
+#[derive(Clone, Debug, PartialEq)]
+enum TemplatePart {
+    Text(String),
+    /// value of the named column; index is only known once the header is processed
+    Column {
+        name: String,
+        index: usize,
+    },
+    /// row timestamp formatted with the given strftime format
+    Date(String),
+}
+
+fn parse_template(template: &str) -> ChopperResult<Vec<TemplatePart>> {
+    let error = |msg: &str| {
+        Err(Error::from(format!(
+            "PartitionedSink -- {} in path template {}",
+            msg, template
+        )))
+    };
+    let mut parts: Vec<TemplatePart> = Vec::new();
+    let mut rest = template;
+    while !rest.is_empty() {
+        let start = match rest.find(['{', '}']) {
+            None => {
+                parts.push(TemplatePart::Text(rest.to_string()));
+                break;
+            }
+            Some(i) if rest[i..].starts_with('}') => return error("unmatched }"),
+            Some(i) => i,
+        };
+        if start > 0 {
+            parts.push(TemplatePart::Text(rest[..start].to_string()));
+        }
+        let end = match rest[start..].find('}') {
+            None => return error("unmatched {"),
+            Some(i) => start + i,
+        };
+        let placeholder = rest[start + 1..end].trim();
+        let part = if placeholder == "date" {
+            TemplatePart::Date(DEFAULT_DATE_FORMAT.to_string())
+        } else if let Some(format) = placeholder.strip_prefix("date:") {
+            if format.is_empty() || StrftimeItems::new(format).any(|item| item == Item::Error) {
+                return error(&format!("invalid date format {:?}", format));
+            }
+            TemplatePart::Date(format.to_string())
+        } else if placeholder.is_empty() || placeholder.contains('{') {
+            return error("empty or nested placeholder");
+        } else {
+            TemplatePart::Column {
+                name: placeholder.to_string(),
+                index: 0,
+            }
+        };
+        parts.push(part);
+        rest = &rest[end + 1..];
+    }
+    if parts
+        .iter()
+        .all(|part| matches!(part, TemplatePart::Text(_)))
+    {
+        return error("no placeholders");
+    }
+    Ok(parts)
+}
+
+/// value as it goes into a path; path separators are replaced, so that every value makes up
+/// at most one path component
+fn path_component(value: &FieldValue) -> String {
+    let value = match value {
+        FieldValue::Boolean(x) => x.to_string(),
+        FieldValue::Byte(x) => x.to_string(),
+        FieldValue::ByteBuf(_) | FieldValue::MultiDimDoubleArray(_) => "array".to_string(),
+        FieldValue::Char(x) => x.to_string(),
+        FieldValue::Double(x) => x.to_string(),
+        FieldValue::Float(x) => x.to_string(),
+        FieldValue::Int(x) => x.to_string(),
+        FieldValue::Long(x) => x.to_string(),
+        FieldValue::Short(x) => x.to_string(),
+        FieldValue::String(x) => x.replace(['/', '\\', '\0'], "_"),
+        FieldValue::None => "null".to_string(),
+    };
+    match value.as_str() {
+        "" | "." | ".." => "_".to_string(),
+        _ => value,
+    }
+}
+
+pub struct PartitionedSinkConfig {
+    template: Vec<TemplatePart>,
+    timezone: ChopperTz,
+    max_open_files: usize,
+    output_factory: OutputFactory,
+}
+
+pub struct PartitionedSink {
+    template: Vec<TemplatePart>,
+    timezone: ChopperTz,
+    max_open_files: usize,
+    output_factory: OutputFactory,
+    header: Header,
+    /// open partitions by path, with the time each of them was last written to
+    open_partitions: HashMap<String, (Box<dyn DataSink>, u64)>,
+    /// paths of partitions that were closed to stay within max_open_files
+    closed_partitions: HashSet<String>,
+    /// incremented on every row
+    clock: u64,
+}
+
+impl PartitionedSink {
+    /// writes rows into files whose paths are given by a template, e.g.
+    /// out/{sym}/{date:%Y%m%d}.csv, where {column} is replaced with the value of that column
+    /// and {date:format} with the row timestamp in given timezone, formatted as per strftime;
+    /// {date} is the same as {date:%Y%m%d}; files are created by output factory, so file type
+    /// and compression follow from the path; at most max_open_files files are open at the
+    /// same time and least recently used one is closed to open a new one; a closed file is
+    /// appended to when it gets more rows, which only works for uncompressed csv and json
+    /// lines, so for other file types, e.g. with date partitions, rows have to be in order
+    pub fn new(
+        template: &str,
+        timezone: ChopperTz,
+        max_open_files: usize,
+        output_factory: OutputFactory,
+    ) -> ChopperResult<Box<dyn DynHeaderSink>> {
+        if max_open_files == 0 {
+            return Err(Error::from(
+                "PartitionedSink -- max number of open files must be at least 1",
+            ));
+        }
+        let config = PartitionedSinkConfig {
+            template: parse_template(template)?,
+            timezone,
+            max_open_files,
+            output_factory,
+        };
+        Ok(Box::new(config) as Box<dyn DynHeaderSink>)
+    }
+
+    fn partition_path(&self, row: &Row) -> ChopperResult<String> {
+        let mut path = String::new();
+        for part in &self.template {
+            match part {
+                TemplatePart::Text(text) => path.push_str(text),
+                TemplatePart::Column { index, .. } => {
+                    path.push_str(&path_component(&row.field_values[*index]))
+                }
+                TemplatePart::Date(format) => {
+                    let date = self.timezone.timestamp(row.timestamp)?;
+                    write!(path, "{}", date.format(format)).unwrap();
+                }
+            }
+        }
+        Ok(path)
+    }
+
+    fn close_least_recently_used(&mut self) -> ChopperResult<()> {
+        let path = self
+            .open_partitions
+            .iter()
+            .min_by_key(|(_, (_, last_used))| *last_used)
+            .map(|(path, _)| path.clone())
+            .unwrap();
+        let (mut sink, _) = self.open_partitions.remove(&path).unwrap();
+        // flush also finishes compressed files, so that their errors are not lost on drop
+        sink.flush(&mut Vec::new())?;
+        // file gets closed when the sink is dropped
+        drop(sink);
+        self.closed_partitions.insert(path);
+        Ok(())
+    }
+
+    fn open_partition(&mut self, path: &str) -> ChopperResult<Box<dyn DataSink>> {
+        if self.open_partitions.len() >= self.max_open_files {
+            self.close_least_recently_used()?;
+        }
+        let header_sink = match self.closed_partitions.contains(path) {
+            true => {
+                if !self.output_factory.is_appendable(path) {
+                    return Err(Error::from(format!(
+                        "PartitionedSink -- {} was closed to keep at most {} files open and \
+                        its file type cannot be appended to; allow more open files",
+                        path, self.max_open_files
+                    )));
+                }
+                self.output_factory.new_appending_header_sink(path)?
+            }
+            false => {
+                if let Some(dir) = Path::new(path).parent() {
+                    if !dir.as_os_str().is_empty() {
+                        fs::create_dir_all(dir)?;
+                    }
+                }
+                self.output_factory.new_header_sink(Some(path))?
+            }
+        };
+        header_sink.process_header(&mut self.header.clone())
+    }
+}
+
+impl DynHeaderSink for PartitionedSinkConfig {
+    fn process_header(self: Box<Self>, header: &mut Header) -> ChopperResult<Box<dyn DataSink>> {
+        let mut template = self.template;
+        for part in &mut template {
+            if let TemplatePart::Column { name, index } = part {
+                *index = header.get_field_index(name)?;
+            }
+        }
+        Ok(Box::new(PartitionedSink {
+            template,
+            timezone: self.timezone,
+            max_open_files: self.max_open_files,
+            output_factory: self.output_factory,
+            header: header.clone(),
+            open_partitions: HashMap::new(),
+            closed_partitions: HashSet::new(),
+            clock: 0,
+        }))
+    }
+}
+
+impl DataSink for PartitionedSink {
+    fn write_row(&mut self, io_rows: &mut Vec<Row>) -> ChopperResult<()> {
+        let path = self.partition_path(io_rows.first().unwrap())?;
+        self.clock += 1;
+        if !self.open_partitions.contains_key(&path) {
+            let sink = self.open_partition(&path)?;
+            self.open_partitions.insert(path.clone(), (sink, 0));
+        }
+        let (sink, last_used) = self.open_partitions.get_mut(&path).unwrap();
+        *last_used = self.clock;
+        sink.write_row(io_rows)
+    }
+
+    /// files stay open until the sink is dropped
+    fn flush(&mut self, _io_rows: &mut Vec<Row>) -> ChopperResult<()> {
+        for (sink, _) in self.open_partitions.values_mut() {
+            sink.flush(&mut Vec::new())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Read;
+
+    use flate2::read::GzDecoder;
+
+    use crate::chopper::types::{FieldType, FieldValue, Header, Nanos, Row};
+    use crate::util::tz::ChopperTz;
+    use crate::write::factory::OutputFactory;
+    use crate::write::partitioned_sink::{parse_template, PartitionedSink, TemplatePart};
+
+    #[test]
+    fn test_parse_template() {
+        assert_eq!(
+            parse_template("out/{sym}/{date}.csv").unwrap(),
+            vec![
+                TemplatePart::Text("out/".to_string()),
+                TemplatePart::Column {
+                    name: "sym".to_string(),
+                    index: 0
+                },
+                TemplatePart::Text("/".to_string()),
+                TemplatePart::Date("%Y%m%d".to_string()),
+                TemplatePart::Text(".csv".to_string()),
+            ]
+        );
+        assert!(parse_template("out.csv").is_err());
+        assert!(parse_template("out/{sym.csv").is_err());
+        assert!(parse_template("out/sym}.csv").is_err());
+        assert!(parse_template("out/{}.csv").is_err());
+        assert!(parse_template("out/{date:%Q}.csv").is_err());
+    }
+
+    #[test]
+    fn test_partitioned_sink() {
+        let dir = std::env::temp_dir().join("chopper_test_partitioned_sink");
+        let _ = fs::remove_dir_all(&dir);
+        let template = format!("{}/{{sym}}/{{date:%H}}.csv", dir.to_str().unwrap());
+        let timezone = ChopperTz::new_from_str("UTC", None).unwrap();
+        // one open file at a time forces closing and appending
+        let sink = PartitionedSink::new(&template, timezone, 1, OutputFactory::new()).unwrap();
+        let mut header = Header::new(
+            vec!["sym".to_string(), "px".to_string()],
+            vec![FieldType::String, FieldType::Double],
+        );
+        let mut sink = sink.process_header(&mut header).unwrap();
+
+        let hour: Nanos = 3600 * 1_000_000_000;
+        for (timestamp, sym, px) in vec![
+            (0, "A", 1.0),
+            (1, "B/C", 2.0),
+            (2, "A", 3.0),
+            (hour, "A", 4.0),
+        ] {
+            let mut io_rows = vec![Row {
+                timestamp,
+                field_values: vec![FieldValue::String(sym.to_string()), FieldValue::Double(px)],
+            }];
+            sink.write_row(&mut io_rows).unwrap();
+        }
+        sink.flush(&mut Vec::new()).unwrap();
+        drop(sink);
+
+        let read = |path: &str| fs::read_to_string(dir.join(path)).unwrap();
+        assert_eq!(
+            read("A/00.csv"),
+            "timestampNanos,sym,px\n0,A,1.0\n2,A,3.0\n"
+        );
+        assert_eq!(read("B_C/00.csv"), "timestampNanos,sym,px\n1,B/C,2.0\n");
+        assert_eq!(
+            read("A/01.csv"),
+            format!("timestampNanos,sym,px\n{},A,4.0\n", hour)
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_compressed_partitions() {
+        let dir = std::env::temp_dir().join("chopper_test_compressed_partitions");
+        let _ = fs::remove_dir_all(&dir);
+        let template = format!("{}/{{sym}}.csv.gz", dir.to_str().unwrap());
+        let timezone = ChopperTz::new_from_str("UTC", None).unwrap();
+        let sink = PartitionedSink::new(&template, timezone, 1, OutputFactory::new()).unwrap();
+        let mut header = Header::new(vec!["sym".to_string()], vec![FieldType::String]);
+        let mut sink = sink.process_header(&mut header).unwrap();
+
+        let read = |path: &str| {
+            let mut output = String::new();
+            GzDecoder::new(fs::File::open(dir.join(path)).unwrap())
+                .read_to_string(&mut output)
+                .unwrap();
+            output
+        };
+        for (timestamp, sym) in vec![(0, "A"), (1, "B")] {
+            let mut io_rows = vec![Row {
+                timestamp,
+                field_values: vec![FieldValue::String(sym.to_string())],
+            }];
+            sink.write_row(&mut io_rows).unwrap();
+        }
+        // A was closed to open B, B is complete once flushed, both while the sink is alive
+        assert_eq!(read("A.csv.gz"), "timestampNanos,sym\n0,A\n");
+        sink.flush(&mut Vec::new()).unwrap();
+        assert_eq!(read("B.csv.gz"), "timestampNanos,sym\n1,B\n");
+        drop(sink);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
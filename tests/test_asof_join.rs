@@ -0,0 +1,84 @@
+use chrono_tz::America::New_York;
+
+use chopper::chopper::driver::ChopperDriver;
+use chopper::chopper::error::ChopperResult;
+use chopper::chopper::header_graph::{HeaderChain, HeaderGraph, HeaderNode};
+use chopper::chopper::types::{self, Header};
+use chopper::driver::asof_join::AsOfJoin;
+use chopper::driver::driver::Driver;
+use chopper::input::input_factory::InputFactoryBuilder;
+use chopper::source::csv_input_config::CSVInputConfig;
+use chopper::source::csv_timestamp_config::{
+    TimestampColConfig, TimestampConfig, TimestampFmtConfig,
+};
+use chopper::source::source::Source;
+use chopper::util::file::are_contents_same;
+use chopper::util::tz::ChopperTz;
+use chopper::write::factory::OutputFactory;
+
+#[test]
+fn test_asof_join() {
+    test().unwrap();
+    assert!(are_contents_same(
+        "./tests/output/test_asof_join.csv",
+        "./tests/reference/test_asof_join.csv"
+    )
+    .unwrap());
+}
+
+fn test() -> ChopperResult<()> {
+    setup_graph()?.drive()
+}
+
+fn setup_graph() -> ChopperResult<Box<dyn ChopperDriver>> {
+    let inputs = vec![
+        "./tests/input/asof_trades.csv",
+        "./tests/input/asof_quotes.csv",
+    ];
+    let output = "./tests/output/test_asof_join.csv";
+
+    // source reader and headers
+    let ts_config = TimestampConfig::new(
+        TimestampColConfig::Index(0),
+        TimestampFmtConfig::Auto,
+        ChopperTz::from(New_York),
+    );
+    let csv_input_config = CSVInputConfig::new(ts_config);
+    let mut input_factory = InputFactoryBuilder::new()
+        .with_csv_input_config(csv_input_config)
+        .build()?;
+    let mut sources: Vec<Box<dyn Source>> = Vec::new();
+    let mut headers: Vec<Header> = Vec::new();
+    for i in inputs {
+        let source = input_factory.create_source_from_path(i)?;
+        headers.push(source.header().clone());
+        sources.push(source);
+    }
+
+    // source chains 0 and 1
+    let chain_0 = HeaderChain::new(vec![HeaderNode::Merge(2)]);
+    let chain_1 = HeaderChain::new(vec![HeaderNode::Merge(2)]);
+
+    // as-of join/sink chain 2; quotes are joined to trades with the same symbol
+    // that are at most 5 seconds later
+    let join = AsOfJoin::new(
+        2,
+        Some("sym"),
+        Some(5_000_000_000),
+        vec!["quote_".to_string()],
+    )?;
+    let header_count_tracker = join.get_new_header_count_tracker();
+    let node_join = HeaderNode::MergeHeaderSink(join, header_count_tracker);
+    let header_sink = OutputFactory::new().new_header_sink(Some(output))?;
+    let node_output = HeaderNode::HeaderSink(header_sink);
+    let chain_2 = HeaderChain::new(vec![node_join, node_output]);
+
+    let graph = HeaderGraph::new(vec![chain_0, chain_1, chain_2]);
+
+    Ok(Box::new(Driver::new(
+        sources,
+        graph,
+        types::TIMESTAMP_RANGE_ALL,
+        headers,
+    )?))
+}
@@ -0,0 +1,56 @@
+use std::fs;
+
+use chrono_tz::America::New_York;
+
+use chopper::chopper::error::ChopperResult;
+use chopper::chopper::types;
+use chopper::input::input_factory::InputFactoryBuilder;
+use chopper::pipeline::config::PipelineConfig;
+use chopper::source::csv_input_config::CSVInputConfig;
+use chopper::source::csv_timestamp_config::{
+    TimestampColConfig, TimestampConfig, TimestampFmtConfig,
+};
+use chopper::util::file::are_contents_same;
+use chopper::util::tz::ChopperTz;
+use chopper::write::factory::OutputFactory;
+
+#[test]
+fn test_explain() {
+    let (text, dot) = dump_graph().unwrap();
+    fs::write("./tests/output/test_explain.txt", text).unwrap();
+    fs::write("./tests/output/test_explain.dot", dot).unwrap();
+    assert!(are_contents_same(
+        "./tests/output/test_explain.txt",
+        "./tests/reference/test_explain.txt",
+    )
+    .unwrap());
+    assert!(are_contents_same(
+        "./tests/output/test_explain.dot",
+        "./tests/reference/test_explain.dot",
+    )
+    .unwrap());
+}
+
+fn dump_graph() -> ChopperResult<(String, String)> {
+    let config = PipelineConfig::from_file("./tests/input/pipeline_asof_routing.toml")?;
+
+    let timezone = ChopperTz::from(New_York);
+    let ts_config = TimestampConfig::new(
+        TimestampColConfig::Index(0),
+        TimestampFmtConfig::Auto,
+        timezone.clone(),
+    );
+    let csv_input_config = CSVInputConfig::new(ts_config).with_type_inference(true, None);
+    let input_factory_builder = InputFactoryBuilder::new().with_csv_input_config(csv_input_config);
+
+    // dry run, so that the outputs of the pipeline are not touched
+    let output_factory = OutputFactory::new().with_dry_run(true);
+    let driver = config.build_driver(
+        input_factory_builder,
+        &output_factory,
+        &timezone,
+        types::TIMESTAMP_RANGE_ALL,
+    )?;
+    let dump = driver.dump();
+    Ok((dump.to_text(), dump.to_dot()))
+}
@@ -0,0 +1,85 @@
+use chopper::chopper::driver::ChopperDriver;
+use chopper::chopper::error::ChopperResult;
+use chopper::chopper::header_graph::{HeaderChain, HeaderGraph, HeaderNode};
+use chopper::chopper::types::{self, FieldType, FieldValue, Header, Row};
+use chopper::driver::driver::Driver;
+use chopper::driver::merge_join::MergeJoin;
+use chopper::source::iter_source::{IterSource, VecSource};
+use chopper::source::source::Source;
+use chopper::util::file::are_contents_same;
+use chopper::write::factory::OutputFactory;
+
+#[test]
+fn test_iter_source() {
+    test().unwrap();
+    assert!(are_contents_same(
+        "./tests/output/test_iter_source.csv",
+        "./tests/reference/test_iter_source.csv",
+    )
+    .unwrap());
+}
+
+fn test() -> ChopperResult<()> {
+    setup_graph()?.drive()
+}
+
+fn header() -> Header {
+    Header::new(
+        vec!["name".to_string(), "qty".to_string()],
+        vec![FieldType::String, FieldType::Long],
+    )
+}
+
+fn row(timestamp: u64, name: &str, qty: i64) -> Row {
+    Row {
+        timestamp,
+        field_values: vec![FieldValue::String(name.to_string()), FieldValue::Long(qty)],
+    }
+}
+
+fn setup_graph() -> ChopperResult<Box<dyn ChopperDriver>> {
+    let output = "./tests/output/test_iter_source.csv";
+
+    // in-memory sources in place of input files
+    let source_1 = VecSource::new(
+        header(),
+        vec![
+            row(1, "a", 10),
+            row(3, "b", 20),
+            row(5, "c", 30),
+            row(7, "d", 40),
+        ],
+    )
+    .filter_rows(|row| row.field_values[1] != FieldValue::Long(20));
+    let source_2 = IterSource::new(header(), (0..10).map(|i| row(i * 2, "x", i as i64)))
+        .map_rows(|mut row| {
+            if let FieldValue::Long(qty) = row.field_values[1] {
+                row.field_values[1] = FieldValue::Long(qty * 100);
+            }
+            row
+        })
+        .take_until(6);
+    let sources: Vec<Box<dyn Source>> = vec![Box::new(source_1), Box::new(source_2)];
+    let headers: Vec<Header> = sources.iter().map(|s| s.header().clone()).collect();
+
+    // source chains 0 and 1
+    let chain_0 = HeaderChain::new(vec![HeaderNode::Merge(2)]);
+    let chain_1 = HeaderChain::new(vec![HeaderNode::Merge(2)]);
+
+    // merge/sink chain 2
+    let merge = MergeJoin::new(2)?;
+    let header_count_tracker = merge.get_new_header_count_tracker();
+    let node_merge_sink = HeaderNode::MergeHeaderSink(merge, header_count_tracker);
+    let header_sink = OutputFactory::new().new_header_sink(Some(output))?;
+    let node_output = HeaderNode::HeaderSink(header_sink);
+    let chain_2 = HeaderChain::new(vec![node_merge_sink, node_output]);
+
+    let graph = HeaderGraph::new(vec![chain_0, chain_1, chain_2]);
+
+    Ok(Box::new(Driver::new(
+        sources,
+        graph,
+        types::TIMESTAMP_RANGE_ALL,
+        headers,
+    )?))
+}
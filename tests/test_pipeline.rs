@@ -0,0 +1,50 @@
+use chrono_tz::America::New_York;
+
+use chopper::chopper::driver::ChopperDriver;
+use chopper::chopper::error::ChopperResult;
+use chopper::chopper::types;
+use chopper::input::input_factory::InputFactoryBuilder;
+use chopper::pipeline::config::PipelineConfig;
+use chopper::source::csv_input_config::CSVInputConfig;
+use chopper::source::csv_timestamp_config::{
+    TimestampColConfig, TimestampConfig, TimestampFmtConfig,
+};
+use chopper::util::file::are_contents_same;
+use chopper::util::tz::ChopperTz;
+use chopper::write::factory::OutputFactory;
+
+#[test]
+fn test_pipeline() {
+    setup_graph().unwrap().drive().unwrap();
+    assert!(are_contents_same(
+        "./tests/output/test_pipeline_large.csv",
+        "./tests/reference/test_pipeline_large.csv",
+    )
+    .unwrap());
+    assert!(are_contents_same(
+        "./tests/output/test_pipeline_small.csv",
+        "./tests/reference/test_pipeline_small.csv",
+    )
+    .unwrap());
+}
+
+fn setup_graph() -> ChopperResult<Box<dyn ChopperDriver>> {
+    let config = PipelineConfig::from_file("./tests/input/pipeline_asof_routing.toml")?;
+
+    let timezone = ChopperTz::from(New_York);
+    let ts_config = TimestampConfig::new(
+        TimestampColConfig::Index(0),
+        TimestampFmtConfig::Auto,
+        timezone.clone(),
+    );
+    let csv_input_config = CSVInputConfig::new(ts_config).with_type_inference(true, None);
+    let input_factory_builder = InputFactoryBuilder::new().with_csv_input_config(csv_input_config);
+
+    let driver = config.build_driver(
+        input_factory_builder,
+        &OutputFactory::new(),
+        &timezone,
+        types::TIMESTAMP_RANGE_ALL,
+    )?;
+    Ok(Box::new(driver))
+}
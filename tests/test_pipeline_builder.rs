@@ -0,0 +1,54 @@
+use chrono_tz::America::New_York;
+
+use chopper::chopper::driver::ChopperDriver;
+use chopper::chopper::error::ChopperResult;
+use chopper::input::input_factory::InputFactoryBuilder;
+use chopper::pipeline::builder::Pipeline;
+use chopper::source::csv_input_config::CSVInputConfig;
+use chopper::source::csv_timestamp_config::{
+    TimestampColConfig, TimestampConfig, TimestampFmtConfig,
+};
+use chopper::util::file::are_contents_same;
+use chopper::util::tz::ChopperTz;
+
+#[test]
+fn test_pipeline_builder() {
+    setup_graph().unwrap().drive().unwrap();
+    assert!(are_contents_same(
+        "./tests/output/test_pipeline_builder_a.csv",
+        "./tests/reference/test_pipeline_builder_a.csv",
+    )
+    .unwrap());
+    assert!(are_contents_same(
+        "./tests/output/test_pipeline_builder.csv",
+        "./tests/reference/test_pipeline_builder.csv",
+    )
+    .unwrap());
+}
+
+fn setup_graph() -> ChopperResult<Box<dyn ChopperDriver>> {
+    let inputs = ["./tests/input/union_1.csv", "./tests/input/union_2.csv"];
+
+    let timezone = ChopperTz::from(New_York);
+    let ts_config = TimestampConfig::new(
+        TimestampColConfig::Index(0),
+        TimestampFmtConfig::Auto,
+        timezone.clone(),
+    );
+    let csv_input_config = CSVInputConfig::new(ts_config).with_type_inference(true, None);
+    let input_factory_builder = InputFactoryBuilder::new().with_csv_input_config(csv_input_config);
+
+    // inputs lose their time column, get merged, and rows of sym A also go to their own file
+    Pipeline::from_paths(&inputs)
+        .with_input_factory_builder(input_factory_builder)
+        .with_timezone(timezone)
+        .drop_columns("time")
+        .merge_union(Some("source"))
+        .split(|a| {
+            a.filter("sym == 'A'")
+                .columns("sym,px")
+                .to_path("./tests/output/test_pipeline_builder_a.csv")
+        })
+        .to_path("./tests/output/test_pipeline_builder.csv")
+        .build()
+}
@@ -0,0 +1,79 @@
+use chrono_tz::America::New_York;
+
+use chopper::chopper::driver::ChopperDriver;
+use chopper::chopper::error::ChopperResult;
+use chopper::chopper::header_graph::{HeaderChain, HeaderGraph, HeaderNode};
+use chopper::chopper::types::{self, Header};
+use chopper::driver::{driver::Driver, split::RoutingSplit};
+use chopper::input::input_factory::InputFactoryBuilder;
+use chopper::source::csv_input_config::CSVInputConfig;
+use chopper::source::csv_timestamp_config::{
+    TimestampColConfig, TimestampConfig, TimestampFmtConfig,
+};
+use chopper::source::source::Source;
+use chopper::util::file::are_contents_same;
+use chopper::util::tz::ChopperTz;
+use chopper::write::factory::OutputFactory;
+
+#[test]
+fn test_routing_split() {
+    setup_graph().unwrap().drive().unwrap();
+    assert!(are_contents_same(
+        "./tests/output/test_routing_split_1.csv",
+        "./tests/reference/test_routing_split_1.csv",
+    )
+    .unwrap());
+    assert!(are_contents_same(
+        "./tests/output/test_routing_split_2.csv",
+        "./tests/reference/test_routing_split_2.csv",
+    )
+    .unwrap());
+}
+
+fn setup_graph() -> ChopperResult<Box<dyn ChopperDriver>> {
+    let inputs = vec!["./tests/input/asof_trades.csv"];
+    let output_1 = Some("./tests/output/test_routing_split_1.csv");
+    let output_2 = Some("./tests/output/test_routing_split_2.csv");
+
+    // source reader and headers
+    let ts_config = TimestampConfig::new(
+        TimestampColConfig::Index(0),
+        TimestampFmtConfig::Auto,
+        ChopperTz::from(New_York),
+    );
+    let csv_input_config = CSVInputConfig::new(ts_config).with_type_inference(true, None);
+    let mut input_factory = InputFactoryBuilder::new()
+        .with_csv_input_config(csv_input_config)
+        .build()?;
+    let mut sources: Vec<Box<dyn Source>> = Vec::new();
+    let mut headers: Vec<Header> = Vec::new();
+    for i in inputs {
+        let source = input_factory.create_source_from_path(i)?;
+        headers.push(source.header().clone());
+        sources.push(source);
+    }
+
+    // split chain 0; large trades go to chain 1 and everything else to chain 2
+    let split = RoutingSplit::new_predicates(vec![("qty >= 100", 1)], Some(2))?;
+    let header_count_tracker = split.get_new_header_count_tracker();
+    let node_split_sink = HeaderNode::SplitHeaderSink(split, header_count_tracker);
+    let chain_0 = HeaderChain::new(vec![node_split_sink]);
+
+    let output_factory = OutputFactory::new();
+
+    // sink chain 1
+    let header_sink_1 = output_factory.new_header_sink(output_1)?;
+    let chain_1 = HeaderChain::new(vec![HeaderNode::HeaderSink(header_sink_1)]);
+
+    // sink chain 2
+    let header_sink_2 = output_factory.new_header_sink(output_2)?;
+    let chain_2 = HeaderChain::new(vec![HeaderNode::HeaderSink(header_sink_2)]);
+
+    let graph = HeaderGraph::new(vec![chain_0, chain_1, chain_2]);
+    Ok(Box::new(Driver::new(
+        sources,
+        graph,
+        types::TIMESTAMP_RANGE_ALL,
+        headers,
+    )?))
+}
@@ -0,0 +1,84 @@
+use chrono_tz::America::New_York;
+
+use chopper::chopper::driver::ChopperDriver;
+use chopper::chopper::error::ChopperResult;
+use chopper::chopper::header_graph::{HeaderChain, HeaderGraph, HeaderNode};
+use chopper::chopper::types::{self, Header};
+use chopper::driver::driver::Driver;
+use chopper::driver::merge_join::MergeJoin;
+use chopper::filter::column_tag::{ColumnTag, TagValue};
+use chopper::input::input::{Input, InputFormat, InputType};
+use chopper::input::input_factory::InputFactoryBuilder;
+use chopper::source::csv_input_config::CSVInputConfig;
+use chopper::source::csv_timestamp_config::{
+    TimestampColConfig, TimestampConfig, TimestampFmtConfig,
+};
+use chopper::source::source::Source;
+use chopper::util::file::are_contents_same;
+use chopper::util::tz::ChopperTz;
+use chopper::write::factory::OutputFactory;
+
+#[test]
+fn test_tag_source() {
+    test().unwrap();
+    assert!(are_contents_same(
+        "./tests/output/test_tag_source.csv",
+        "./tests/reference/test_tag_source.csv"
+    )
+    .unwrap());
+}
+
+fn test() -> ChopperResult<()> {
+    setup_graph()?.drive()
+}
+
+fn setup_graph() -> ChopperResult<Box<dyn ChopperDriver>> {
+    let inputs = vec!["./tests/input/union_1.csv", "./tests/input/asof_trades.csv"];
+    let output = "./tests/output/test_tag_source.csv";
+
+    // source reader and headers
+    let ts_config = TimestampConfig::new(
+        TimestampColConfig::Index(0),
+        TimestampFmtConfig::Auto,
+        ChopperTz::from(New_York),
+    );
+    let csv_input_config = CSVInputConfig::new(ts_config);
+    let mut input_factory = InputFactoryBuilder::new()
+        .with_csv_input_config(csv_input_config)
+        .build()?;
+    let inputs: Vec<Input> = inputs
+        .into_iter()
+        .map(|path| Input {
+            input: InputType::Path(path.to_string()),
+            format: InputFormat::Auto,
+        })
+        .collect();
+    let mut sources: Vec<Box<dyn Source>> = Vec::new();
+    let mut headers: Vec<Header> = Vec::new();
+    let mut chains: Vec<HeaderChain> = Vec::new();
+    // source chains 0 and 1, where each input is tagged with its file stem before the merge
+    for (input, tag) in inputs.iter().zip(TagValue::Stem.values(&inputs)?) {
+        let source = input_factory.create_source_from_input(input)?;
+        headers.push(source.header().clone());
+        sources.push(source);
+        let node_tag = HeaderNode::HeaderSink(ColumnTag::new("host", &tag));
+        chains.push(HeaderChain::new(vec![node_tag, HeaderNode::Merge(2)]));
+    }
+
+    // merge/sink chain 2
+    let join = MergeJoin::new(2)?;
+    let header_count_tracker = join.get_new_header_count_tracker();
+    let node_join = HeaderNode::MergeHeaderSink(join, header_count_tracker);
+    let header_sink = OutputFactory::new().new_header_sink(Some(output))?;
+    let node_output = HeaderNode::HeaderSink(header_sink);
+    chains.push(HeaderChain::new(vec![node_join, node_output]));
+
+    let graph = HeaderGraph::new(chains);
+
+    Ok(Box::new(Driver::new(
+        sources,
+        graph,
+        types::TIMESTAMP_RANGE_ALL,
+        headers,
+    )?))
+}
@@ -0,0 +1,93 @@
+use chrono_tz::America::New_York;
+
+use chopper::chopper::driver::ChopperDriver;
+use chopper::chopper::error::ChopperResult;
+use chopper::chopper::header_graph::{HeaderChain, HeaderGraph, HeaderNode};
+use chopper::chopper::types::{self, Header};
+use chopper::driver::driver::Driver;
+use chopper::driver::merge_join::MergeJoin;
+use chopper::driver::tie_break::TieBreakPolicy;
+use chopper::input::input_factory::InputFactoryBuilder;
+use chopper::source::csv_input_config::CSVInputConfig;
+use chopper::source::csv_timestamp_config::{
+    TimestampColConfig, TimestampConfig, TimestampFmtConfig,
+};
+use chopper::source::source::Source;
+use chopper::util::file::are_contents_same;
+use chopper::util::tz::ChopperTz;
+use chopper::write::factory::OutputFactory;
+
+#[test]
+fn test_tie_break_input_order() {
+    test(TieBreakPolicy::InputOrder, "input_order").unwrap();
+}
+
+#[test]
+fn test_tie_break_reverse_input_order() {
+    test(TieBreakPolicy::ReverseInputOrder, "reverse_input_order").unwrap();
+}
+
+#[test]
+fn test_tie_break_round_robin() {
+    test(TieBreakPolicy::RoundRobin, "round_robin").unwrap();
+}
+
+#[test]
+fn test_tie_break_column() {
+    test(TieBreakPolicy::Column("rank".to_string()), "column").unwrap();
+}
+
+fn test(tie_break_policy: TieBreakPolicy, name: &str) -> ChopperResult<()> {
+    let output = format!("./tests/output/test_tie_break_{}.csv", name);
+    let reference = format!("./tests/reference/test_tie_break_{}.csv", name);
+    setup_graph(tie_break_policy, &output)?.drive()?;
+    assert!(are_contents_same(&output, &reference)?);
+    Ok(())
+}
+
+fn setup_graph(
+    tie_break_policy: TieBreakPolicy,
+    output: &str,
+) -> ChopperResult<Box<dyn ChopperDriver>> {
+    let inputs = vec![
+        "./tests/input/tie_break_1.csv",
+        "./tests/input/tie_break_2.csv",
+    ];
+
+    // source reader and headers
+    let ts_config = TimestampConfig::new(
+        TimestampColConfig::Index(0),
+        TimestampFmtConfig::Auto,
+        ChopperTz::from(New_York),
+    );
+    let csv_input_config = CSVInputConfig::new(ts_config);
+    let mut input_factory = InputFactoryBuilder::new()
+        .with_csv_input_config(csv_input_config)
+        .build()?;
+    let mut sources: Vec<Box<dyn Source>> = Vec::new();
+    let mut headers: Vec<Header> = Vec::new();
+    for i in inputs {
+        let source = input_factory.create_source_from_path(i)?;
+        headers.push(source.header().clone());
+        sources.push(source);
+    }
+
+    // source chains 0 and 1
+    let chain_0 = HeaderChain::new(vec![HeaderNode::Merge(2)]);
+    let chain_1 = HeaderChain::new(vec![HeaderNode::Merge(2)]);
+
+    // merge/sink chain 2
+    let merge = MergeJoin::new(2)?;
+    let header_count_tracker = merge.get_new_header_count_tracker();
+    let node_merge_sink = HeaderNode::MergeHeaderSink(merge, header_count_tracker);
+    let header_sink = OutputFactory::new().new_header_sink(Some(output))?;
+    let node_output = HeaderNode::HeaderSink(header_sink);
+    let chain_2 = HeaderChain::new(vec![node_merge_sink, node_output]);
+
+    let graph = HeaderGraph::new(vec![chain_0, chain_1, chain_2]);
+
+    Ok(Box::new(
+        Driver::new(sources, graph, types::TIMESTAMP_RANGE_ALL, headers)?
+            .with_tie_break_policy(tie_break_policy),
+    ))
+}